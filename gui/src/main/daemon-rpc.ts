@@ -229,6 +229,12 @@ const accountDataSchema = partialObject({
   expiry: string,
 });
 
+const accountExpirySchema = partialObject({
+  expiry: string,
+  seconds_left: number,
+  status: enumeration('active', 'three_days_left', 'one_day_left', 'expired'),
+});
+
 const voucherResponseSchema = partialObject({
   new_expiry: string,
 });
@@ -254,6 +260,7 @@ const tunnelStateSchema = oneOf(
         ),
       }),
       location: maybe(locationSchema),
+      connected_since: maybe(string),
     }),
   }),
   object({
@@ -269,6 +276,7 @@ const tunnelStateSchema = oneOf(
             'start_tunnel_error',
             'is_offline',
             'tap_adapter_problem',
+            'connect_timeout',
           ),
         }),
         object({
@@ -287,6 +295,12 @@ const tunnelStateSchema = oneOf(
       ),
     }),
   }),
+  object({
+    state: enumeration('disconnected'),
+    details: object({
+      blocking: boolean,
+    }),
+  }),
   object({
     state: enumeration('connected', 'connecting', 'disconnected'),
   }),
@@ -379,6 +393,12 @@ const daemonEventSchema = oneOf(
   object({
     app_version_info: appVersionInfoSchema,
   }),
+  object({
+    location: locationSchema,
+  }),
+  object({
+    account_expiry: accountExpirySchema,
+  }),
 );
 
 export class ResponseParseError extends Error {
@@ -567,6 +567,8 @@ class ApplicationMain {
           this.handleWireguardKeygenEvent(daemonEvent.wireguardKey);
         } else if ('appVersionInfo' in daemonEvent) {
           this.setLatestVersion(daemonEvent.appVersionInfo);
+        } else if ('location' in daemonEvent) {
+          this.handleLocationEvent(daemonEvent.location);
         }
       },
       (error: Error) => {
@@ -808,6 +810,15 @@ class ApplicationMain {
     }
   }
 
+  private handleLocationEvent(location: ILocation) {
+    // Note: hostname is only set for relay servers.
+    if (location.hostname === null) {
+      this.lastDisconnectedLocation = location;
+    }
+
+    this.setLocation(location);
+  }
+
   private async updateLocation() {
     const tunnelState = this.tunnelState;
 
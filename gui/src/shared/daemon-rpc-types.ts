@@ -1,6 +1,15 @@
 export interface IAccountData {
   expiry: string;
 }
+
+export type AccountExpiryStatus = 'active' | 'three_days_left' | 'one_day_left' | 'expired';
+
+export interface IAccountExpiry {
+  expiry: string;
+  secondsLeft: number;
+  status: AccountExpiryStatus;
+}
+
 export type AccountToken = string;
 export type Ip = string;
 export interface ILocation {
@@ -29,7 +38,8 @@ export type ErrorStateCause =
         | 'set_dns_error'
         | 'start_tunnel_error'
         | 'is_offline'
-        | 'tap_adapter_problem';
+        | 'tap_adapter_problem'
+        | 'connect_timeout';
     }
   | { reason: 'tunnel_parameter_error'; details: TunnelParameterError }
   | { reason: 'auth_failed'; details?: string };
@@ -87,15 +97,18 @@ export type DaemonEvent =
   | { settings: ISettings }
   | { relayList: IRelayList }
   | { wireguardKey: KeygenEvent }
-  | { appVersionInfo: IAppVersionInfo };
+  | { appVersionInfo: IAppVersionInfo }
+  | { location: ILocation }
+  | { accountExpiry: IAccountExpiry };
 
 export interface ITunnelStateRelayInfo {
   endpoint: ITunnelEndpoint;
   location?: ILocation;
+  connectedSince?: string;
 }
 
 export type TunnelState =
-  | { state: 'disconnected' }
+  | { state: 'disconnected'; details?: { blocking: boolean } }
   | { state: 'connecting'; details?: ITunnelStateRelayInfo }
   | { state: 'connected'; details: ITunnelStateRelayInfo }
   | { state: 'disconnecting'; details: AfterDisconnect }
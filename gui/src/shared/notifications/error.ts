@@ -124,6 +124,11 @@ function getInAppNotificationSubtitle(tunnelState: { state: 'error'; details: IE
           'in-app-notifications',
           "Unable to detect a working TAP adapter on this device. If you've disabled it, enable it again. Otherwise, please reinstall the app",
         );
+      case 'connect_timeout':
+        return messages.pgettext(
+          'in-app-notifications',
+          'Failed to connect within the configured timeout',
+        );
     }
   }
 }
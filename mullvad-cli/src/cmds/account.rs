@@ -56,7 +56,7 @@ impl Command for Account {
         } else if let Some(_matches) = matches.subcommand_matches("get") {
             self.get()
         } else if let Some(_matches) = matches.subcommand_matches("unset") {
-            self.set(None)
+            self.logout()
         } else if let Some(_matches) = matches.subcommand_matches("clear-history") {
             self.clear_history()
         } else if let Some(_matches) = matches.subcommand_matches("create") {
@@ -82,6 +82,13 @@ impl Account {
         Ok(())
     }
 
+    fn logout(&self) -> Result<()> {
+        let mut rpc = new_rpc_client()?;
+        rpc.logout()?;
+        println!("Mullvad account removed");
+        Ok(())
+    }
+
     fn get(&self) -> Result<()> {
         let mut rpc = new_rpc_client()?;
         let settings = rpc.get_settings()?;
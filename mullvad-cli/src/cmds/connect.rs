@@ -1,4 +1,4 @@
-use crate::{new_rpc_client, Command, Result};
+use crate::{location, new_rpc_client, Command, Error, Result};
 use talpid_types::ErrorExt;
 
 pub struct Connect;
@@ -9,14 +9,37 @@ impl Command for Connect {
     }
 
     fn clap_subcommand(&self) -> clap::App<'static, 'static> {
-        clap::SubCommand::with_name(self.name())
-            .about("Command the client to start establishing a VPN tunnel")
+        location::with_location_args(
+            clap::SubCommand::with_name(self.name()).about(
+                "Command the client to start establishing a VPN tunnel. If a location is given, \
+                 the relay selection is restricted to it first",
+            ),
+            false,
+        )
+        .arg(
+            clap::Arg::with_name("wait")
+                .long("wait")
+                .short("w")
+                .help("Wait until the tunnel is connected, and fail if it can't be"),
+        )
     }
 
-    fn run(&self, _matches: &clap::ArgMatches<'_>) -> Result<()> {
+    fn run(&self, matches: &clap::ArgMatches<'_>) -> Result<()> {
         let mut rpc = new_rpc_client()?;
-        if let Err(e) = rpc.connect() {
-            eprintln!("{}", e.display_chain());
+        let wait = matches.is_present("wait");
+        let result = if matches.value_of("country").is_some() {
+            rpc.connect_to(location::get_constraint(matches))
+                .and_then(|_| if wait { rpc.connect_and_wait() } else { Ok(()) })
+        } else if wait {
+            rpc.connect_and_wait()
+        } else {
+            rpc.connect()
+        };
+        match result {
+            // Scripts waiting for the tunnel need a failing exit code.
+            Err(e) if wait => return Err(Error::RpcClientError(e)),
+            Err(e) => eprintln!("{}", e.display_chain()),
+            Ok(()) => (),
         }
         Ok(())
     }
@@ -1,7 +1,9 @@
 use crate::{new_rpc_client, Command, Error, Result};
 use futures::{Future, Stream};
 use mullvad_ipc_client::DaemonRpcClient;
-use mullvad_types::{auth_failed::AuthFailed, states::TunnelState, DaemonEvent};
+use mullvad_types::{
+    auth_failed::AuthFailed, location::GeoIpLocation, states::TunnelState, DaemonEvent,
+};
 use talpid_types::tunnel::{ErrorState, ErrorStateCause};
 
 pub struct Status;
@@ -50,14 +52,15 @@ impl Command for Status {
                 match event? {
                     DaemonEvent::TunnelState(new_state) => {
                         print_state(&new_state);
-                        use self::TunnelState::*;
-                        match new_state {
-                            Connected { .. } | Disconnected => {
-                                if matches.is_present("location") {
-                                    print_location(&mut rpc)?;
-                                }
-                            }
-                            _ => {}
+                    }
+                    DaemonEvent::Location(location) => {
+                        if matches.is_present("location") {
+                            print_geoip_location(location);
+                        }
+                    }
+                    DaemonEvent::AccountExpiry(account_expiry) => {
+                        if verbose {
+                            println!("New account expiry: {:#?}", account_expiry);
                         }
                     }
                     DaemonEvent::Settings(settings) => {
@@ -96,7 +99,8 @@ fn print_state(state: &TunnelState) {
             println!("Connected to {}", endpoint);
         }
         Connecting { endpoint, .. } => println!("Connecting to {}...", endpoint),
-        Disconnected => println!("Disconnected"),
+        Disconnected { blocking: false } => println!("Disconnected"),
+        Disconnected { blocking: true } => println!("Disconnected, blocking all traffic"),
         Disconnecting(_) => println!("Disconnecting..."),
     }
 }
@@ -129,13 +133,14 @@ fn print_blocked_reason(reason: &ErrorStateCause) {
 }
 
 fn print_location(rpc: &mut DaemonRpcClient) -> Result<()> {
-    let location = match rpc.get_current_location()? {
-        Some(loc) => loc,
-        None => {
-            println!("Location data unavailable");
-            return Ok(());
-        }
-    };
+    match rpc.get_current_location()? {
+        Some(location) => print_geoip_location(location),
+        None => println!("Location data unavailable"),
+    }
+    Ok(())
+}
+
+fn print_geoip_location(location: GeoIpLocation) {
     if let Some(hostname) = location.hostname {
         println!("Relay: {}", hostname);
     }
@@ -156,5 +161,4 @@ fn print_location(rpc: &mut DaemonRpcClient) -> Result<()> {
         "Position: {:.5}°N, {:.5}°W",
         location.latitude, location.longitude
     );
-    Ok(())
 }
@@ -2,6 +2,7 @@ use crate::{new_rpc_client, Command, Result};
 use clap::value_t;
 
 use mullvad_types::settings::TunnelOptions;
+use talpid_types::net::openvpn::OPENVPN_CIPHERS;
 
 pub struct Tunnel;
 
@@ -76,6 +77,7 @@ fn create_openvpn_subcommand() -> clap::App<'static, 'static> {
         .about("Manage options for OpenVPN tunnels")
         .setting(clap::AppSettings::SubcommandRequiredElseHelp)
         .subcommand(create_openvpn_mssfix_subcommand())
+        .subcommand(create_openvpn_cipher_subcommand())
 }
 
 fn create_openvpn_mssfix_subcommand() -> clap::App<'static, 'static> {
@@ -89,6 +91,21 @@ fn create_openvpn_mssfix_subcommand() -> clap::App<'static, 'static> {
         )
 }
 
+fn create_openvpn_cipher_subcommand() -> clap::App<'static, 'static> {
+    clap::SubCommand::with_name("cipher")
+        .about("Configure the data channel cipher")
+        .setting(clap::AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(clap::SubCommand::with_name("get"))
+        .subcommand(clap::SubCommand::with_name("unset"))
+        .subcommand(
+            clap::SubCommand::with_name("set").arg(
+                clap::Arg::with_name("cipher")
+                    .required(true)
+                    .possible_values(OPENVPN_CIPHERS),
+            ),
+        )
+}
+
 fn create_ipv6_subcommand() -> clap::App<'static, 'static> {
     clap::SubCommand::with_name("ipv6")
         .setting(clap::AppSettings::SubcommandRequiredElseHelp)
@@ -107,6 +124,16 @@ impl Tunnel {
     fn handle_openvpn_cmd(matches: &clap::ArgMatches<'_>) -> Result<()> {
         match matches.subcommand() {
             ("mssfix", Some(mssfix_matches)) => Self::handle_openvpn_mssfix_cmd(mssfix_matches),
+            ("cipher", Some(cipher_matches)) => Self::handle_openvpn_cipher_cmd(cipher_matches),
+            _ => unreachable!("unhandled command"),
+        }
+    }
+
+    fn handle_openvpn_cipher_cmd(matches: &clap::ArgMatches<'_>) -> Result<()> {
+        match matches.subcommand() {
+            ("get", Some(_)) => Self::process_openvpn_cipher_get(),
+            ("unset", Some(_)) => Self::process_openvpn_cipher_unset(),
+            ("set", Some(set_matches)) => Self::process_openvpn_cipher_set(set_matches),
             _ => unreachable!("unhandled command"),
         }
     }
@@ -276,6 +303,33 @@ impl Tunnel {
         Ok(())
     }
 
+    fn process_openvpn_cipher_get() -> Result<()> {
+        let tunnel_options = Self::get_tunnel_options()?;
+        println!(
+            "cipher: {}",
+            tunnel_options
+                .openvpn
+                .cipher
+                .unwrap_or_else(|| "default".to_owned())
+        );
+        Ok(())
+    }
+
+    fn process_openvpn_cipher_unset() -> Result<()> {
+        let mut rpc = new_rpc_client()?;
+        rpc.set_openvpn_cipher(None)?;
+        println!("cipher has been unset");
+        Ok(())
+    }
+
+    fn process_openvpn_cipher_set(matches: &clap::ArgMatches<'_>) -> Result<()> {
+        let cipher = matches.value_of("cipher").unwrap().to_owned();
+        let mut rpc = new_rpc_client()?;
+        rpc.set_openvpn_cipher(Some(cipher))?;
+        println!("cipher has been updated");
+        Ok(())
+    }
+
     fn process_ipv6_get() -> Result<()> {
         let tunnel_options = Self::get_tunnel_options()?;
         println!(
@@ -1,11 +1,19 @@
 use mullvad_types::relay_constraints::{Constraint, LocationConstraint};
 
 pub fn get_subcommand() -> clap::App<'static, 'static> {
-    clap::SubCommand::with_name("location")
+    with_location_args(clap::SubCommand::with_name("location"), true)
+}
+
+/// Adds the country, city and hostname arguments read by `get_constraint` to the given command.
+pub fn with_location_args(
+    command: clap::App<'static, 'static>,
+    country_required: bool,
+) -> clap::App<'static, 'static> {
+    command
         .arg(
             clap::Arg::with_name("country")
                 .help("The two letter country code, or 'any' for no preference.")
-                .required(true)
+                .required(country_required)
                 .index(1)
                 .validator(country_code_validator),
         )
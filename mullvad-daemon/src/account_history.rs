@@ -174,13 +174,7 @@ impl AccountHistory {
 
     /// Always inserts a new entry at the start of the list
     pub fn insert(&mut self, new_entry: AccountEntry) -> Result<()> {
-        self.accounts
-            .retain(|entry| entry.account != new_entry.account);
-
-        self.accounts.push_front(new_entry);
-
-        if self.accounts.len() > ACCOUNT_HISTORY_LIMIT {
-            let last_entry = self.accounts.pop_back().unwrap();
+        if let Some(last_entry) = insert_entry(&mut self.accounts, new_entry) {
             if let Some(wg_data) = last_entry.wireguard {
                 self.rpc_handle
                     .service()
@@ -201,8 +195,7 @@ impl AccountHistory {
 
     /// Remove account data
     pub fn remove_account(&mut self, account: &str) -> Result<()> {
-        let entry = self.get(&String::from(account))?;
-        let entry = match entry {
+        let entry = match remove_entry(&mut self.accounts, account) {
             Some(entry) => entry,
             None => return Ok(()),
         };
@@ -213,7 +206,6 @@ impl AccountHistory {
                 .spawn(self.create_remove_wg_key_rpc(account, &wg_data))
         }
 
-        let _ = self.accounts.pop_front();
         self.save_to_disk()
     }
 
@@ -259,8 +251,82 @@ impl AccountHistory {
     }
 }
 
+/// Inserts `new_entry` at the start of `accounts`, replacing any existing entry for the same
+/// account. Returns the oldest entry if it was pushed out of the history.
+fn insert_entry(
+    accounts: &mut VecDeque<AccountEntry>,
+    new_entry: AccountEntry,
+) -> Option<AccountEntry> {
+    accounts.retain(|entry| entry.account != new_entry.account);
+    accounts.push_front(new_entry);
+    if accounts.len() > ACCOUNT_HISTORY_LIMIT {
+        accounts.pop_back()
+    } else {
+        None
+    }
+}
+
+/// Removes the entry for `account` from `accounts` and returns it, if there was one.
+fn remove_entry(accounts: &mut VecDeque<AccountEntry>, account: &str) -> Option<AccountEntry> {
+    let idx = accounts.iter().position(|entry| entry.account == account)?;
+    accounts.remove(idx)
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct AccountEntry {
     pub account: AccountToken,
     pub wireguard: Option<WireguardData>,
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entry(account: &str) -> AccountEntry {
+        AccountEntry {
+            account: account.to_owned(),
+            wireguard: None,
+        }
+    }
+
+    fn tokens(accounts: &VecDeque<AccountEntry>) -> Vec<&str> {
+        accounts
+            .iter()
+            .map(|entry| entry.account.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_history_is_capped() {
+        let mut accounts = VecDeque::new();
+        for account in &["1111", "2222", "3333"] {
+            assert!(insert_entry(&mut accounts, entry(account)).is_none());
+        }
+        assert_eq!(tokens(&accounts), ["3333", "2222", "1111"]);
+
+        // Using an account again moves it to the front without evicting anything
+        assert!(insert_entry(&mut accounts, entry("1111")).is_none());
+        assert_eq!(tokens(&accounts), ["1111", "3333", "2222"]);
+
+        let evicted = insert_entry(&mut accounts, entry("4444")).unwrap();
+        assert_eq!(evicted.account, "2222");
+        assert_eq!(accounts.len(), ACCOUNT_HISTORY_LIMIT);
+        assert_eq!(tokens(&accounts), ["4444", "1111", "3333"]);
+    }
+
+    #[test]
+    fn test_remove_from_history() {
+        let mut accounts = VecDeque::new();
+        for account in &["1111", "2222", "3333"] {
+            insert_entry(&mut accounts, entry(account));
+        }
+
+        assert_eq!(remove_entry(&mut accounts, "2222").unwrap().account, "2222");
+        assert_eq!(tokens(&accounts), ["3333", "1111"]);
+        assert!(remove_entry(&mut accounts, "2222").is_none());
+
+        // Removing an account doesn't reorder the remaining ones
+        remove_entry(&mut accounts, "1111");
+        assert_eq!(tokens(&accounts), ["3333"]);
+    }
+}
@@ -1,5 +1,6 @@
 use clap::{crate_authors, crate_description, crate_name, App, Arg};
 use log;
+use std::net::SocketAddr;
 
 use crate::version;
 
@@ -9,6 +10,7 @@ pub struct Config {
     pub log_stdout_timestamps: bool,
     pub run_as_service: bool,
     pub register_service: bool,
+    pub metrics_address: Option<SocketAddr>,
 }
 
 pub fn get_config() -> &'static Config {
@@ -32,6 +34,9 @@ pub fn create_config() -> Config {
 
     let run_as_service = cfg!(windows) && matches.is_present("run_as_service");
     let register_service = cfg!(windows) && matches.is_present("register_service");
+    let metrics_address = matches
+        .value_of("metrics_address")
+        .map(|address| address.parse().expect("Invalid metrics address"));
 
     Config {
         log_level,
@@ -39,6 +44,7 @@ pub fn create_config() -> Config {
         log_stdout_timestamps,
         run_as_service,
         register_service,
+        metrics_address,
     }
 }
 
@@ -83,6 +89,18 @@ fn create_app() -> App<'static, 'static> {
             Arg::with_name("disable_stdout_timestamps")
                 .long("disable-stdout-timestamps")
                 .help("Don't log timestamps when logging to stdout, useful when running as a systemd service")
+        )
+        .arg(
+            Arg::with_name("metrics_address")
+                .long("metrics-address")
+                .takes_value(true)
+                .value_name("ADDRESS")
+                .validator(|address| match address.parse::<SocketAddr>() {
+                    Ok(address) if address.ip().is_loopback() => Ok(()),
+                    Ok(_) => Err("Metrics can only be served on a loopback address".to_owned()),
+                    Err(error) => Err(error.to_string()),
+                })
+                .help("Serve Prometheus metrics over HTTP on this loopback address, e.g. 127.0.0.1:9100"),
         );
     // A workaround since clap 2 will not fix the deprecation warnings in this macro.
     #[allow(deprecated)]
@@ -1,11 +1,55 @@
 use futures01::{self, Future};
 use mullvad_rpc::{self, rest::RequestServiceHandle};
 use mullvad_types::location::{AmIMullvad, GeoIpLocation};
-
+use parking_lot::Mutex;
+use std::{
+    fmt,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 const URI_V4: &str = "https://ipv4.am.i.mullvad.net/json";
 const URI_V6: &str = "https://ipv6.am.i.mullvad.net/json";
 
+/// How long a successful lookup is considered fresh enough to be served from the cache.
+const CACHE_TTL: Duration = Duration::from_secs(10);
+
+/// Holds the result of the most recent successful GeoIP lookup, so that frequent polling from
+/// frontends doesn't result in a request to am.i.mullvad.net every time.
+#[derive(Clone, Default)]
+pub struct GeoIpCache {
+    last_location: Arc<Mutex<Option<(Instant, GeoIpLocation)>>>,
+}
+
+impl GeoIpCache {
+    /// Returns the cached location if it was fetched less than `CACHE_TTL` ago.
+    pub fn get_fresh(&self) -> Option<GeoIpLocation> {
+        match &*self.last_location.lock() {
+            Some((fetched_at, location)) if fetched_at.elapsed() < CACHE_TTL => {
+                Some(location.clone())
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the cached location regardless of its age.
+    pub fn get_last(&self) -> Option<GeoIpLocation> {
+        self.last_location
+            .lock()
+            .as_ref()
+            .map(|(_, location)| location.clone())
+    }
+
+    pub fn store(&self, location: GeoIpLocation) {
+        *self.last_location.lock() = Some((Instant::now(), location));
+    }
+
+    /// Forgets the cached location. Should be called whenever the route to the internet changes.
+    pub fn clear(&self) {
+        *self.last_location.lock() = None;
+    }
+}
+
 pub fn send_location_request(
     request_sender: RequestServiceHandle,
 ) -> impl Future<Item = GeoIpLocation, Error = mullvad_rpc::rest::Error> {
@@ -13,30 +57,31 @@ pub fn send_location_request(
         send_location_request_internal(URI_V4, request_sender.clone()).map(GeoIpLocation::from);
     let v6_future = send_location_request_internal(URI_V6, request_sender).map(GeoIpLocation::from);
 
-    v4_future.then(
-        |v4_result: Result<GeoIpLocation, mullvad_rpc::rest::Error>| {
-            v6_future.then(
-                |v6_result: Result<GeoIpLocation, mullvad_rpc::rest::Error>| match (
-                    v4_result, v6_result,
-                ) {
-                    (Ok(mut v4), Ok(v6)) => {
-                        v4.ipv6 = v6.ipv6;
-                        v4.mullvad_exit_ip = v4.mullvad_exit_ip && v6.mullvad_exit_ip;
-                        Ok(v4)
-                    }
-                    (Ok(v4), Err(e)) => {
-                        log::debug!("Unable to fetch IPv6 GeoIP location: {}", e);
-                        Ok(v4)
-                    }
-                    (Err(e), Ok(v6)) => {
-                        log::debug!("Unable to fetch IPv4 GeoIP location: {}", e);
-                        Ok(v6)
-                    }
-                    (Err(e_v4), Err(_)) => Err(e_v4),
-                },
-            )
-        },
-    )
+    v4_future.then(|v4_result| v6_future.then(|v6_result| merge_lookups(v4_result, v6_result)))
+}
+
+/// Combines the results of the IPv4 and IPv6 lookups. Either lookup may fail without hiding the
+/// address found by the other one.
+fn merge_lookups<E: fmt::Display>(
+    v4_result: Result<GeoIpLocation, E>,
+    v6_result: Result<GeoIpLocation, E>,
+) -> Result<GeoIpLocation, E> {
+    match (v4_result, v6_result) {
+        (Ok(mut v4), Ok(v6)) => {
+            v4.ipv6 = v6.ipv6;
+            v4.mullvad_exit_ip = v4.mullvad_exit_ip && v6.mullvad_exit_ip;
+            Ok(v4)
+        }
+        (Ok(v4), Err(e)) => {
+            log::debug!("Unable to fetch IPv6 GeoIP location: {}", e);
+            Ok(v4)
+        }
+        (Err(e), Ok(v6)) => {
+            log::debug!("Unable to fetch IPv4 GeoIP location: {}", e);
+            Ok(v6)
+        }
+        (Err(e_v4), Err(_)) => Err(e_v4),
+    }
 }
 
 fn send_location_request_internal(
@@ -51,3 +96,42 @@ fn send_location_request_internal(
     };
     service.compat_spawn(future)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use mullvad_types::location::PublicIp;
+    use std::net::IpAddr;
+
+    fn lookup(ip: &str) -> Result<GeoIpLocation, String> {
+        Ok(GeoIpLocation::from(AmIMullvad {
+            ip: ip.parse().unwrap(),
+            country: "Sweden".to_owned(),
+            city: Some("Gothenburg".to_owned()),
+            latitude: 57.7,
+            longitude: 11.97,
+            mullvad_exit_ip: true,
+        }))
+    }
+
+    #[test]
+    fn test_both_families_are_reported() {
+        let location = merge_lookups(lookup("1.2.3.4"), lookup("2001:db8::1")).unwrap();
+        let addresses: Vec<IpAddr> =
+            vec!["1.2.3.4".parse().unwrap(), "2001:db8::1".parse().unwrap()];
+        assert_eq!(PublicIp::from(&location).addresses(), addresses);
+    }
+
+    #[test]
+    fn test_failed_lookup_does_not_hide_the_other_family() {
+        let location = merge_lookups(Err("timed out".to_owned()), lookup("2001:db8::1")).unwrap();
+        assert_eq!(location.ipv4, None);
+        assert_eq!(location.ipv6, Some("2001:db8::1".parse().unwrap()));
+
+        let location = merge_lookups(lookup("1.2.3.4"), Err("timed out".to_owned())).unwrap();
+        assert_eq!(location.ipv4, Some("1.2.3.4".parse().unwrap()));
+        assert_eq!(location.ipv6, None);
+
+        assert!(merge_lookups(Err("timed out".to_owned()), Err("timed out".to_owned())).is_err());
+    }
+}
@@ -0,0 +1,167 @@
+//! Measures the round-trip time to relays, so that frontends can present or pick the most
+//! responsive ones.
+
+use mullvad_types::relay_list::{Relay, RelayLatency};
+use parking_lot::Mutex;
+use std::{
+    io,
+    net::{IpAddr, SocketAddr, TcpStream},
+    sync::{mpsc, Arc},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How long to wait for a single relay to respond.
+pub const MEASUREMENT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Maximum number of relays that are measured at the same time.
+const MAX_CONCURRENT_MEASUREMENTS: usize = 16;
+
+/// Port used for measurements. All relays listen on it or actively refuse connections to it,
+/// either of which gives a round-trip time.
+const MEASUREMENT_PORT: u16 = 443;
+
+/// Something that can measure the round-trip time to a single address.
+pub trait LatencyMeasurer: Send + Sync + 'static {
+    /// Returns the round-trip time to `address`, or `None` if no response was received within
+    /// `timeout`.
+    fn measure(&self, address: IpAddr, timeout: Duration) -> Option<Duration>;
+}
+
+/// Measures latency as the time it takes to get a response to a TCP connection attempt. This is
+/// done outside the tunnel, so relays that the firewall blocks traffic to will time out.
+pub struct TcpLatencyMeasurer;
+
+impl LatencyMeasurer for TcpLatencyMeasurer {
+    fn measure(&self, address: IpAddr, timeout: Duration) -> Option<Duration> {
+        let start = Instant::now();
+        match TcpStream::connect_timeout(&SocketAddr::new(address, MEASUREMENT_PORT), timeout) {
+            Ok(_) => Some(start.elapsed()),
+            Err(ref error) if error.kind() == io::ErrorKind::ConnectionRefused => {
+                Some(start.elapsed())
+            }
+            Err(_) => None,
+        }
+    }
+}
+
+/// Returns the hostname and entry address of each relay, in the form `measure_relays` expects.
+pub fn relay_addresses(relays: &[Relay]) -> Vec<(String, IpAddr)> {
+    relays
+        .iter()
+        .map(|relay| (relay.hostname.clone(), IpAddr::V4(relay.ipv4_addr_in)))
+        .collect()
+}
+
+/// Measures the latency to all `relays` concurrently. The results are returned in the same order
+/// as the relays were given. Measurements slower than `timeout` are reported as `None`, no matter
+/// what the measurer returned.
+pub fn measure_relays(
+    measurer: Arc<dyn LatencyMeasurer>,
+    relays: Vec<(String, IpAddr)>,
+    timeout: Duration,
+) -> Vec<RelayLatency> {
+    let relay_count = relays.len();
+    let queue = Arc::new(Mutex::new(relays.into_iter().enumerate()));
+    let (result_tx, result_rx) = mpsc::channel();
+
+    for _ in 0..MAX_CONCURRENT_MEASUREMENTS.min(relay_count) {
+        let measurer = measurer.clone();
+        let queue = queue.clone();
+        let result_tx = result_tx.clone();
+        thread::spawn(move || loop {
+            let next = queue.lock().next();
+            let (index, (hostname, address)) = match next {
+                Some(relay) => relay,
+                None => break,
+            };
+            let latency = measurer
+                .measure(address, timeout)
+                .filter(|latency| *latency <= timeout);
+            let result = RelayLatency {
+                hostname,
+                address,
+                latency,
+            };
+            if result_tx.send((index, result)).is_err() {
+                break;
+            }
+        });
+    }
+    drop(result_tx);
+
+    let mut results: Vec<_> = result_rx.iter().collect();
+    results.sort_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+/// Returns the relay with the lowest latency, ignoring relays that timed out.
+pub fn fastest(latencies: &[RelayLatency]) -> Option<&RelayLatency> {
+    latencies
+        .iter()
+        .filter(|relay| relay.latency.is_some())
+        .min_by_key(|relay| relay.latency)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    struct FakeMeasurer;
+
+    impl LatencyMeasurer for FakeMeasurer {
+        fn measure(&self, address: IpAddr, timeout: Duration) -> Option<Duration> {
+            match address {
+                IpAddr::V4(address) if address.is_loopback() => Some(Duration::from_millis(10)),
+                _ => Some(timeout * 2),
+            }
+        }
+    }
+
+    #[test]
+    fn test_timed_out_measurement_is_none() {
+        let relays = vec![
+            ("fast".to_owned(), "127.0.0.1".parse().unwrap()),
+            ("slow".to_owned(), "10.0.0.1".parse().unwrap()),
+        ];
+        let results = measure_relays(Arc::new(FakeMeasurer), relays, MEASUREMENT_TIMEOUT);
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].hostname, "fast");
+        assert_eq!(results[0].latency, Some(Duration::from_millis(10)));
+        assert_eq!(results[1].hostname, "slow");
+        assert_eq!(results[1].latency, None);
+    }
+
+    struct StubMeasurer;
+
+    impl LatencyMeasurer for StubMeasurer {
+        fn measure(&self, address: IpAddr, _timeout: Duration) -> Option<Duration> {
+            match address {
+                IpAddr::V4(address) => Some(Duration::from_millis(address.octets()[3].into())),
+                IpAddr::V6(_) => None,
+            }
+        }
+    }
+
+    #[test]
+    fn test_fastest_relay_is_selected() {
+        let relays = vec![
+            ("se1".to_owned(), "10.0.0.30".parse().unwrap()),
+            ("se2".to_owned(), "10.0.0.5".parse().unwrap()),
+            ("se3".to_owned(), "::1".parse().unwrap()),
+            ("se4".to_owned(), "10.0.0.12".parse().unwrap()),
+        ];
+        let results = measure_relays(Arc::new(StubMeasurer), relays, MEASUREMENT_TIMEOUT);
+
+        assert_eq!(fastest(&results).unwrap().hostname, "se2");
+    }
+
+    #[test]
+    fn test_no_fastest_relay_if_all_timed_out() {
+        let relays = vec![("se1".to_owned(), "::1".parse().unwrap())];
+        let results = measure_relays(Arc::new(StubMeasurer), relays, MEASUREMENT_TIMEOUT);
+
+        assert!(fastest(&results).is_none());
+    }
+}
@@ -8,9 +8,11 @@ extern crate serde;
 mod account_history;
 pub mod exception_logging;
 mod geoip;
+mod latency;
 pub mod logging;
 #[cfg(not(target_os = "android"))]
 pub mod management_interface;
+pub mod metrics;
 mod relays;
 #[cfg(not(target_os = "android"))]
 pub mod rpc_uniqueness_check;
@@ -18,9 +20,10 @@ mod settings;
 pub mod version;
 mod version_check;
 
+use chrono::{DateTime, Utc};
 use futures01::{
     future::{self, Executor},
-    stream::Wait,
+    stream::{self, Wait},
     sync::{
         mpsc::{UnboundedReceiver, UnboundedSender},
         oneshot,
@@ -30,31 +33,40 @@ use futures01::{
 use log::{debug, error, info, warn};
 use mullvad_rpc::AccountsProxy;
 use mullvad_types::{
-    account::{AccountData, AccountToken, VoucherSubmission},
-    endpoint::MullvadEndpoint,
-    location::GeoIpLocation,
+    account::{AccountData, AccountExpiry, AccountToken, ExpiryStatus, VoucherSubmission},
+    endpoint::{MullvadEndpoint, RelayEndpoint},
+    location::{CountryCode, GeoIpLocation, PublicIp},
+    logging::LogLevel,
     relay_constraints::{
-        BridgeSettings, BridgeState, Constraint, InternalBridgeConstraints, RelaySettings,
-        RelaySettingsUpdate,
+        BridgeSettings, BridgeState, Constraint, InternalBridgeConstraints, LocationConstraint,
+        OpenVpnConstraints, RelayConstraintsUpdate, RelayFilter, RelaySettings,
+        RelaySettingsUpdate, SelectionMode,
+    },
+    relay_list::{CountryRelayCount, Relay, RelayLatency, RelayList},
+    settings::{CustomRelayError, DnsBlockers, Settings, SettingsPatch},
+    states::{
+        ConnectionProgress, Connectivity, FirewallState, ReconnectStatus, StateHistoryEntry,
+        StatusSnapshot, TargetState, TunnelState, TunnelStats,
     },
-    relay_list::{Relay, RelayList},
-    settings::Settings,
-    states::{TargetState, TunnelState},
     version::{AppVersion, AppVersionInfo},
     wireguard::KeygenEvent,
+    CustomTunnelEndpoint,
 };
+use parking_lot::Mutex;
 use settings::SettingsPersister;
 #[cfg(not(target_os = "android"))]
 use std::path::Path;
 use std::{
+    collections::{BTreeMap, VecDeque},
     fs::{self, File},
     io,
     marker::PhantomData,
     mem,
+    net::{IpAddr, SocketAddr},
     path::PathBuf,
     sync::{mpsc, Arc, Weak},
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
 #[cfg(target_os = "linux")]
 use talpid_core::split_tunnel;
@@ -65,8 +77,11 @@ use talpid_core::{
 #[cfg(target_os = "android")]
 use talpid_types::android::AndroidContext;
 use talpid_types::{
-    net::{openvpn, TransportProtocol, TunnelParameters, TunnelType},
-    tunnel::{ErrorStateCause, ParameterGenerationError, TunnelStateTransition},
+    net::{openvpn, TransportProtocol, TunnelEndpoint, TunnelParameters, TunnelType},
+    tunnel::{
+        ActionAfterDisconnect, ErrorState, ErrorStateCause, ParameterGenerationError,
+        TunnelStateTransition,
+    },
     ErrorExt,
 };
 
@@ -74,6 +89,13 @@ use talpid_types::{
 mod wireguard;
 
 const TARGET_START_STATE_FILE: &str = "target-start-state.json";
+/// Files in the cache directory that only hold data fetched from the API. Removing them loses no
+/// state, the data is fetched again when needed.
+const API_CACHE_FILES: &[&str] = &[
+    relays::RELAYS_FILENAME,
+    version_check::VERSION_INFO_FILENAME,
+    mullvad_rpc::API_IP_CACHE_FILENAME,
+];
 mod event_loop;
 
 /// FIXME(linus): This is here just because the futures crate has deprecated it and jsonrpc_core
@@ -86,12 +108,38 @@ const TUNNEL_STATE_MACHINE_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
 /// Timeout for first WireGuard key pushing
 const FIRST_KEY_PUSH_TIMEOUT: Duration = Duration::from_secs(5);
 
+/// How often to check whether the current account is about to expire.
+const ACCOUNT_EXPIRY_CHECK_INTERVAL: Duration = Duration::from_secs(5 * 60);
+/// How old the cached account data may get before the expiry check fetches it again.
+const ACCOUNT_DATA_REFRESH_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// How often tunnel stats are broadcast to subscribers while connected.
+const TUNNEL_STATS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Delay before the first automatic reconnection attempt after the tunnel failed. The delay is
+/// doubled for every following attempt, up to `RECONNECT_BACKOFF_MAX`.
+const RECONNECT_BACKOFF_BASE: Duration = Duration::from_secs(5);
+const RECONNECT_BACKOFF_MAX: Duration = Duration::from_secs(5 * 60);
+
+/// How long the computer has to stay offline or online before `connectivity` subscribers are
+/// notified.
+const OFFLINE_STATE_DEBOUNCE: Duration = Duration::from_secs(2);
+
+/// How many commands may be waiting for the daemon before further commands are rejected.
+const DAEMON_COMMAND_QUEUE_SIZE: usize = 100;
+
+/// How many tunnel state transitions are kept for `get_state_history`.
+pub const STATE_HISTORY_CAPACITY: usize = 100;
+
 #[derive(err_derive::Error, Debug)]
 #[error(no_from)]
 pub enum Error {
     #[error(display = "Failed to send command to daemon because it is not running")]
     DaemonUnavailable,
 
+    #[error(display = "Failed to send command to daemon because too many commands are queued")]
+    DaemonBusy,
+
     #[error(display = "Unable to initialize network event loop")]
     InitIoEventLoop(#[error(source)] io::Error),
 
@@ -123,6 +171,9 @@ pub enum Error {
     #[error(display = "Failed to create directory {}", _0)]
     CreateDirError(String, #[error(source)] io::Error),
 
+    #[error(display = "Failed to remove cache file {}", _0)]
+    RemoveCacheFileError(String, #[error(source)] io::Error),
+
     #[error(display = "Failed to get path")]
     PathError(#[error(source)] mullvad_paths::Error),
 
@@ -149,20 +200,57 @@ pub enum Error {
 pub enum DaemonCommand {
     /// Set target state. Does nothing if the daemon already has the state that is being set.
     SetTargetState(oneshot::Sender<std::result::Result<(), ()>>, TargetState),
-    /// Reconnect the tunnel, if one is connecting/connected.
-    Reconnect,
+    /// Force a fresh tunnel to be established, connecting if currently disconnected. Fails if no
+    /// account token is configured.
+    Reconnect(oneshot::Sender<std::result::Result<(), ()>>),
+    /// Restrict the relay selection to the given location and connect. Fails if there are no
+    /// active relays in the location.
+    ConnectTo(
+        oneshot::Sender<std::result::Result<(), ()>>,
+        Constraint<LocationConstraint>,
+    ),
+    /// Connect to the given relay without saving it in the relay settings. Only the next tunnel
+    /// uses it. Fails if no account token is configured.
+    ConnectOnce(
+        oneshot::Sender<std::result::Result<(), ()>>,
+        CustomTunnelEndpoint,
+    ),
     /// Request the current state.
     GetState(oneshot::Sender<TunnelState>),
+    /// Request what the firewall currently lets through
+    GetFirewallState(oneshot::Sender<FirewallState>),
+    /// Get up to the given number of the most recent tunnel state transitions
+    GetStateHistory(oneshot::Sender<Vec<StateHistoryEntry>>, usize),
+    /// Get the endpoint of the connected tunnel
+    GetCurrentRelay(oneshot::Sender<Option<TunnelEndpoint>>),
+    /// Get the relay that a connection with the current settings would use, without connecting
+    PreviewRelaySelection(oneshot::Sender<Option<RelayEndpoint>>),
+    /// Request whether the computer is online and whether the API can be reached
+    GetConnectivity(oneshot::Sender<Connectivity>),
+    /// Request the progress of the automatic reconnection after a tunnel failure
+    GetReconnectStatus(oneshot::Sender<ReconnectStatus>),
+    /// Request the tunnel state, current relay, traffic statistics, account expiry and
+    /// reconnection status at once
+    GetStatusSnapshot(oneshot::Sender<StatusSnapshot>),
     /// Get the current geographical location.
     GetCurrentLocation(oneshot::Sender<Option<GeoIpLocation>>),
+    /// Look up the public IPv4 and IPv6 addresses of the device
+    GetPublicIp(oneshot::Sender<Option<PublicIp>>),
+    /// Request traffic statistics for the connected tunnel
+    GetTunnelStats(oneshot::Sender<Option<TunnelStats>>),
+    /// Request the most recent error state, unless a tunnel has connected since
+    GetLastError(oneshot::Sender<Option<ErrorState>>),
     CreateNewAccount(oneshot::Sender<std::result::Result<String, mullvad_rpc::rest::Error>>),
     /// Request the metadata for an account.
     GetAccountData(
         oneshot::Sender<BoxFuture<AccountData, mullvad_rpc::rest::Error>>,
         AccountToken,
     ),
-    /// Request www auth token for an account
-    GetWwwAuthToken(oneshot::Sender<BoxFuture<String, mullvad_rpc::rest::Error>>),
+    /// Request the expiry of the current account. Served from the last fetched account data if
+    /// available. Resolves to `None` if no account is set.
+    GetAccountExpiry(oneshot::Sender<BoxFuture<Option<AccountExpiry>, mullvad_rpc::rest::Error>>),
+    /// Request www auth token for the current account. Responds with `None` if no account is set.
+    GetWwwAuthToken(oneshot::Sender<Option<BoxFuture<String, mullvad_rpc::rest::Error>>>),
     /// Submit voucher to add time to the current account. Returns time added in seconds
     SubmitVoucher(
         oneshot::Sender<BoxFuture<VoucherSubmission, mullvad_rpc::rest::Error>>,
@@ -170,19 +258,56 @@ pub enum DaemonCommand {
     ),
     /// Request account history
     GetAccountHistory(oneshot::Sender<Vec<AccountToken>>),
-    /// Request account history
+    /// Remove an account from the account history
     RemoveAccountFromHistory(oneshot::Sender<()>, AccountToken),
     /// Clear account history
     ClearAccountHistory(oneshot::Sender<()>),
     /// Get the list of countries and cities where there are relays.
     GetRelayLocations(oneshot::Sender<RelayList>),
+    /// Get the names of all countries that have at least one active relay.
+    GetRelayCountries(oneshot::Sender<BTreeMap<CountryCode, String>>),
+    /// Get the number of active relays in each city, grouped by country.
+    GetRelayLocationCounts(oneshot::Sender<BTreeMap<CountryCode, CountryRelayCount>>),
+    /// Measure the latency to all active relays in a location.
+    MeasureRelayLatency(
+        oneshot::Sender<Vec<RelayLatency>>,
+        Constraint<LocationConstraint>,
+    ),
+    /// Measure the latency to all active relays in a location and connect to the fastest one.
+    /// Responds with the chosen relay, or `None` if no relay responded.
+    ConnectFastest(
+        oneshot::Sender<Option<RelayLatency>>,
+        Constraint<LocationConstraint>,
+    ),
     /// Trigger an asynchronous relay list update. This returns before the relay list is actually
     /// updated.
     UpdateRelayLocations,
+    /// Trigger a relay list update and report back once the download has completed or failed.
+    UpdateRelayList(oneshot::Sender<std::result::Result<(), ()>>),
     /// Set which account token to use for subsequent connection attempts.
     SetAccount(oneshot::Sender<()>, Option<AccountToken>),
+    /// Disconnect, clear the active account and forget any cached account data.
+    Logout(oneshot::Sender<()>),
     /// Place constraints on the type of tunnel and relay
     UpdateRelaySettings(oneshot::Sender<()>, RelaySettingsUpdate),
+    /// Change several settings at once, reconnecting at most once
+    ApplySettings(oneshot::Sender<()>, SettingsPatch),
+    /// Store a custom relay under a new name
+    AddCustomRelay(
+        oneshot::Sender<Result<(), CustomRelayError>>,
+        String,
+        CustomTunnelEndpoint,
+    ),
+    /// Remove a named custom relay
+    RemoveCustomRelay(oneshot::Sender<Result<(), CustomRelayError>>, String),
+    /// Get all named custom relays
+    GetCustomRelays(oneshot::Sender<BTreeMap<String, CustomTunnelEndpoint>>),
+    /// Connect using the named custom relay
+    SetActiveCustomRelay(oneshot::Sender<Result<(), CustomRelayError>>, String),
+    /// Use relays matching the given location, or any relay if `None`
+    SetLocationConstraint(oneshot::Sender<()>, Option<LocationConstraint>),
+    /// Limit the transport protocol and port used for OpenVPN tunnels
+    SetOpenVpnConstraints(oneshot::Sender<()>, OpenVpnConstraints),
     /// Set the allow LAN setting.
     SetAllowLan(oneshot::Sender<()>, bool),
     /// Set the beta program setting.
@@ -191,36 +316,94 @@ pub enum DaemonCommand {
     SetBlockWhenDisconnected(oneshot::Sender<()>, bool),
     /// Set the auto-connect setting.
     SetAutoConnect(oneshot::Sender<()>, bool),
+    /// Get the auto-connect setting.
+    GetAutoConnect(oneshot::Sender<bool>),
+    /// Set how many seconds a connection attempt may take, or zero for no limit
+    SetConnectTimeout(oneshot::Sender<()>, u32),
+    /// Get how many seconds a connection attempt may take
+    GetConnectTimeout(oneshot::Sender<u32>),
     /// Set the mssfix argument for OpenVPN
     SetOpenVpnMssfix(oneshot::Sender<()>, Option<u16>),
+    /// Get the mssfix argument for OpenVPN
+    GetOpenVpnMssfix(oneshot::Sender<Option<u16>>),
+    /// Set the data channel cipher for OpenVPN
+    SetOpenVpnCipher(oneshot::Sender<()>, Option<String>),
+    /// Get the data channel cipher for OpenVPN
+    GetOpenVpnCipher(oneshot::Sender<Option<String>>),
     /// Set proxy details for OpenVPN
     SetBridgeSettings(
         oneshot::Sender<std::result::Result<(), settings::Error>>,
         BridgeSettings,
     ),
+    /// Get proxy details for OpenVPN
+    GetBridgeSettings(oneshot::Sender<BridgeSettings>),
     /// Set proxy state
     SetBridgeState(
         oneshot::Sender<std::result::Result<(), settings::Error>>,
         BridgeState,
     ),
+    /// Get proxy state
+    GetBridgeState(oneshot::Sender<BridgeState>),
+    /// Set which relay owners and hosting providers may be selected
+    SetRelayFilter(oneshot::Sender<()>, RelayFilter),
+    /// Get which relay owners and hosting providers may be selected
+    GetRelayFilter(oneshot::Sender<RelayFilter>),
+    /// Set how a relay is picked among the ones that match the constraints
+    SetRelaySelectionMode(oneshot::Sender<()>, SelectionMode),
+    /// Get how a relay is picked among the ones that match the constraints
+    GetRelaySelectionMode(oneshot::Sender<SelectionMode>),
+    /// Set whether selected relays are checked to respond before connecting to them
+    SetProbeBeforeConnect(oneshot::Sender<()>, bool),
+    /// Get whether selected relays are checked to respond before connecting to them
+    GetProbeBeforeConnect(oneshot::Sender<bool>),
     /// Set if IPv6 should be enabled in the tunnel
     SetEnableIpv6(oneshot::Sender<()>, bool),
+    /// Get whether IPv6 is enabled in the tunnel
+    GetEnableIpv6(oneshot::Sender<bool>),
+    /// Set the DNS servers to use inside the tunnel, or `None` for the default ones
+    SetCustomDns(oneshot::Sender<()>, Option<Vec<IpAddr>>),
+    /// Get the DNS servers used inside the tunnel, if custom ones are set
+    GetCustomDns(oneshot::Sender<Option<Vec<IpAddr>>>),
+    /// Set which categories of domains the Mullvad DNS servers should block
+    SetDnsBlockers(oneshot::Sender<()>, DnsBlockers),
+    /// Get which categories of domains the Mullvad DNS servers block
+    GetDnsBlockers(oneshot::Sender<DnsBlockers>),
     /// Set MTU for wireguard tunnels
     SetWireguardMtu(oneshot::Sender<()>, Option<u16>),
     /// Set automatic key rotation interval for wireguard tunnels
     SetWireguardRotationInterval(oneshot::Sender<()>, Option<u32>),
     /// Get the daemon settings
     GetSettings(oneshot::Sender<Settings>),
+    /// Get the relay settings, which are either a custom tunnel endpoint or relay constraints
+    GetRelaySettings(oneshot::Sender<RelaySettings>),
     /// Generate new wireguard key
     GenerateWireguardKey(oneshot::Sender<wireguard::KeygenEvent>),
     /// Return a public key of the currently set wireguard private key, if there is one
     GetWireguardKey(oneshot::Sender<Option<wireguard::PublicKey>>),
     /// Verify if the currently set wireguard key is valid.
-    VerifyWireguardKey(oneshot::Sender<bool>),
+    VerifyWireguardKey(oneshot::Sender<Result<bool, wireguard::Error>>),
     /// Get information about the currently running and latest app versions
     GetVersionInfo(oneshot::Sender<AppVersionInfo>),
     /// Get current version of the app
     GetCurrentVersion(oneshot::Sender<AppVersion>),
+    /// Get up to the given number of the most recent log lines
+    GetRecentLogs(oneshot::Sender<Vec<String>>, usize),
+    /// Change the most verbose level that is logged
+    SetLogLevel(oneshot::Sender<()>, LogLevel),
+    /// Get the most verbose level that is logged
+    GetLogLevel(oneshot::Sender<Option<LogLevel>>),
+    /// Restore all settings except the account to their defaults
+    ResetSettings(oneshot::Sender<()>),
+    /// Replace the settings with the ones in the settings file and apply any changes
+    ReloadSettings(oneshot::Sender<std::result::Result<(), settings::Error>>),
+    /// Remove cached relay lists, version info and API responses, keeping settings and the
+    /// account
+    ClearCache(oneshot::Sender<Result<(), Error>>),
+    /// Set the address of the API server to use instead of the default one, or `None` to use
+    /// the default
+    SetApiEndpoint(oneshot::Sender<()>, Option<SocketAddr>),
+    /// Get the address of the API server used instead of the default one, if any
+    GetApiEndpoint(oneshot::Sender<Option<SocketAddr>>),
     /// Remove settings and clear the cache
     #[cfg(not(target_os = "android"))]
     FactoryReset(oneshot::Sender<()>),
@@ -236,11 +419,17 @@ pub enum DaemonCommand {
     /// Clear list of processes excluded from the tunnel
     #[cfg(target_os = "linux")]
     ClearSplitTunnelProcesses(oneshot::Sender<()>),
+    /// Request list of applications excluded from the tunnel
+    GetSplitTunnelApps(oneshot::Sender<Vec<PathBuf>>),
+    /// Exclude traffic of an application (absolute path) from the tunnel
+    AddSplitTunnelApp(oneshot::Sender<()>, PathBuf),
+    /// Remove application from list of applications excluded from the tunnel
+    RemoveSplitTunnelApp(oneshot::Sender<()>, PathBuf),
     /// Makes the daemon exit the main loop and quit.
     Shutdown,
     /// Saves the target tunnel state and enters a blocking state. The state is restored
-    /// upon restart.
-    PrepareRestart,
+    /// upon restart. Responds once the blocking state has been requested.
+    PrepareRestart(oneshot::Sender<()>),
 }
 
 /// All events that can happen in the daemon. Sent from various threads and exposed interfaces.
@@ -270,6 +459,19 @@ pub(crate) enum InternalDaemonEvent {
     ),
     /// The background job fetching new `AppVersionInfo`s got a new info object.
     NewAppVersionInfo(AppVersionInfo),
+    /// Account data was fetched from the API.
+    NewAccountData(AccountToken, AccountData),
+    /// Periodic check of whether the account is about to expire.
+    CheckAccountExpiry,
+    /// Time to broadcast a new tunnel stats sample.
+    SampleTunnelStats,
+    /// The tunnel state machine detected that the computer went offline or came back online.
+    OfflineStateChange(bool),
+    /// Time to check whether a change of the offline state has lasted long enough to be
+    /// broadcast.
+    CheckOfflineState,
+    /// The current connection attempt has taken longer than the connect timeout.
+    ConnectTimeout,
 }
 
 impl From<TunnelStateTransition> for InternalDaemonEvent {
@@ -304,7 +506,7 @@ impl DaemonExecutionState {
         match self {
             Running => {
                 match tunnel_state {
-                    TunnelState::Disconnected => mem::replace(self, Finished),
+                    TunnelState::Disconnected { .. } => mem::replace(self, Finished),
                     _ => mem::replace(self, Exiting),
                 };
             }
@@ -333,38 +535,76 @@ impl DaemonExecutionState {
     }
 }
 
+/// Internal events merged with the commands sent by frontends.
+type InternalDaemonEventReceiver = stream::Select<
+    UnboundedReceiver<InternalDaemonEvent>,
+    stream::Map<
+        futures01::sync::mpsc::Receiver<DaemonCommand>,
+        fn(DaemonCommand) -> InternalDaemonEvent,
+    >,
+>;
+
 pub struct DaemonCommandChannel {
     sender: DaemonCommandSender,
-    receiver: UnboundedReceiver<InternalDaemonEvent>,
+    event_receiver: UnboundedReceiver<InternalDaemonEvent>,
+    command_receiver: futures01::sync::mpsc::Receiver<DaemonCommand>,
 }
 
 impl DaemonCommandChannel {
     pub fn new() -> Self {
-        let (untracked_sender, receiver) = futures01::sync::mpsc::unbounded();
-        let sender = DaemonCommandSender(Arc::new(untracked_sender));
+        Self::with_queue_size(DAEMON_COMMAND_QUEUE_SIZE)
+    }
+
+    fn with_queue_size(queue_size: usize) -> Self {
+        let (untracked_sender, event_receiver) = futures01::sync::mpsc::unbounded();
+        let (command_sender, command_receiver) = futures01::sync::mpsc::channel(queue_size);
+        let sender = DaemonCommandSender {
+            event_sender: Arc::new(untracked_sender),
+            command_sender: Arc::new(Mutex::new(command_sender)),
+        };
 
-        Self { sender, receiver }
+        Self {
+            sender,
+            event_receiver,
+            command_receiver,
+        }
     }
 
     pub fn sender(&self) -> DaemonCommandSender {
         self.sender.clone()
     }
 
-    fn destructure(self) -> (DaemonEventSender, UnboundedReceiver<InternalDaemonEvent>) {
-        let event_sender = DaemonEventSender::new(Arc::downgrade(&self.sender.0));
+    fn destructure(self) -> (DaemonEventSender, InternalDaemonEventReceiver) {
+        let event_sender = DaemonEventSender::new(Arc::downgrade(&self.sender.event_sender));
+        let commands = self
+            .command_receiver
+            .map(InternalDaemonEvent::Command as fn(DaemonCommand) -> InternalDaemonEvent);
 
-        (event_sender, self.receiver)
+        (event_sender, self.event_receiver.select(commands))
     }
 }
 
 #[derive(Clone)]
-pub struct DaemonCommandSender(Arc<UnboundedSender<InternalDaemonEvent>>);
+pub struct DaemonCommandSender {
+    /// Internal events can only be sent to the daemon while this is alive.
+    event_sender: Arc<UnboundedSender<InternalDaemonEvent>>,
+    command_sender: Arc<Mutex<futures01::sync::mpsc::Sender<DaemonCommand>>>,
+}
 
 impl DaemonCommandSender {
+    /// Queues a command for the daemon without blocking. Fails with `Error::DaemonBusy` if the
+    /// daemon has fallen `DAEMON_COMMAND_QUEUE_SIZE` commands behind.
     pub fn send(&self, command: DaemonCommand) -> Result<(), Error> {
-        self.0
-            .unbounded_send(InternalDaemonEvent::Command(command))
-            .map_err(|_| Error::DaemonUnavailable)
+        self.command_sender
+            .lock()
+            .try_send(command)
+            .map_err(|error| {
+                if error.is_full() {
+                    Error::DaemonBusy
+                } else {
+                    Error::DaemonUnavailable
+                }
+            })
     }
 }
 
@@ -431,6 +671,16 @@ where
     }
 }
 
+/// Forwards offline state changes from the tunnel state machine to the daemon.
+struct OfflineStateListener(DaemonEventSender);
+
+impl Sender<bool> for OfflineStateListener {
+    fn send(&self, is_offline: bool) -> Result<(), ()> {
+        self.0
+            .send(InternalDaemonEvent::OfflineStateChange(is_offline))
+    }
+}
+
 /// Trait representing something that can broadcast daemon events.
 pub trait EventListener {
     /// Notify that the tunnel state changed.
@@ -442,27 +692,175 @@ pub trait EventListener {
     /// Notify that the relay list changed.
     fn notify_relay_list(&self, relay_list: RelayList);
 
+    /// Notify that the account was changed, or removed if `None`.
+    fn notify_account(&self, account_token: Option<AccountToken>);
+
     /// Notify that info about the latest available app version changed.
     /// Or some flag about the currently running version is changed.
     fn notify_app_version(&self, app_version_info: AppVersionInfo);
 
     /// Notify clients of a key generation event.
     fn notify_key_event(&self, key_event: KeygenEvent);
+
+    /// Notify that a GeoIP lookup finished after the tunnel connected or disconnected.
+    fn notify_location(&self, location: GeoIpLocation);
+
+    /// Notify that the account data was refreshed or that the account moved closer to expiring.
+    fn notify_account_expiry(&self, account_expiry: AccountExpiry);
+
+    /// Notify that an attempt to establish a tunnel advanced to a new phase.
+    fn notify_connection_progress(&self, progress: ConnectionProgress);
+
+    /// Notify clients of a new tunnel stats sample.
+    fn notify_tunnel_stats(&self, tunnel_stats: TunnelStats);
+
+    /// Notify that the computer went offline or came back online.
+    fn notify_connectivity(&self, connectivity: Connectivity);
+
+    /// Returns whether anyone is listening for tunnel stats, so that they aren't sampled
+    /// needlessly.
+    fn has_tunnel_stats_subscribers(&self) -> bool;
+}
+
+/// Holds back changes of the offline state until they have lasted for `OFFLINE_STATE_DEBOUNCE`,
+/// so that a link that bounces doesn't make frontends flicker.
+struct OfflineStateDebouncer {
+    /// The offline state that listeners were last notified about.
+    notified: bool,
+    /// When the offline state last changed away from `notified`, unless it has changed back.
+    pending_since: Option<Instant>,
+}
+
+impl OfflineStateDebouncer {
+    fn new(is_offline: bool) -> Self {
+        OfflineStateDebouncer {
+            notified: is_offline,
+            pending_since: None,
+        }
+    }
+
+    /// Records the current offline state. Returns true if it differs from the notified state, in
+    /// which case `poll` should be called once `OFFLINE_STATE_DEBOUNCE` has passed.
+    fn update(&mut self, is_offline: bool, now: Instant) -> bool {
+        if is_offline == self.notified {
+            self.pending_since = None;
+            false
+        } else {
+            self.pending_since.get_or_insert(now);
+            true
+        }
+    }
+
+    /// Returns the new offline state if a change has lasted for `OFFLINE_STATE_DEBOUNCE`.
+    fn poll(&mut self, now: Instant) -> Option<bool> {
+        match self.pending_since {
+            Some(since) if now.duration_since(since) >= OFFLINE_STATE_DEBOUNCE => {
+                self.pending_since = None;
+                self.notified = !self.notified;
+                Some(self.notified)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Spaces out automatic reconnection attempts exponentially.
+#[derive(Default)]
+struct ReconnectBackoff {
+    attempt: u32,
+    next_attempt: Option<DateTime<Utc>>,
+}
+
+impl ReconnectBackoff {
+    /// Schedules another attempt relative to `now`, and returns how long to wait for it.
+    fn schedule(&mut self, now: DateTime<Utc>) -> Duration {
+        let delay = 2u32
+            .checked_pow(self.attempt)
+            .and_then(|factor| RECONNECT_BACKOFF_BASE.checked_mul(factor))
+            .map(|delay| delay.min(RECONNECT_BACKOFF_MAX))
+            .unwrap_or(RECONNECT_BACKOFF_MAX);
+        self.attempt = self.attempt.saturating_add(1);
+        self.next_attempt = Some(now + chrono::Duration::seconds(delay.as_secs() as i64));
+        delay
+    }
+
+    /// Forgets about the scheduled attempt, without resetting the delay.
+    fn unschedule(&mut self) {
+        self.next_attempt = None;
+    }
+
+    fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    fn status(&self) -> ReconnectStatus {
+        ReconnectStatus {
+            attempt: self.attempt,
+            next_attempt: self.next_attempt,
+        }
+    }
+}
+
+/// Keeps the most recent tunnel state transitions, dropping the oldest ones when full.
+struct StateHistory {
+    entries: VecDeque<StateHistoryEntry>,
+    capacity: usize,
+}
+
+impl StateHistory {
+    fn new(capacity: usize) -> Self {
+        StateHistory {
+            entries: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    fn push(&mut self, timestamp: DateTime<Utc>, state: TunnelState) {
+        if self.entries.len() >= self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries
+            .push_back(StateHistoryEntry { timestamp, state });
+    }
+
+    fn tail(&self, max_entries: usize) -> Vec<StateHistoryEntry> {
+        let skip = self.entries.len().saturating_sub(max_entries);
+        self.entries.iter().skip(skip).cloned().collect()
+    }
+}
+
+/// Account data fetched from the API, along with when it was fetched.
+struct CachedAccountData {
+    account_token: AccountToken,
+    account_data: AccountData,
+    fetched_at: Instant,
 }
 
 pub struct Daemon<L: EventListener> {
     tunnel_command_tx: Arc<UnboundedSender<TunnelCommand>>,
     tunnel_state: TunnelState,
+    state_history: StateHistory,
     target_state: TargetState,
     state: DaemonExecutionState,
     #[cfg(target_os = "linux")]
     exclude_pids: split_tunnel::PidManager,
-    rx: Wait<UnboundedReceiver<InternalDaemonEvent>>,
+    rx: Wait<InternalDaemonEventReceiver>,
     tx: DaemonEventSender,
     reconnection_loop_tx: Option<mpsc::Sender<()>>,
+    reconnect_backoff: ReconnectBackoff,
+    tunnel_stats_sampler_tx: Option<mpsc::Sender<()>>,
+    connect_timer_tx: Option<mpsc::Sender<()>>,
+    last_error: Option<ErrorState>,
+    /// True when the tunnel state machine reports that the computer is offline.
+    is_offline: bool,
+    offline_state_debouncer: OfflineStateDebouncer,
     event_listener: L,
     settings: SettingsPersister,
     account_history: account_history::AccountHistory,
+    /// The most recently fetched data for the current account.
+    account_data_cache: Option<CachedAccountData>,
+    /// The expiry status that frontends were last notified about.
+    last_expiry_status: Option<ExpiryStatus>,
     accounts_proxy: AccountsProxy,
     rpc_runtime: mullvad_rpc::MullvadRpcRuntime,
     rpc_handle: mullvad_rpc::rest::MullvadRestHandle,
@@ -472,6 +870,9 @@ pub struct Daemon<L: EventListener> {
     relay_selector: relays::RelaySelector,
     last_generated_relay: Option<Relay>,
     last_generated_bridge_relay: Option<Relay>,
+    /// Relay to use for the next tunnel instead of the relay settings.
+    one_shot_relay: Option<CustomTunnelEndpoint>,
+    geoip_cache: geoip::GeoIpCache,
     app_version_info: AppVersionInfo,
     shutdown_callbacks: Vec<Box<dyn FnOnce()>>,
     /// oneshot channel that completes once the tunnel state machine has been shut down
@@ -499,24 +900,27 @@ where
             .map_err(Error::InitRpcFactory)?;
         let rpc_handle = rpc_runtime.mullvad_rest_handle();
 
+        let mut settings = SettingsPersister::load(&settings_dir);
+        if settings.api_endpoint.is_some() {
+            apply_api_endpoint(rpc_handle.factory(), settings.api_endpoint);
+        }
+
         let core_handle = event_loop::spawn();
 
         let relay_list_listener = event_listener.clone();
         let on_relay_list_update = move |relay_list: &RelayList| {
             relay_list_listener.notify_relay_list(relay_list.clone());
         };
-        let relay_selector = relays::RelaySelector::new(
+        let mut relay_selector = relays::RelaySelector::new(
             rpc_handle.clone(),
             on_relay_list_update,
             &resource_dir,
             &cache_dir,
         );
+        relay_selector.set_relay_prober(relay_prober(settings.probe_before_connect));
 
         let (internal_event_tx, internal_event_rx) = command_channel.destructure();
 
-
-        let mut settings = SettingsPersister::load(&settings_dir);
-
         if version::is_beta_version() {
             let _ = settings.set_show_beta_releases(true);
         }
@@ -560,11 +964,13 @@ where
         let tunnel_command_tx = tunnel_state_machine::spawn(
             settings.allow_lan,
             settings.block_when_disconnected,
+            settings.tunnel_options.dns_servers(),
             tunnel_parameters_generator,
             log_dir,
             resource_dir,
             cache_dir.clone(),
             internal_event_tx.to_specialized_sender(),
+            OfflineStateListener(internal_event_tx.clone()),
             tunnel_state_machine_shutdown_tx,
             #[cfg(target_os = "android")]
             android_context,
@@ -592,7 +998,10 @@ where
 
         let mut daemon = Daemon {
             tunnel_command_tx,
-            tunnel_state: TunnelState::Disconnected,
+            tunnel_state: TunnelState::Disconnected {
+                blocking: settings.block_when_disconnected,
+            },
+            state_history: StateHistory::new(STATE_HISTORY_CAPACITY),
             target_state: initial_target_state,
             state: DaemonExecutionState::Running,
             #[cfg(target_os = "linux")]
@@ -600,9 +1009,17 @@ where
             rx: internal_event_rx.wait(),
             tx: internal_event_tx,
             reconnection_loop_tx: None,
+            reconnect_backoff: ReconnectBackoff::default(),
+            tunnel_stats_sampler_tx: None,
+            connect_timer_tx: None,
+            last_error: None,
+            is_offline: false,
+            offline_state_debouncer: OfflineStateDebouncer::new(false),
             event_listener,
             settings,
             account_history,
+            account_data_cache: None,
+            last_expiry_status: None,
             rpc_runtime,
             accounts_proxy: AccountsProxy::new(rpc_handle.clone()),
             rpc_handle,
@@ -612,6 +1029,8 @@ where
             relay_selector,
             last_generated_relay: None,
             last_generated_bridge_relay: None,
+            one_shot_relay: None,
+            geoip_cache: geoip::GeoIpCache::default(),
             app_version_info,
             shutdown_callbacks: vec![],
             tunnel_state_machine_shutdown_signal,
@@ -619,6 +1038,7 @@ where
         };
 
         daemon.ensure_wireguard_keys_for_current_account();
+        Self::spawn_account_expiry_checker(daemon.tx.clone());
 
         if let Some(token) = daemon.settings.get_account_token() {
             daemon.wireguard_key_manager.set_rotation_interval(
@@ -636,6 +1056,17 @@ where
         Ok(daemon)
     }
 
+    fn spawn_account_expiry_checker(daemon_tx: DaemonEventSender) {
+        thread::spawn(move || {
+            while daemon_tx
+                .send(InternalDaemonEvent::CheckAccountExpiry)
+                .is_ok()
+            {
+                thread::sleep(ACCOUNT_EXPIRY_CHECK_INTERVAL);
+            }
+        });
+    }
+
     /// Consume the `Daemon` and run the main event loop. Blocks until an error happens or a
     /// shutdown event is received.
     pub fn run(mut self) -> Result<(), Error> {
@@ -711,6 +1142,47 @@ where
             NewAppVersionInfo(app_version_info) => {
                 self.handle_new_app_version_info(app_version_info)
             }
+            NewAccountData(account_token, account_data) => {
+                self.handle_new_account_data(account_token, account_data)
+            }
+            CheckAccountExpiry => self.handle_check_account_expiry(),
+            SampleTunnelStats => self.handle_sample_tunnel_stats(),
+            OfflineStateChange(is_offline) => self.handle_offline_state_change(is_offline),
+            CheckOfflineState => self.handle_check_offline_state(),
+            ConnectTimeout => self.handle_connect_timeout(),
+        }
+    }
+
+    fn handle_offline_state_change(&mut self, is_offline: bool) {
+        if self.is_offline == is_offline {
+            return;
+        }
+        if is_offline {
+            info!("The computer is offline");
+        } else {
+            info!("The computer is online");
+        }
+        self.is_offline = is_offline;
+
+        if self
+            .offline_state_debouncer
+            .update(is_offline, Instant::now())
+        {
+            let tx = self.tx.clone();
+            thread::spawn(move || {
+                thread::sleep(OFFLINE_STATE_DEBOUNCE);
+                let _ = tx.send(InternalDaemonEvent::CheckOfflineState);
+            });
+        }
+    }
+
+    fn handle_check_offline_state(&mut self) {
+        if let Some(is_offline) = self.offline_state_debouncer.poll(Instant::now()) {
+            let connectivity = Connectivity::new(
+                is_offline,
+                self.rpc_handle.service().last_request_reached_host(),
+            );
+            self.event_listener.notify_connectivity(connectivity);
         }
     }
 
@@ -722,12 +1194,16 @@ where
                 // Reset the RPCs so that they fail immediately after the underlying socket gets
                 // invalidated due to the tunnel either coming up or breaking.
                 self.rpc_handle.service().reset();
+                // The public IP most likely changed.
+                self.geoip_cache.clear();
             }
             _ => (),
         };
 
         let tunnel_state = match tunnel_state_transition {
-            TunnelStateTransition::Disconnected => TunnelState::Disconnected,
+            TunnelStateTransition::Disconnected => TunnelState::Disconnected {
+                blocking: self.settings.block_when_disconnected,
+            },
             TunnelStateTransition::Connecting(endpoint) => TunnelState::Connecting {
                 endpoint,
                 location: self.build_location_from_relay(),
@@ -735,6 +1211,7 @@ where
             TunnelStateTransition::Connected(endpoint) => TunnelState::Connected {
                 endpoint,
                 location: self.build_location_from_relay(),
+                connected_since: Some(Utc::now()),
             },
             TunnelStateTransition::Disconnecting(after_disconnect) => {
                 TunnelState::Disconnecting(after_disconnect)
@@ -744,11 +1221,29 @@ where
 
 
         self.unschedule_reconnect();
+        self.stop_tunnel_stats_sampler();
+        if let TunnelState::Connected { .. } = tunnel_state {
+            self.start_tunnel_stats_sampler();
+        }
+        match tunnel_state {
+            TunnelState::Connecting { .. } => self.start_connect_timer(),
+            // The tunnel state machine is retrying, which is part of the same attempt.
+            TunnelState::Disconnecting(ActionAfterDisconnect::Reconnect) => (),
+            _ => self.stop_connect_timer(),
+        }
 
         debug!("New tunnel state: {:?}", tunnel_state);
         match tunnel_state {
-            TunnelState::Disconnected => self.state.disconnected(),
+            TunnelState::Disconnected { .. } => {
+                self.state.disconnected();
+                self.reconnect_backoff.reset();
+            }
+            TunnelState::Connected { .. } => {
+                self.last_error = None;
+                self.reconnect_backoff.reset();
+            }
             TunnelState::Error(ref error_state) => {
+                self.last_error = Some(error_state.clone());
                 if error_state.is_blocking() {
                     info!(
                         "Blocking all network connections, reason: {}",
@@ -761,15 +1256,50 @@ where
                     );
                 }
 
-                if let ErrorStateCause::AuthFailed(_) = error_state.cause() {
-                    self.schedule_reconnect(Duration::from_secs(60))
+                if self.target_state == TargetState::Secured {
+                    match error_state.cause() {
+                        // The tunnel state machine reconnects by itself once back online, and
+                        // a connect timeout is meant to end the attempt.
+                        ErrorStateCause::IsOffline | ErrorStateCause::ConnectTimeout => (),
+                        _ => {
+                            let delay = self.reconnect_backoff.schedule(Utc::now());
+                            info!("Reconnecting in {} seconds", delay.as_secs());
+                            self.schedule_reconnect(delay);
+                        }
+                    }
                 }
             }
             _ => {}
         }
 
+        let look_up_location = match tunnel_state {
+            TunnelState::Connected { .. } | TunnelState::Disconnected { .. } => true,
+            _ => false,
+        };
+
+        let progress = ConnectionProgress::from_transition(&self.tunnel_state, &tunnel_state);
+
         self.tunnel_state = tunnel_state.clone();
+        self.state_history.push(Utc::now(), tunnel_state.clone());
         self.event_listener.notify_new_state(tunnel_state);
+        if let Some(progress) = progress {
+            self.event_listener.notify_connection_progress(progress);
+        }
+
+        if look_up_location {
+            self.broadcast_location();
+        }
+    }
+
+    /// Performs a GeoIP lookup for the current tunnel state and broadcasts the result.
+    fn broadcast_location(&mut self) {
+        let event_listener = self.event_listener.clone();
+        let lookup = self.get_current_location().map(move |location| {
+            if let Some(location) = location {
+                event_listener.notify_location(location);
+            }
+        });
+        self.core_handle.remote.spawn(move |_| lookup);
     }
 
     fn handle_generate_tunnel_parameters(
@@ -777,8 +1307,12 @@ where
         tunnel_parameters_tx: &mpsc::Sender<Result<TunnelParameters, ParameterGenerationError>>,
         retry_attempt: u32,
     ) {
+        self.event_listener
+            .notify_connection_progress(ConnectionProgress::SelectingRelay);
         if let Some(account_token) = self.settings.get_account_token() {
-            let result = match self.settings.get_relay_settings() {
+            let relay_settings =
+                next_relay_settings(&mut self.one_shot_relay, self.settings.get_relay_settings());
+            let result = match relay_settings {
                 RelaySettings::CustomTunnelEndpoint(custom_relay) => {
                     self.last_generated_relay = None;
                     custom_relay
@@ -793,6 +1327,8 @@ where
                     .relay_selector
                     .get_tunnel_endpoint(
                         &constraints,
+                        &self.settings.relay_filter,
+                        self.settings.relay_selection_mode,
                         self.settings.get_bridge_state(),
                         retry_attempt,
                         self.account_history
@@ -887,7 +1423,7 @@ where
                         match self.settings.get_bridge_state() {
                             BridgeState::On => Some(proxy_settings.clone()),
                             BridgeState::Auto => {
-                                if self.relay_selector.should_use_bridge(retry_attempt) {
+                                if relays::RelaySelector::should_use_bridge(retry_attempt) {
                                     Some(proxy_settings.clone())
                                 } else {
                                     None
@@ -966,6 +1502,7 @@ where
         if let Some(tx) = self.reconnection_loop_tx.take() {
             let _ = tx.send(());
         }
+        self.reconnect_backoff.unschedule();
     }
 
     fn handle_command(&mut self, command: DaemonCommand) {
@@ -976,44 +1513,102 @@ where
         }
         match command {
             SetTargetState(tx, state) => self.on_set_target_state(tx, state),
-            Reconnect => self.on_reconnect(),
+            Reconnect(tx) => self.on_reconnect(tx),
+            ConnectTo(tx, location) => self.on_connect_to(tx, location),
+            ConnectOnce(tx, relay) => self.on_connect_once(tx, relay),
             GetState(tx) => self.on_get_state(tx),
+            GetFirewallState(tx) => self.on_get_firewall_state(tx),
+            GetStateHistory(tx, max_entries) => self.on_get_state_history(tx, max_entries),
+            GetCurrentRelay(tx) => self.on_get_current_relay(tx),
+            PreviewRelaySelection(tx) => self.on_preview_relay_selection(tx),
+            GetConnectivity(tx) => self.on_get_connectivity(tx),
+            GetReconnectStatus(tx) => self.on_get_reconnect_status(tx),
+            GetStatusSnapshot(tx) => self.on_get_status_snapshot(tx),
             GetCurrentLocation(tx) => self.on_get_current_location(tx),
+            GetPublicIp(tx) => self.on_get_public_ip(tx),
+            GetTunnelStats(tx) => self.on_get_tunnel_stats(tx),
+            GetLastError(tx) => self.on_get_last_error(tx),
             CreateNewAccount(tx) => self.on_create_new_account(tx),
             GetAccountData(tx, account_token) => self.on_get_account_data(tx, account_token),
+            GetAccountExpiry(tx) => self.on_get_account_expiry(tx),
             GetWwwAuthToken(tx) => self.on_get_www_auth_token(tx),
             SubmitVoucher(tx, voucher) => self.on_submit_voucher(tx, voucher),
             GetRelayLocations(tx) => self.on_get_relay_locations(tx),
+            GetRelayCountries(tx) => self.on_get_relay_countries(tx),
+            GetRelayLocationCounts(tx) => self.on_get_relay_location_counts(tx),
+            MeasureRelayLatency(tx, location) => self.on_measure_relay_latency(tx, location),
+            ConnectFastest(tx, location) => self.on_connect_fastest(tx, location),
             UpdateRelayLocations => self.on_update_relay_locations(),
+            UpdateRelayList(tx) => self.on_update_relay_list(tx),
             SetAccount(tx, account_token) => self.on_set_account(tx, account_token),
+            Logout(tx) => self.on_logout(tx),
             GetAccountHistory(tx) => self.on_get_account_history(tx),
             RemoveAccountFromHistory(tx, account_token) => {
                 self.on_remove_account_from_history(tx, account_token)
             }
             ClearAccountHistory(tx) => self.on_clear_account_history(tx),
             UpdateRelaySettings(tx, update) => self.on_update_relay_settings(tx, update),
+            ApplySettings(tx, patch) => self.on_apply_settings(tx, patch),
+            AddCustomRelay(tx, name, endpoint) => self.on_add_custom_relay(tx, name, endpoint),
+            RemoveCustomRelay(tx, name) => self.on_remove_custom_relay(tx, name),
+            GetCustomRelays(tx) => self.on_get_custom_relays(tx),
+            SetActiveCustomRelay(tx, name) => self.on_set_active_custom_relay(tx, name),
+            SetLocationConstraint(tx, location) => self.on_set_location_constraint(tx, location),
+            SetOpenVpnConstraints(tx, constraints) => {
+                self.on_set_openvpn_constraints(tx, constraints)
+            }
             SetAllowLan(tx, allow_lan) => self.on_set_allow_lan(tx, allow_lan),
             SetShowBetaReleases(tx, enabled) => self.on_set_show_beta_releases(tx, enabled),
             SetBlockWhenDisconnected(tx, block_when_disconnected) => {
                 self.on_set_block_when_disconnected(tx, block_when_disconnected)
             }
             SetAutoConnect(tx, auto_connect) => self.on_set_auto_connect(tx, auto_connect),
+            GetAutoConnect(tx) => self.on_get_auto_connect(tx),
+            SetConnectTimeout(tx, connect_timeout) => {
+                self.on_set_connect_timeout(tx, connect_timeout)
+            }
+            GetConnectTimeout(tx) => self.on_get_connect_timeout(tx),
             SetOpenVpnMssfix(tx, mssfix_arg) => self.on_set_openvpn_mssfix(tx, mssfix_arg),
+            GetOpenVpnMssfix(tx) => self.on_get_openvpn_mssfix(tx),
+            SetOpenVpnCipher(tx, cipher) => self.on_set_openvpn_cipher(tx, cipher),
+            GetOpenVpnCipher(tx) => self.on_get_openvpn_cipher(tx),
             SetBridgeSettings(tx, bridge_settings) => {
                 self.on_set_bridge_settings(tx, bridge_settings)
             }
+            GetBridgeSettings(tx) => self.on_get_bridge_settings(tx),
             SetBridgeState(tx, bridge_state) => self.on_set_bridge_state(tx, bridge_state),
+            GetBridgeState(tx) => self.on_get_bridge_state(tx),
+            SetRelayFilter(tx, relay_filter) => self.on_set_relay_filter(tx, relay_filter),
+            GetRelayFilter(tx) => self.on_get_relay_filter(tx),
+            SetRelaySelectionMode(tx, mode) => self.on_set_relay_selection_mode(tx, mode),
+            GetRelaySelectionMode(tx) => self.on_get_relay_selection_mode(tx),
+            SetProbeBeforeConnect(tx, probe) => self.on_set_probe_before_connect(tx, probe),
+            GetProbeBeforeConnect(tx) => self.on_get_probe_before_connect(tx),
             SetEnableIpv6(tx, enable_ipv6) => self.on_set_enable_ipv6(tx, enable_ipv6),
+            GetEnableIpv6(tx) => self.on_get_enable_ipv6(tx),
+            SetCustomDns(tx, servers) => self.on_set_custom_dns(tx, servers),
+            GetCustomDns(tx) => self.on_get_custom_dns(tx),
+            SetDnsBlockers(tx, blockers) => self.on_set_dns_blockers(tx, blockers),
+            GetDnsBlockers(tx) => self.on_get_dns_blockers(tx),
             SetWireguardMtu(tx, mtu) => self.on_set_wireguard_mtu(tx, mtu),
             SetWireguardRotationInterval(tx, interval) => {
                 self.on_set_wireguard_rotation_interval(tx, interval)
             }
             GetSettings(tx) => self.on_get_settings(tx),
+            GetRelaySettings(tx) => self.on_get_relay_settings(tx),
             GenerateWireguardKey(tx) => self.on_generate_wireguard_key(tx),
             GetWireguardKey(tx) => self.on_get_wireguard_key(tx),
             VerifyWireguardKey(tx) => self.on_verify_wireguard_key(tx),
             GetVersionInfo(tx) => self.on_get_version_info(tx),
             GetCurrentVersion(tx) => self.on_get_current_version(tx),
+            GetRecentLogs(tx, max_lines) => self.on_get_recent_logs(tx, max_lines),
+            SetLogLevel(tx, level) => self.on_set_log_level(tx, level),
+            GetLogLevel(tx) => self.on_get_log_level(tx),
+            ResetSettings(tx) => self.on_reset_settings(tx),
+            ReloadSettings(tx) => self.on_reload_settings(tx),
+            ClearCache(tx) => self.on_clear_cache(tx),
+            SetApiEndpoint(tx, endpoint) => self.on_set_api_endpoint(tx, endpoint),
+            GetApiEndpoint(tx) => self.on_get_api_endpoint(tx),
             #[cfg(not(target_os = "android"))]
             FactoryReset(tx) => self.on_factory_reset(tx),
             #[cfg(target_os = "linux")]
@@ -1024,8 +1619,11 @@ where
             RemoveSplitTunnelProcess(tx, pid) => self.on_remove_split_tunnel_process(tx, pid),
             #[cfg(target_os = "linux")]
             ClearSplitTunnelProcesses(tx) => self.on_clear_split_tunnel_processes(tx),
+            GetSplitTunnelApps(tx) => self.on_get_split_tunnel_apps(tx),
+            AddSplitTunnelApp(tx, path) => self.on_add_split_tunnel_app(tx, path),
+            RemoveSplitTunnelApp(tx, path) => self.on_remove_split_tunnel_app(tx, path),
             Shutdown => self.trigger_shutdown_event(),
-            PrepareRestart => self.on_prepare_restart(),
+            PrepareRestart(tx) => self.on_prepare_restart(tx),
         }
     }
 
@@ -1127,144 +1725,650 @@ where
         Self::oneshot_send(tx, Ok(()), "target state");
     }
 
-    fn on_reconnect(&mut self) {
-        if self.target_state == TargetState::Secured || self.tunnel_state.is_in_error_state() {
+    fn on_reconnect(&mut self, tx: oneshot::Sender<Result<(), ()>>) {
+        if self.settings.get_account_token().is_none() {
+            warn!("Ignoring reconnect command since no account token is configured");
+            Self::oneshot_send(tx, Err(()), "reconnect response");
+            return;
+        }
+        if !self.state.is_running() {
+            warn!("Ignoring reconnect command due to shutdown");
+        } else if self.target_state == TargetState::Secured || self.tunnel_state.is_in_error_state()
+        {
             self.connect_tunnel();
         } else {
-            debug!("Ignoring reconnect command. Currently not in secured state");
+            self.set_target_state(TargetState::Secured);
         }
+        Self::oneshot_send(tx, Ok(()), "reconnect response");
     }
 
-    fn on_get_state(&self, tx: oneshot::Sender<TunnelState>) {
-        Self::oneshot_send(tx, self.tunnel_state.clone(), "current state");
-    }
-
-    fn on_get_current_location(&mut self, tx: oneshot::Sender<Option<GeoIpLocation>>) {
-        use self::TunnelState::*;
-        let get_location: Box<dyn Future<Item = Option<GeoIpLocation>, Error = ()> + Send> =
-            match &self.tunnel_state {
-                Disconnected => Box::new(self.get_geo_location().map(Some)),
-                Connecting { location, .. } => Box::new(future::result(Ok(location.clone()))),
-                Disconnecting(..) => Box::new(future::result(Ok(self.build_location_from_relay()))),
-                Connected { location, .. } => {
-                    let relay_location = location.clone();
-                    Box::new(
-                        self.get_geo_location()
-                            .map(|fetched_location| GeoIpLocation {
-                                ipv4: fetched_location.ipv4,
-                                ipv6: fetched_location.ipv6,
-                                ..relay_location.unwrap_or(fetched_location)
-                            })
-                            .map(Some),
-                    )
+    fn on_connect_to(
+        &mut self,
+        tx: oneshot::Sender<Result<(), ()>>,
+        location: Constraint<LocationConstraint>,
+    ) {
+        if !self.relay_selector.has_active_relays(&location) {
+            Self::oneshot_send(tx, Err(()), "connect_to response");
+            return;
+        }
+        let update = RelaySettingsUpdate::Normal(RelayConstraintsUpdate {
+            location: Some(location),
+            ..Default::default()
+        });
+        match self.settings.update_relay_settings(update) {
+            Ok(settings_changed) => {
+                Self::oneshot_send(tx, Ok(()), "connect_to response");
+                if settings_changed {
+                    self.event_listener
+                        .notify_settings(self.settings.to_settings());
                 }
-                Error(..) => {
-                    // We are not online at all at this stage so no location data is available.
-                    Box::new(future::result(Ok(None)))
+                if self.target_state == TargetState::Secured {
+                    if settings_changed {
+                        info!("Initiating tunnel restart because the relay location changed");
+                        self.connect_tunnel();
+                    }
+                } else if self.state.is_running() {
+                    self.set_target_state(TargetState::Secured);
+                } else {
+                    warn!("Ignoring connect_to command due to shutdown");
                 }
-            };
-
-        self.core_handle.remote.spawn(move |_| {
-            get_location.map(|location| Self::oneshot_send(tx, location, "current location"))
-        });
+            }
+            Err(e) => error!("{}", e.display_chain_with_msg("Unable to save settings")),
+        }
     }
 
-    fn get_geo_location(&mut self) -> impl Future<Item = GeoIpLocation, Error = ()> {
-        let https_handle = self.rpc_runtime.rest_handle();
+    fn on_connect_once(
+        &mut self,
+        tx: oneshot::Sender<Result<(), ()>>,
+        relay: CustomTunnelEndpoint,
+    ) {
+        if self.settings.get_account_token().is_none() {
+            warn!("Ignoring connect_once command since no account token is configured");
+            Self::oneshot_send(tx, Err(()), "connect_once response");
+            return;
+        }
+        if !self.state.is_running() {
+            warn!("Ignoring connect_once command due to shutdown");
+        } else {
+            info!("Connecting once to {}", relay.endpoint());
+            self.one_shot_relay = Some(relay);
+            if self.target_state == TargetState::Secured || self.tunnel_state.is_in_error_state() {
+                self.connect_tunnel();
+            } else {
+                self.set_target_state(TargetState::Secured);
+            }
+        }
+        Self::oneshot_send(tx, Ok(()), "connect_once response");
+    }
 
-        geoip::send_location_request(https_handle).map_err(|e| {
-            warn!("Unable to fetch GeoIP location: {}", e.display_chain());
-        })
+    fn on_get_state(&self, tx: oneshot::Sender<TunnelState>) {
+        Self::oneshot_send(tx, self.tunnel_state.clone(), "current state");
     }
 
-    fn build_location_from_relay(&self) -> Option<GeoIpLocation> {
-        let relay = self.last_generated_relay.as_ref()?;
-        let bridge_hostname = self
-            .last_generated_bridge_relay
-            .as_ref()
-            .map(|bridge| bridge.hostname.clone());
-        let location = relay.location.as_ref().cloned().unwrap();
-        let hostname = relay.hostname.clone();
+    fn on_get_firewall_state(&self, tx: oneshot::Sender<FirewallState>) {
+        Self::oneshot_send(
+            tx,
+            FirewallState::from_tunnel_state(
+                &self.tunnel_state,
+                self.settings.allow_lan,
+                self.settings.tunnel_options.generic.enable_ipv6,
+            ),
+            "firewall state",
+        );
+    }
 
-        Some(GeoIpLocation {
-            ipv4: None,
-            ipv6: None,
-            country: location.country,
-            city: Some(location.city),
-            latitude: location.latitude,
-            longitude: location.longitude,
-            mullvad_exit_ip: true,
-            hostname: Some(hostname),
-            bridge_hostname,
-        })
+    fn on_get_current_relay(&self, tx: oneshot::Sender<Option<TunnelEndpoint>>) {
+        Self::oneshot_send(tx, self.tunnel_state.connected_endpoint(), "current relay");
     }
 
-    fn on_create_new_account(
-        &mut self,
-        tx: oneshot::Sender<Result<String, mullvad_rpc::rest::Error>>,
-    ) {
-        let daemon_tx = self.tx.clone();
-        let future = self
-            .accounts_proxy
-            .create_account()
-            .then(move |result| -> Result<(), ()> {
-                match result {
-                    Ok(account_token) => {
-                        let _ =
-                            daemon_tx.send(InternalDaemonEvent::NewAccountEvent(account_token, tx));
-                    }
-                    Err(err) => {
-                        let _ = tx.send(Err(err));
-                    }
+    fn on_preview_relay_selection(&mut self, tx: oneshot::Sender<Option<RelayEndpoint>>) {
+        let preview = match self.settings.get_relay_settings() {
+            RelaySettings::CustomTunnelEndpoint(_) => None,
+            RelaySettings::Normal(constraints) => {
+                let wg_key_exists = match self.settings.get_account_token() {
+                    Some(token) => self
+                        .account_history
+                        .get(&token)
+                        .unwrap_or(None)
+                        .and_then(|entry| entry.wireguard)
+                        .is_some(),
+                    None => false,
                 };
-                Ok(())
-            });
-
-        if self.core_handle.remote.execute(future).is_err() {
-            log::error!("Failed to spawn future for creating a new account");
-        }
+                self.relay_selector.preview_tunnel_endpoint(
+                    &constraints,
+                    &self.settings.relay_filter,
+                    self.settings.relay_selection_mode,
+                    self.settings.get_bridge_state(),
+                    wg_key_exists,
+                )
+            }
+        };
+        Self::oneshot_send(tx, preview, "relay selection preview");
     }
 
-    fn on_get_account_data(
-        &mut self,
-        tx: oneshot::Sender<BoxFuture<AccountData, mullvad_rpc::rest::Error>>,
-        account_token: AccountToken,
-    ) {
-        let rpc_call = self
-            .accounts_proxy
-            .get_expiry(account_token)
-            .map(|expiry| AccountData { expiry });
-        Self::oneshot_send(tx, Box::new(rpc_call), "account data")
+    fn on_get_connectivity(&self, tx: oneshot::Sender<Connectivity>) {
+        let connectivity = Connectivity::new(
+            self.is_offline,
+            self.rpc_handle.service().last_request_reached_host(),
+        );
+        Self::oneshot_send(tx, connectivity, "connectivity");
     }
 
-    fn on_get_www_auth_token(
-        &mut self,
-        tx: oneshot::Sender<BoxFuture<String, mullvad_rpc::rest::Error>>,
+    fn on_get_state_history(
+        &self,
+        tx: oneshot::Sender<Vec<StateHistoryEntry>>,
+        max_entries: usize,
     ) {
-        if let Some(account_token) = self.settings.get_account_token() {
-            let rpc_call = self.accounts_proxy.get_www_auth_token(account_token);
-            Self::oneshot_send(tx, Box::new(rpc_call), "get_www_auth_token response")
-        }
+        Self::oneshot_send(
+            tx,
+            self.state_history.tail(max_entries),
+            "get_state_history response",
+        );
     }
 
-    fn on_submit_voucher(
-        &mut self,
-        tx: oneshot::Sender<BoxFuture<VoucherSubmission, mullvad_rpc::rest::Error>>,
-        voucher: String,
-    ) {
-        if let Some(account_token) = self.settings.get_account_token() {
-            let rpc_call = self.accounts_proxy.submit_voucher(account_token, voucher);
-            Self::oneshot_send(tx, Box::new(rpc_call), "submit_voucher response");
-        }
+    fn on_get_reconnect_status(&self, tx: oneshot::Sender<ReconnectStatus>) {
+        Self::oneshot_send(tx, self.reconnect_backoff.status(), "reconnect status");
     }
 
-    fn on_get_relay_locations(&mut self, tx: oneshot::Sender<RelayList>) {
-        Self::oneshot_send(tx, self.relay_selector.get_locations(), "relay locations");
+    fn on_get_status_snapshot(&mut self, tx: oneshot::Sender<StatusSnapshot>) {
+        let state = self.tunnel_state.clone();
+        // Only cached account data is used, so that polling the snapshot never hits the API.
+        let account_token = self.settings.get_account_token();
+        let account_expiry = self
+            .account_data_cache
+            .as_ref()
+            .filter(|cache| Some(&cache.account_token) == account_token.as_ref())
+            .map(|cache| AccountExpiry::new(cache.account_data.expiry, Utc::now()));
+        let reconnect_status = self.reconnect_backoff.status();
+        let response = self.get_tunnel_stats().map(move |tunnel_stats| {
+            let snapshot =
+                StatusSnapshot::new(state, tunnel_stats, account_expiry, reconnect_status);
+            Self::oneshot_send(tx, snapshot, "status snapshot");
+        });
+        self.core_handle.remote.spawn(move |_| response);
     }
 
-    fn on_update_relay_locations(&mut self) {
-        self.relay_selector.update();
-    }
+    fn on_get_current_location(&mut self, tx: oneshot::Sender<Option<GeoIpLocation>>) {
+        let get_location = self.get_current_location();
+        self.core_handle.remote.spawn(move |_| {
+            get_location.map(|location| Self::oneshot_send(tx, location, "current location"))
+        });
+    }
+
+    fn get_current_location(
+        &mut self,
+    ) -> Box<dyn Future<Item = Option<GeoIpLocation>, Error = ()> + Send> {
+        use self::TunnelState::*;
+        match &self.tunnel_state {
+            Disconnected { .. } => Box::new(self.get_cached_geo_location().map(Some)),
+            Connecting { location, .. } => Box::new(future::result(Ok(location.clone()))),
+            Disconnecting(..) => Box::new(future::result(Ok(self.build_location_from_relay()))),
+            Connected { location, .. } => {
+                let relay_location = location.clone();
+                Box::new(
+                    self.get_cached_geo_location()
+                        .map(|fetched_location| GeoIpLocation {
+                            ipv4: fetched_location.ipv4,
+                            ipv6: fetched_location.ipv6,
+                            stale: fetched_location.stale,
+                            ..relay_location.unwrap_or(fetched_location)
+                        })
+                        .map(Some),
+                )
+            }
+            Error(..) => {
+                // We are not online at all at this stage so no location data is available.
+                Box::new(future::result(Ok(None)))
+            }
+        }
+    }
+
+    fn on_get_public_ip(&mut self, tx: oneshot::Sender<Option<PublicIp>>) {
+        let lookup = self.get_cached_geo_location().then(move |result| {
+            let public_ip = result.ok().map(|location| PublicIp::from(&location));
+            Self::oneshot_send(tx, public_ip, "public IP");
+            Ok(())
+        });
+
+        self.core_handle.remote.spawn(move |_| lookup);
+    }
+
+    fn on_get_tunnel_stats(&mut self, tx: oneshot::Sender<Option<TunnelStats>>) {
+        let response = self.get_tunnel_stats().map(|tunnel_stats| {
+            Self::oneshot_send(tx, tunnel_stats, "tunnel stats");
+        });
+        self.core_handle.remote.spawn(move |_| response);
+    }
+
+    fn on_get_last_error(&self, tx: oneshot::Sender<Option<ErrorState>>) {
+        Self::oneshot_send(tx, self.last_error.clone(), "last error");
+    }
+
+    /// Reads the traffic statistics of the connected tunnel. Resolves to `None` if not
+    /// connected, or if the tunnel doesn't report statistics.
+    fn get_tunnel_stats(
+        &mut self,
+    ) -> Box<dyn Future<Item = Option<TunnelStats>, Error = ()> + Send> {
+        match self.tunnel_state {
+            TunnelState::Connected { .. } => (),
+            _ => return Box::new(future::ok(None)),
+        }
+
+        let (stats_tx, stats_rx) = oneshot::channel();
+        self.send_tunnel_command(TunnelCommand::GetTunnelStats(stats_tx));
+
+        let tunnel_state = self.tunnel_state.clone();
+        // Only WireGuard tunnels report statistics, and changing the MTU reconnects them.
+        let mtu = self
+            .settings
+            .tunnel_options
+            .wireguard
+            .mtu
+            .unwrap_or(talpid_core::tunnel::wireguard::config::DEFAULT_MTU);
+        Box::new(stats_rx.then(move |result| {
+            Ok(result.ok().and_then(|stats| {
+                TunnelStats::new(
+                    &tunnel_state,
+                    stats.rx_bytes,
+                    stats.tx_bytes,
+                    stats.last_handshake_time.map(DateTime::<Utc>::from),
+                )
+                .map(|tunnel_stats| TunnelStats {
+                    mtu: Some(mtu),
+                    ..tunnel_stats
+                })
+            }))
+        }))
+    }
+
+    fn handle_sample_tunnel_stats(&mut self) {
+        if !self.event_listener.has_tunnel_stats_subscribers() {
+            return;
+        }
+        let event_listener = self.event_listener.clone();
+        let broadcast = self.get_tunnel_stats().map(move |tunnel_stats| {
+            if let Some(tunnel_stats) = tunnel_stats {
+                event_listener.notify_tunnel_stats(tunnel_stats);
+            }
+        });
+        self.core_handle.remote.spawn(move |_| broadcast);
+    }
+
+    /// Starts periodically sampling tunnel stats. The samples are only taken while there are
+    /// subscribers to them.
+    fn start_tunnel_stats_sampler(&mut self) {
+        let daemon_tx = self.tx.clone();
+        let (tx, rx) = mpsc::channel();
+
+        self.tunnel_stats_sampler_tx = Some(tx);
+
+        thread::spawn(move || {
+            while let Err(mpsc::RecvTimeoutError::Timeout) = rx.recv_timeout(TUNNEL_STATS_INTERVAL)
+            {
+                if daemon_tx
+                    .send(InternalDaemonEvent::SampleTunnelStats)
+                    .is_err()
+                {
+                    break;
+                }
+            }
+        });
+    }
+
+    fn stop_tunnel_stats_sampler(&mut self) {
+        if let Some(tx) = self.tunnel_stats_sampler_tx.take() {
+            let _ = tx.send(());
+        }
+    }
+
+    /// Starts timing the connection attempt, unless it is already being timed or there is no
+    /// connect timeout.
+    fn start_connect_timer(&mut self) {
+        if self.connect_timer_tx.is_none() {
+            if let Some(timeout) = self.settings.get_connect_timeout() {
+                self.connect_timer_tx = Some(spawn_connect_timer(self.tx.clone(), timeout));
+            }
+        }
+    }
+
+    fn stop_connect_timer(&mut self) {
+        if let Some(tx) = self.connect_timer_tx.take() {
+            let _ = tx.send(());
+        }
+    }
+
+    fn handle_connect_timeout(&mut self) {
+        // The timer may have fired right as the attempt ended.
+        if self.connect_timer_tx.take().is_none() {
+            return;
+        }
+        match self.tunnel_state {
+            TunnelState::Connecting { .. }
+            | TunnelState::Disconnecting(ActionAfterDisconnect::Reconnect) => {
+                warn!(
+                    "Failed to connect within {} seconds, giving up",
+                    self.settings.connect_timeout
+                );
+                self.send_tunnel_command(TunnelCommand::Block(ErrorStateCause::ConnectTimeout));
+            }
+            _ => (),
+        }
+    }
+
+    /// Returns the cached GeoIP location if it is recent enough, and performs a new lookup
+    /// otherwise. If the lookup fails, the last known location is returned and marked as stale.
+    fn get_cached_geo_location(
+        &mut self,
+    ) -> Box<dyn Future<Item = GeoIpLocation, Error = ()> + Send> {
+        if let Some(location) = self.geoip_cache.get_fresh() {
+            return Box::new(future::ok(location));
+        }
+
+        let geoip_cache = self.geoip_cache.clone();
+        Box::new(self.get_geo_location().then(move |result| {
+            match result {
+                Ok(location) => {
+                    geoip_cache.store(location.clone());
+                    Ok(location)
+                }
+                Err(()) => geoip_cache
+                    .get_last()
+                    .map(|location| GeoIpLocation {
+                        stale: true,
+                        ..location
+                    })
+                    .ok_or(()),
+            }
+        }))
+    }
+
+    fn get_geo_location(&mut self) -> impl Future<Item = GeoIpLocation, Error = ()> {
+        let https_handle = self.rpc_runtime.rest_handle();
+
+        geoip::send_location_request(https_handle).map_err(|e| {
+            warn!("Unable to fetch GeoIP location: {}", e.display_chain());
+        })
+    }
+
+    fn build_location_from_relay(&self) -> Option<GeoIpLocation> {
+        let relay = self.last_generated_relay.as_ref()?;
+        let bridge_hostname = self
+            .last_generated_bridge_relay
+            .as_ref()
+            .map(|bridge| bridge.hostname.clone());
+        let location = relay.location.as_ref().cloned().unwrap();
+        let hostname = relay.hostname.clone();
+
+        Some(GeoIpLocation {
+            ipv4: None,
+            ipv6: None,
+            country: location.country,
+            city: Some(location.city),
+            latitude: location.latitude,
+            longitude: location.longitude,
+            mullvad_exit_ip: true,
+            hostname: Some(hostname),
+            bridge_hostname,
+            stale: false,
+        })
+    }
+
+    fn on_create_new_account(
+        &mut self,
+        tx: oneshot::Sender<Result<String, mullvad_rpc::rest::Error>>,
+    ) {
+        let daemon_tx = self.tx.clone();
+        let future = self
+            .accounts_proxy
+            .create_account()
+            .then(move |result| -> Result<(), ()> {
+                match result {
+                    Ok(account_token) => {
+                        let _ =
+                            daemon_tx.send(InternalDaemonEvent::NewAccountEvent(account_token, tx));
+                    }
+                    Err(err) => {
+                        let _ = tx.send(Err(err));
+                    }
+                };
+                Ok(())
+            });
+
+        if self.core_handle.remote.execute(future).is_err() {
+            log::error!("Failed to spawn future for creating a new account");
+        }
+    }
+
+    fn on_get_account_data(
+        &mut self,
+        tx: oneshot::Sender<BoxFuture<AccountData, mullvad_rpc::rest::Error>>,
+        account_token: AccountToken,
+    ) {
+        let rpc_call = self.fetch_account_data(account_token);
+        Self::oneshot_send(tx, Box::new(rpc_call), "account data")
+    }
+
+    fn on_get_account_expiry(
+        &mut self,
+        tx: oneshot::Sender<BoxFuture<Option<AccountExpiry>, mullvad_rpc::rest::Error>>,
+    ) {
+        let account_token = match self.settings.get_account_token() {
+            Some(account_token) => account_token,
+            None => {
+                Self::oneshot_send(tx, Box::new(future::ok(None)), "account expiry");
+                return;
+            }
+        };
+
+        let account_data: BoxFuture<AccountData, mullvad_rpc::rest::Error> =
+            match &self.account_data_cache {
+                Some(cache) if cache.account_token == account_token => {
+                    Box::new(future::ok(cache.account_data.clone()))
+                }
+                _ => Box::new(self.fetch_account_data(account_token)),
+            };
+        let expiry = account_data
+            .map(|account_data| Some(AccountExpiry::new(account_data.expiry, Utc::now())));
+        Self::oneshot_send(tx, Box::new(expiry), "account expiry");
+    }
+
+    /// Fetches the account data from the API and updates the cached account data on success.
+    fn fetch_account_data(
+        &self,
+        account_token: AccountToken,
+    ) -> impl Future<Item = AccountData, Error = mullvad_rpc::rest::Error> + Send {
+        let daemon_tx = self.tx.clone();
+        self.accounts_proxy
+            .get_expiry(account_token.clone())
+            .map(move |expiry| {
+                let account_data = AccountData { expiry };
+                let _ = daemon_tx.send(InternalDaemonEvent::NewAccountData(
+                    account_token,
+                    account_data.clone(),
+                ));
+                account_data
+            })
+    }
+
+    fn handle_new_account_data(&mut self, account_token: AccountToken, account_data: AccountData) {
+        if self.settings.get_account_token().as_ref() == Some(&account_token) {
+            let account_expiry = AccountExpiry::new(account_data.expiry, Utc::now());
+            self.account_data_cache = Some(CachedAccountData {
+                account_token,
+                account_data,
+                fetched_at: Instant::now(),
+            });
+            self.last_expiry_status = Some(account_expiry.status);
+            self.event_listener.notify_account_expiry(account_expiry);
+        }
+    }
+
+    /// Refreshes the account data if the cached data is too old. Otherwise, notifies frontends if
+    /// the account has moved closer to expiring since they were last notified.
+    fn handle_check_account_expiry(&mut self) {
+        let account_token = match self.settings.get_account_token() {
+            Some(account_token) => account_token,
+            None => return,
+        };
+
+        match &self.account_data_cache {
+            Some(cache)
+                if cache.account_token == account_token
+                    && cache.fetched_at.elapsed() < ACCOUNT_DATA_REFRESH_INTERVAL =>
+            {
+                let account_expiry = AccountExpiry::new(cache.account_data.expiry, Utc::now());
+                if self.last_expiry_status != Some(account_expiry.status) {
+                    self.last_expiry_status = Some(account_expiry.status);
+                    self.event_listener.notify_account_expiry(account_expiry);
+                }
+            }
+            _ => {
+                let refresh = self
+                    .fetch_account_data(account_token)
+                    .map(|_| ())
+                    .map_err(|error| {
+                        log::error!(
+                            "{}",
+                            error.display_chain_with_msg("Failed to refresh account data")
+                        );
+                    });
+                self.core_handle.remote.spawn(move |_| refresh);
+            }
+        }
+    }
+
+    fn on_get_www_auth_token(
+        &mut self,
+        tx: oneshot::Sender<Option<BoxFuture<String, mullvad_rpc::rest::Error>>>,
+    ) {
+        match self.settings.get_account_token() {
+            Some(account_token) => {
+                let rpc_call = self.accounts_proxy.get_www_auth_token(account_token);
+                Self::oneshot_send(tx, Some(Box::new(rpc_call)), "get_www_auth_token response")
+            }
+            None => Self::oneshot_send(tx, None, "get_www_auth_token response"),
+        }
+    }
+
+    fn on_submit_voucher(
+        &mut self,
+        tx: oneshot::Sender<BoxFuture<VoucherSubmission, mullvad_rpc::rest::Error>>,
+        voucher: String,
+    ) {
+        if let Some(account_token) = self.settings.get_account_token() {
+            let daemon_tx = self.tx.clone();
+            let rpc_call = self
+                .accounts_proxy
+                .submit_voucher(account_token.clone(), voucher)
+                .map(move |submission| {
+                    let account_data = AccountData {
+                        expiry: submission.new_expiry,
+                    };
+                    let _ = daemon_tx.send(InternalDaemonEvent::NewAccountData(
+                        account_token,
+                        account_data,
+                    ));
+                    submission
+                });
+            Self::oneshot_send(tx, Box::new(rpc_call), "submit_voucher response");
+        }
+    }
+
+    fn on_get_relay_locations(&mut self, tx: oneshot::Sender<RelayList>) {
+        Self::oneshot_send(tx, self.relay_selector.get_locations(), "relay locations");
+    }
+
+    fn on_get_relay_countries(&mut self, tx: oneshot::Sender<BTreeMap<CountryCode, String>>) {
+        Self::oneshot_send(tx, self.relay_selector.get_countries(), "relay countries");
+    }
+
+    fn on_get_relay_location_counts(
+        &mut self,
+        tx: oneshot::Sender<BTreeMap<CountryCode, CountryRelayCount>>,
+    ) {
+        Self::oneshot_send(
+            tx,
+            self.relay_selector.get_relay_counts(),
+            "relay location counts",
+        );
+    }
+
+    fn on_measure_relay_latency(
+        &mut self,
+        tx: oneshot::Sender<Vec<RelayLatency>>,
+        location: Constraint<LocationConstraint>,
+    ) {
+        let relays = latency::relay_addresses(&self.relay_selector.get_active_relays(&location));
+        thread::spawn(move || {
+            let latencies = latency::measure_relays(
+                Arc::new(latency::TcpLatencyMeasurer),
+                relays,
+                latency::MEASUREMENT_TIMEOUT,
+            );
+            if tx.send(latencies).is_err() {
+                warn!("Unable to send relay latencies to the daemon command sender");
+            }
+        });
+    }
+
+    fn on_connect_fastest(
+        &mut self,
+        tx: oneshot::Sender<Option<RelayLatency>>,
+        location: Constraint<LocationConstraint>,
+    ) {
+        let relays = self.relay_selector.get_active_relays(&location);
+        let daemon_tx = self.tx.clone();
+        thread::spawn(move || {
+            let latencies = latency::measure_relays(
+                Arc::new(latency::TcpLatencyMeasurer),
+                latency::relay_addresses(&relays),
+                latency::MEASUREMENT_TIMEOUT,
+            );
+            let fastest = latency::fastest(&latencies).and_then(|fastest| {
+                let relay = relays
+                    .iter()
+                    .find(|relay| relay.hostname == fastest.hostname)?;
+                let relay_location = relay.location.as_ref()?;
+                Some((
+                    fastest.clone(),
+                    LocationConstraint::Hostname(
+                        relay_location.country_code.clone(),
+                        relay_location.city_code.clone(),
+                        relay.hostname.clone(),
+                    ),
+                ))
+            });
+            let result = match fastest {
+                Some((fastest, location)) => {
+                    info!("Connecting to {}, the fastest relay", fastest.hostname);
+                    let (connect_tx, connect_rx) = oneshot::channel();
+                    let command = DaemonCommand::ConnectTo(connect_tx, Constraint::Only(location));
+                    if daemon_tx
+                        .send(InternalDaemonEvent::Command(command))
+                        .is_err()
+                    {
+                        return;
+                    }
+                    match connect_rx.wait() {
+                        Ok(Ok(())) => Some(fastest),
+                        _ => None,
+                    }
+                }
+                None => None,
+            };
+            if tx.send(result).is_err() {
+                warn!("Unable to send connect_fastest response to the daemon command sender");
+            }
+        });
+    }
+
+    fn on_update_relay_locations(&mut self) {
+        self.relay_selector.update();
+    }
+
+    fn on_update_relay_list(&mut self, tx: oneshot::Sender<std::result::Result<(), ()>>) {
+        self.relay_selector.update_and_wait(tx);
+    }
 
     fn on_set_account(&mut self, tx: oneshot::Sender<()>, account_token: Option<String>) {
         match self.set_account(account_token.clone()) {
@@ -1289,28 +2393,51 @@ where
         }
     }
 
+    fn on_logout(&mut self, tx: oneshot::Sender<()>) {
+        info!("Logging out");
+        self.set_target_state(TargetState::Unsecured);
+        match self.set_account(None) {
+            Ok(_) => {
+                self.account_data_cache = None;
+                self.last_expiry_status = None;
+                Self::oneshot_send(tx, (), "logout response");
+            }
+            Err(e) => {
+                log::error!("Failed to log out - {}", e);
+            }
+        }
+    }
+
     fn set_account(&mut self, account_token: Option<String>) -> Result<bool, settings::Error> {
         let account_changed = self.settings.set_account_token(account_token.clone())?;
         if account_changed {
             self.event_listener
                 .notify_settings(self.settings.to_settings());
+            self.handle_account_change(account_token);
+        }
+        Ok(account_changed)
+    }
 
-            // Bump account history if a token was set
-            if let Some(token) = account_token.clone() {
-                if let Err(e) = self.account_history.bump_history(&token) {
-                    log::error!("Failed to bump account history: {}", e);
-                }
+    /// Resets account specific state after the account token in the settings has changed.
+    fn handle_account_change(&mut self, account_token: Option<String>) {
+        self.account_data_cache = None;
+        self.last_expiry_status = None;
+        self.event_listener.notify_account(account_token.clone());
+
+        // Bump account history if a token was set
+        if let Some(token) = account_token.clone() {
+            if let Err(e) = self.account_history.bump_history(&token) {
+                log::error!("Failed to bump account history: {}", e);
             }
+        }
 
-            self.ensure_wireguard_keys_for_current_account();
+        self.ensure_wireguard_keys_for_current_account();
 
-            if let Some(token) = account_token {
-                // update automatic rotation
-                self.wireguard_key_manager
-                    .reset_rotation(&mut self.account_history, token);
-            }
+        if let Some(token) = account_token {
+            // update automatic rotation
+            self.wireguard_key_manager
+                .reset_rotation(&mut self.account_history, token);
         }
-        Ok(account_changed)
     }
 
     fn on_get_account_history(&mut self, tx: oneshot::Sender<Vec<AccountToken>>) {
@@ -1360,6 +2487,79 @@ where
         );
     }
 
+    fn on_get_recent_logs(&self, tx: oneshot::Sender<Vec<String>>, max_lines: usize) {
+        Self::oneshot_send(
+            tx,
+            logging::recent_log_lines(max_lines),
+            "get_recent_logs response",
+        );
+    }
+
+    fn on_set_log_level(&self, tx: oneshot::Sender<()>, level: LogLevel) {
+        info!("Changing log level to {:?}", level);
+        logging::set_log_level(level);
+        Self::oneshot_send(tx, (), "set_log_level response");
+    }
+
+    fn on_get_log_level(&self, tx: oneshot::Sender<Option<LogLevel>>) {
+        Self::oneshot_send(tx, logging::get_log_level(), "get_log_level response");
+    }
+
+    fn on_reset_settings(&mut self, tx: oneshot::Sender<()>) {
+        let old_settings = self.settings.to_settings();
+        match self.settings.reset_keeping_account() {
+            Ok(()) => {
+                Self::oneshot_send(tx, (), "reset_settings response");
+                self.apply_changed_settings(&old_settings);
+            }
+            Err(e) => error!("{}", e.display_chain_with_msg("Unable to save settings")),
+        }
+    }
+
+    fn on_clear_cache(&mut self, tx: oneshot::Sender<Result<(), Error>>) {
+        self.geoip_cache.clear();
+        self.account_data_cache = None;
+
+        let result = remove_api_cache_files(&self.cache_dir);
+        match &result {
+            Ok(()) => {
+                log::info!("Cleared cached API data");
+                self.relay_selector.update();
+            }
+            Err(error) => log::error!(
+                "{}",
+                error.display_chain_with_msg("Failed to clear cached API data")
+            ),
+        }
+        Self::oneshot_send(tx, result, "clear_cache response");
+    }
+
+    fn on_set_api_endpoint(&mut self, tx: oneshot::Sender<()>, endpoint: Option<SocketAddr>) {
+        match self.settings.set_api_endpoint(endpoint) {
+            Ok(settings_changed) => {
+                Self::oneshot_send(tx, (), "set_api_endpoint response");
+                if settings_changed {
+                    self.event_listener
+                        .notify_settings(self.settings.to_settings());
+                    self.switch_api_endpoint();
+                }
+            }
+            Err(e) => error!("{}", e.display_chain_with_msg("Unable to save settings")),
+        }
+    }
+
+    fn on_get_api_endpoint(&self, tx: oneshot::Sender<Option<SocketAddr>>) {
+        Self::oneshot_send(tx, self.settings.api_endpoint, "get_api_endpoint response");
+    }
+
+    /// Starts using the API endpoint in the settings and refetches data from it, since data from
+    /// the previous API may not be valid for the new one.
+    fn switch_api_endpoint(&mut self) {
+        apply_api_endpoint(self.rpc_handle.factory(), self.settings.api_endpoint);
+        self.account_data_cache = None;
+        self.relay_selector.update();
+    }
+
     #[cfg(not(target_os = "android"))]
     fn on_factory_reset(&mut self, tx: oneshot::Sender<()>) {
         let mut failed = false;
@@ -1424,19 +2624,259 @@ where
         }
     }
 
-    #[cfg(target_os = "linux")]
-    fn on_clear_split_tunnel_processes(&mut self, tx: oneshot::Sender<()>) {
-        match self.exclude_pids.clear() {
-            Ok(()) => Self::oneshot_send(tx, (), "clear_split_tunnel_processes response"),
-            Err(e) => error!("{}", e.display_chain_with_msg("Unable to clear PIDs")),
+    #[cfg(target_os = "linux")]
+    fn on_clear_split_tunnel_processes(&mut self, tx: oneshot::Sender<()>) {
+        match self.exclude_pids.clear() {
+            Ok(()) => Self::oneshot_send(tx, (), "clear_split_tunnel_processes response"),
+            Err(e) => error!("{}", e.display_chain_with_msg("Unable to clear PIDs")),
+        }
+    }
+
+    fn on_get_split_tunnel_apps(&self, tx: oneshot::Sender<Vec<PathBuf>>) {
+        Self::oneshot_send(
+            tx,
+            self.settings
+                .get_split_tunnel_apps()
+                .iter()
+                .cloned()
+                .collect(),
+            "get_split_tunnel_apps response",
+        );
+    }
+
+    fn on_add_split_tunnel_app(&mut self, tx: oneshot::Sender<()>, path: PathBuf) {
+        match self.settings.add_split_tunnel_app(path) {
+            Ok(settings_changed) => {
+                Self::oneshot_send(tx, (), "add_split_tunnel_app response");
+                if settings_changed {
+                    self.event_listener
+                        .notify_settings(self.settings.to_settings());
+                }
+            }
+            Err(e) => error!("{}", e.display_chain_with_msg("Unable to save settings")),
+        }
+    }
+
+    fn on_remove_split_tunnel_app(&mut self, tx: oneshot::Sender<()>, path: PathBuf) {
+        match self.settings.remove_split_tunnel_app(&path) {
+            Ok(settings_changed) => {
+                Self::oneshot_send(tx, (), "remove_split_tunnel_app response");
+                if settings_changed {
+                    self.event_listener
+                        .notify_settings(self.settings.to_settings());
+                }
+            }
+            Err(e) => error!("{}", e.display_chain_with_msg("Unable to save settings")),
+        }
+    }
+
+    fn on_update_relay_settings(&mut self, tx: oneshot::Sender<()>, update: RelaySettingsUpdate) {
+        let save_result = self.settings.update_relay_settings(update);
+        match save_result {
+            Ok(settings_changed) => {
+                Self::oneshot_send(tx, (), "update_relay_settings response");
+                if settings_changed {
+                    self.event_listener
+                        .notify_settings(self.settings.to_settings());
+                    info!("Initiating tunnel restart because the relay settings changed");
+                    self.reconnect_tunnel();
+                }
+            }
+            Err(e) => error!("{}", e.display_chain_with_msg("Unable to save settings")),
+        }
+    }
+
+    fn on_apply_settings(&mut self, tx: oneshot::Sender<()>, patch: SettingsPatch) {
+        let old_settings = self.settings.to_settings();
+        match self.settings.apply_patch(patch) {
+            Ok(settings_changed) => {
+                Self::oneshot_send(tx, (), "apply_settings response");
+                if settings_changed {
+                    self.apply_changed_settings(&old_settings);
+                }
+            }
+            Err(e) => error!("{}", e.display_chain_with_msg("Unable to save settings")),
+        }
+    }
+
+    fn on_reload_settings(&mut self, tx: oneshot::Sender<Result<(), settings::Error>>) {
+        let old_settings = self.settings.to_settings();
+        match self.settings.reload() {
+            Ok(settings_changed) => {
+                Self::oneshot_send(tx, Ok(()), "reload_settings response");
+                if settings_changed {
+                    info!("Applying settings reloaded from disk");
+                    self.apply_changed_settings(&old_settings);
+                }
+            }
+            Err(e) => {
+                error!("{}", e.display_chain_with_msg("Unable to reload settings"));
+                Self::oneshot_send(tx, Err(e), "reload_settings response");
+            }
+        }
+    }
+
+    /// Notifies listeners of the current settings and applies everything that differs from
+    /// `old_settings`, reconnecting at most once.
+    fn apply_changed_settings(&mut self, old_settings: &Settings) {
+        let new_settings = self.settings.to_settings();
+        self.event_listener.notify_settings(new_settings.clone());
+
+        if old_settings.allow_lan != new_settings.allow_lan {
+            self.send_tunnel_command(TunnelCommand::AllowLan(new_settings.allow_lan));
+        }
+        if old_settings.block_when_disconnected != new_settings.block_when_disconnected {
+            self.apply_block_when_disconnected(new_settings.block_when_disconnected);
+        }
+        let dns_servers = new_settings.tunnel_options.dns_servers();
+        if old_settings.tunnel_options.dns_servers() != dns_servers {
+            self.send_tunnel_command(TunnelCommand::CustomDns(dns_servers));
+        }
+        if old_settings.api_endpoint != new_settings.api_endpoint {
+            self.switch_api_endpoint();
+        }
+        if old_settings.probe_before_connect != new_settings.probe_before_connect {
+            self.relay_selector
+                .set_relay_prober(relay_prober(new_settings.probe_before_connect));
+        }
+        if old_settings.show_beta_releases != new_settings.show_beta_releases {
+            let runtime = self.rpc_runtime.runtime();
+            let mut handle = self.version_updater_handle.clone();
+            let enabled = new_settings.show_beta_releases;
+            runtime.block_on(async { handle.set_show_beta_releases(enabled).await });
+        }
+
+        let account_token = new_settings.get_account_token();
+        if old_settings.get_account_token() != account_token {
+            self.handle_account_change(account_token.clone());
+            match account_token {
+                Some(_) => {
+                    info!("Initiating tunnel restart because the account token changed");
+                    self.reconnect_tunnel();
+                }
+                None => {
+                    info!("Disconnecting because account token was cleared");
+                    self.set_target_state(TargetState::Unsecured);
+                }
+            }
+            return;
+        }
+
+        let rotation_interval = new_settings.tunnel_options.wireguard.automatic_rotation;
+        if old_settings.tunnel_options.wireguard.automatic_rotation != rotation_interval {
+            if let Some(token) = account_token {
+                self.wireguard_key_manager.set_rotation_interval(
+                    &mut self.account_history,
+                    token,
+                    rotation_interval
+                        .map(|hours| Duration::from_secs(60u64 * 60u64 * hours as u64)),
+                );
+            }
+        }
+        if settings_change_requires_reconnect(
+            old_settings,
+            &new_settings,
+            self.get_connected_tunnel_type(),
+        ) {
+            info!("Initiating tunnel restart because the settings changed");
+            self.reconnect_tunnel();
+        }
+    }
+
+    fn on_add_custom_relay(
+        &mut self,
+        tx: oneshot::Sender<Result<(), CustomRelayError>>,
+        name: String,
+        endpoint: CustomTunnelEndpoint,
+    ) {
+        match self.settings.add_custom_relay(name, endpoint) {
+            Ok(()) => {
+                Self::oneshot_send(tx, Ok(()), "add_custom_relay response");
+                self.event_listener
+                    .notify_settings(self.settings.to_settings());
+            }
+            Err(settings::Error::CustomRelayError(error)) => {
+                Self::oneshot_send(tx, Err(error), "add_custom_relay response");
+            }
+            Err(e) => error!("{}", e.display_chain_with_msg("Unable to save settings")),
+        }
+    }
+
+    fn on_remove_custom_relay(
+        &mut self,
+        tx: oneshot::Sender<Result<(), CustomRelayError>>,
+        name: String,
+    ) {
+        match self.settings.remove_custom_relay(&name) {
+            Ok(()) => {
+                Self::oneshot_send(tx, Ok(()), "remove_custom_relay response");
+                self.event_listener
+                    .notify_settings(self.settings.to_settings());
+            }
+            Err(settings::Error::CustomRelayError(error)) => {
+                Self::oneshot_send(tx, Err(error), "remove_custom_relay response");
+            }
+            Err(e) => error!("{}", e.display_chain_with_msg("Unable to save settings")),
+        }
+    }
+
+    fn on_get_custom_relays(&self, tx: oneshot::Sender<BTreeMap<String, CustomTunnelEndpoint>>) {
+        Self::oneshot_send(
+            tx,
+            self.settings.get_custom_relays().clone(),
+            "get_custom_relays response",
+        );
+    }
+
+    fn on_set_active_custom_relay(
+        &mut self,
+        tx: oneshot::Sender<Result<(), CustomRelayError>>,
+        name: String,
+    ) {
+        match self.settings.set_active_custom_relay(&name) {
+            Ok(settings_changed) => {
+                Self::oneshot_send(tx, Ok(()), "set_active_custom_relay response");
+                if settings_changed {
+                    self.event_listener
+                        .notify_settings(self.settings.to_settings());
+                    info!("Initiating tunnel restart because the relay settings changed");
+                    self.reconnect_tunnel();
+                }
+            }
+            Err(settings::Error::CustomRelayError(error)) => {
+                Self::oneshot_send(tx, Err(error), "set_active_custom_relay response");
+            }
+            Err(e) => error!("{}", e.display_chain_with_msg("Unable to save settings")),
+        }
+    }
+
+    fn on_set_location_constraint(
+        &mut self,
+        tx: oneshot::Sender<()>,
+        location: Option<LocationConstraint>,
+    ) {
+        match self.settings.set_location_constraint(location) {
+            Ok(settings_changed) => {
+                Self::oneshot_send(tx, (), "set_location_constraint response");
+                if settings_changed {
+                    self.event_listener
+                        .notify_settings(self.settings.to_settings());
+                    info!("Initiating tunnel restart because the relay settings changed");
+                    self.reconnect_tunnel();
+                }
+            }
+            Err(e) => error!("{}", e.display_chain_with_msg("Unable to save settings")),
         }
     }
 
-    fn on_update_relay_settings(&mut self, tx: oneshot::Sender<()>, update: RelaySettingsUpdate) {
-        let save_result = self.settings.update_relay_settings(update);
-        match save_result {
+    fn on_set_openvpn_constraints(
+        &mut self,
+        tx: oneshot::Sender<()>,
+        constraints: OpenVpnConstraints,
+    ) {
+        match self.settings.set_openvpn_constraints(constraints) {
             Ok(settings_changed) => {
-                Self::oneshot_send(tx, (), "update_relay_settings response");
+                Self::oneshot_send(tx, (), "set_openvpn_constraints response");
                 if settings_changed {
                     self.event_listener
                         .notify_settings(self.settings.to_settings());
@@ -1494,15 +2934,26 @@ where
                 if settings_changed {
                     self.event_listener
                         .notify_settings(self.settings.to_settings());
-                    self.send_tunnel_command(TunnelCommand::BlockWhenDisconnected(
-                        block_when_disconnected,
-                    ));
+                    self.apply_block_when_disconnected(block_when_disconnected);
                 }
             }
             Err(e) => error!("{}", e.display_chain_with_msg("Unable to save settings")),
         }
     }
 
+    fn apply_block_when_disconnected(&mut self, block_when_disconnected: bool) {
+        self.send_tunnel_command(TunnelCommand::BlockWhenDisconnected(
+            block_when_disconnected,
+        ));
+        if let TunnelState::Disconnected { .. } = self.tunnel_state {
+            self.tunnel_state = TunnelState::Disconnected {
+                blocking: block_when_disconnected,
+            };
+            self.event_listener
+                .notify_new_state(self.tunnel_state.clone());
+        }
+    }
+
     fn on_set_auto_connect(&mut self, tx: oneshot::Sender<()>, auto_connect: bool) {
         let save_result = self.settings.set_auto_connect(auto_connect);
         match save_result {
@@ -1517,6 +2968,32 @@ where
         }
     }
 
+    fn on_get_auto_connect(&self, tx: oneshot::Sender<bool>) {
+        Self::oneshot_send(tx, self.settings.auto_connect, "get auto-connect response");
+    }
+
+    fn on_set_connect_timeout(&mut self, tx: oneshot::Sender<()>, connect_timeout: u32) {
+        let save_result = self.settings.set_connect_timeout(connect_timeout);
+        match save_result {
+            Ok(settings_changed) => {
+                Self::oneshot_send(tx, (), "set connect timeout response");
+                if settings_changed {
+                    self.event_listener
+                        .notify_settings(self.settings.to_settings());
+                }
+            }
+            Err(e) => error!("{}", e.display_chain_with_msg("Unable to save settings")),
+        }
+    }
+
+    fn on_get_connect_timeout(&self, tx: oneshot::Sender<u32>) {
+        Self::oneshot_send(
+            tx,
+            self.settings.connect_timeout,
+            "get connect timeout response",
+        );
+    }
+
     fn on_set_openvpn_mssfix(&mut self, tx: oneshot::Sender<()>, mssfix_arg: Option<u16>) {
         let save_result = self.settings.set_openvpn_mssfix(mssfix_arg);
         match save_result {
@@ -1537,6 +3014,45 @@ where
         }
     }
 
+    fn on_get_openvpn_mssfix(&self, tx: oneshot::Sender<Option<u16>>) {
+        Self::oneshot_send(
+            tx,
+            self.settings.tunnel_options.openvpn.mssfix,
+            "get_openvpn_mssfix response",
+        );
+    }
+
+    fn on_set_openvpn_cipher(&mut self, tx: oneshot::Sender<()>, cipher: Option<String>) {
+        match self.settings.set_openvpn_cipher(cipher) {
+            Ok(settings_changed) => {
+                Self::oneshot_send(tx, (), "set_openvpn_cipher response");
+                if settings_changed {
+                    info!(
+                        "OpenVPN cipher changed to {}",
+                        self.settings
+                            .tunnel_options
+                            .openvpn
+                            .cipher
+                            .as_ref()
+                            .map(String::as_str)
+                            .unwrap_or("default")
+                    );
+                    self.event_listener
+                        .notify_settings(self.settings.to_settings());
+                }
+            }
+            Err(e) => error!("{}", e.display_chain_with_msg("Unable to save settings")),
+        }
+    }
+
+    fn on_get_openvpn_cipher(&self, tx: oneshot::Sender<Option<String>>) {
+        Self::oneshot_send(
+            tx,
+            self.settings.tunnel_options.openvpn.cipher.clone(),
+            "get_openvpn_cipher response",
+        );
+    }
+
     fn on_set_bridge_settings(
         &mut self,
         tx: oneshot::Sender<Result<(), settings::Error>>,
@@ -1562,6 +3078,14 @@ where
         }
     }
 
+    fn on_get_bridge_settings(&self, tx: oneshot::Sender<BridgeSettings>) {
+        Self::oneshot_send(
+            tx,
+            self.settings.bridge_settings.clone(),
+            "get_bridge_settings response",
+        );
+    }
+
     fn on_set_bridge_state(
         &mut self,
         tx: oneshot::Sender<Result<(), settings::Error>>,
@@ -1588,6 +3112,90 @@ where
         Self::oneshot_send(tx, result, "on_set_bridge_state response");
     }
 
+    fn on_get_bridge_state(&self, tx: oneshot::Sender<BridgeState>) {
+        Self::oneshot_send(
+            tx,
+            self.settings.get_bridge_state().clone(),
+            "get_bridge_state response",
+        );
+    }
+
+    fn on_set_relay_filter(&mut self, tx: oneshot::Sender<()>, relay_filter: RelayFilter) {
+        match self.settings.set_relay_filter(relay_filter) {
+            Ok(settings_changed) => {
+                Self::oneshot_send(tx, (), "set_relay_filter response");
+                if settings_changed {
+                    info!("Relay filter changed to {}", self.settings.relay_filter);
+                    self.event_listener
+                        .notify_settings(self.settings.to_settings());
+                    self.reconnect_tunnel();
+                }
+            }
+            Err(e) => error!("{}", e.display_chain_with_msg("Unable to save settings")),
+        }
+    }
+
+    fn on_get_relay_filter(&self, tx: oneshot::Sender<RelayFilter>) {
+        Self::oneshot_send(
+            tx,
+            self.settings.relay_filter.clone(),
+            "get_relay_filter response",
+        );
+    }
+
+    fn on_set_relay_selection_mode(&mut self, tx: oneshot::Sender<()>, mode: SelectionMode) {
+        match self.settings.set_relay_selection_mode(mode) {
+            Ok(settings_changed) => {
+                Self::oneshot_send(tx, (), "set_relay_selection_mode response");
+                if settings_changed {
+                    info!("Relay selection mode changed to {}", mode);
+                    self.event_listener
+                        .notify_settings(self.settings.to_settings());
+                }
+            }
+            Err(e) => error!("{}", e.display_chain_with_msg("Unable to save settings")),
+        }
+    }
+
+    fn on_get_relay_selection_mode(&self, tx: oneshot::Sender<SelectionMode>) {
+        Self::oneshot_send(
+            tx,
+            self.settings.relay_selection_mode,
+            "get_relay_selection_mode response",
+        );
+    }
+
+    fn on_set_probe_before_connect(&mut self, tx: oneshot::Sender<()>, probe_before_connect: bool) {
+        match self.settings.set_probe_before_connect(probe_before_connect) {
+            Ok(settings_changed) => {
+                Self::oneshot_send(tx, (), "set_probe_before_connect response");
+                if settings_changed {
+                    info!("Probe before connect changed to {}", probe_before_connect);
+                    self.relay_selector
+                        .set_relay_prober(relay_prober(probe_before_connect));
+                    self.event_listener
+                        .notify_settings(self.settings.to_settings());
+                }
+            }
+            Err(e) => error!("{}", e.display_chain_with_msg("Unable to save settings")),
+        }
+    }
+
+    fn on_get_probe_before_connect(&self, tx: oneshot::Sender<bool>) {
+        Self::oneshot_send(
+            tx,
+            self.settings.probe_before_connect,
+            "get_probe_before_connect response",
+        );
+    }
+
+    fn on_get_enable_ipv6(&self, tx: oneshot::Sender<bool>) {
+        Self::oneshot_send(
+            tx,
+            self.settings.tunnel_options.generic.enable_ipv6,
+            "get_enable_ipv6 response",
+        );
+    }
 
     fn on_set_enable_ipv6(&mut self, tx: oneshot::Sender<()>, enable_ipv6: bool) {
         let save_result = self.settings.set_enable_ipv6(enable_ipv6);
@@ -1605,6 +3213,59 @@ where
         }
     }
 
+    fn on_set_custom_dns(&mut self, tx: oneshot::Sender<()>, servers: Option<Vec<IpAddr>>) {
+        let save_result = self.settings.set_custom_dns(servers);
+        match save_result {
+            Ok(settings_changed) => {
+                Self::oneshot_send(tx, (), "set_custom_dns response");
+                if settings_changed {
+                    self.event_listener
+                        .notify_settings(self.settings.to_settings());
+                    self.send_tunnel_command(TunnelCommand::CustomDns(
+                        self.settings.tunnel_options.dns_servers(),
+                    ));
+                }
+            }
+            Err(e) => error!("{}", e.display_chain_with_msg("Unable to save settings")),
+        }
+    }
+
+    fn on_get_custom_dns(&self, tx: oneshot::Sender<Option<Vec<IpAddr>>>) {
+        Self::oneshot_send(
+            tx,
+            self.settings.tunnel_options.custom_dns.clone(),
+            "get_custom_dns response",
+        );
+    }
+
+    fn on_set_dns_blockers(&mut self, tx: oneshot::Sender<()>, blockers: DnsBlockers) {
+        match self.settings.set_dns_blockers(blockers) {
+            Ok(settings_changed) => {
+                Self::oneshot_send(tx, (), "set_dns_blockers response");
+                if settings_changed {
+                    self.event_listener
+                        .notify_settings(self.settings.to_settings());
+                    if self.settings.tunnel_options.custom_dns.is_some() {
+                        warn!("DNS content blockers are ignored while custom DNS servers are set");
+                    } else {
+                        self.send_tunnel_command(TunnelCommand::CustomDns(
+                            self.settings.tunnel_options.dns_servers(),
+                        ));
+                    }
+                }
+            }
+            Err(e) => error!("{}", e.display_chain_with_msg("Unable to save settings")),
+        }
+    }
+
+    fn on_get_dns_blockers(&self, tx: oneshot::Sender<DnsBlockers>) {
+        Self::oneshot_send(
+            tx,
+            self.settings.tunnel_options.dns_blockers,
+            "get_dns_blockers response",
+        );
+    }
+
     fn on_set_wireguard_mtu(&mut self, tx: oneshot::Sender<()>, mtu: Option<u16>) {
         let save_result = self.settings.set_wireguard_mtu(mtu);
         match save_result {
@@ -1754,11 +3415,15 @@ where
         Self::oneshot_send(tx, key, "get_wireguard_key response");
     }
 
-    fn on_verify_wireguard_key(&mut self, tx: oneshot::Sender<bool>) {
+    fn on_verify_wireguard_key(&mut self, tx: oneshot::Sender<Result<bool, wireguard::Error>>) {
         let account = match self.settings.get_account_token() {
             Some(account) => account,
             None => {
-                Self::oneshot_send(tx, false, "verify_wireguard_key response");
+                Self::oneshot_send(
+                    tx,
+                    Err(wireguard::Error::NoKey),
+                    "verify_wireguard_key response",
+                );
                 return;
             }
         };
@@ -1771,7 +3436,11 @@ where
         let public_key = match key {
             Ok(Some(public_key)) => public_key,
             Ok(None) => {
-                Self::oneshot_send(tx, false, "verify_wireguard_key response");
+                Self::oneshot_send(
+                    tx,
+                    Err(wireguard::Error::NoKey),
+                    "verify_wireguard_key response",
+                );
                 return;
             }
             Err(e) => {
@@ -1785,14 +3454,11 @@ where
             .verify_wireguard_key(account, public_key);
 
         self.rpc_handle.service().spawn(async move {
-            match verification_rpc.await {
-                Ok(is_valid) => {
-                    Self::oneshot_send(tx, is_valid, "verify_wireguard_key response");
-                }
-                Err(err) => {
-                    log::error!("Failed to verify wireguard key - {}", err);
-                }
+            let result = verification_rpc.await;
+            if let Err(err) = &result {
+                log::error!("Failed to verify wireguard key - {}", err);
             }
+            Self::oneshot_send(tx, result, "verify_wireguard_key response");
         });
     }
 
@@ -1800,6 +3466,14 @@ where
         Self::oneshot_send(tx, self.settings.to_settings(), "get_settings response");
     }
 
+    fn on_get_relay_settings(&self, tx: oneshot::Sender<RelaySettings>) {
+        Self::oneshot_send(
+            tx,
+            self.settings.get_relay_settings(),
+            "get_relay_settings response",
+        );
+    }
+
     fn oneshot_send<T>(tx: oneshot::Sender<T>, t: T, msg: &'static str) {
         if tx.send(t).is_err() {
             warn!("Unable to send {} to the daemon command sender", msg);
@@ -1811,29 +3485,19 @@ where
         self.disconnect_tunnel();
     }
 
-    fn on_prepare_restart(&mut self) {
+    fn on_prepare_restart(&mut self, tx: oneshot::Sender<()>) {
         // TODO: See if this can be made to also shut down the daemon
         //       without causing the service to be restarted.
 
         // Cache the current target state
-        let cache_file = self.cache_dir.join(TARGET_START_STATE_FILE);
-        log::debug!("Saving tunnel target state to {}", cache_file.display());
-        match File::create(&cache_file) {
-            Ok(handle) => {
-                if let Err(e) =
-                    serde_json::to_writer(io::BufWriter::new(handle), &self.target_state)
-                {
-                    log::error!("Failed to serialize target start state: {}", e);
-                }
-            }
-            Err(e) => {
-                log::error!("Failed to save target start state: {}", e);
-            }
+        if let Err(e) = write_target_start_state(&self.cache_dir, self.target_state) {
+            log::error!("Failed to save target start state: {}", e);
         }
 
         if self.target_state == TargetState::Secured {
             self.send_tunnel_command(TunnelCommand::BlockWhenDisconnected(true));
         }
+        Self::oneshot_send(tx, (), "prepare_restart response");
     }
 
     /// Set the target state of the client. If it changed trigger the operations needed to
@@ -1855,6 +3519,7 @@ where
     }
 
     fn disconnect_tunnel(&mut self) {
+        self.one_shot_relay = None;
         self.send_tunnel_command(TunnelCommand::Disconnect);
     }
 
@@ -1865,18 +3530,9 @@ where
     }
 
     fn get_connected_tunnel_type(&self) -> Option<TunnelType> {
-        use talpid_types::net::TunnelEndpoint;
-        use TunnelState::Connected;
-
-        if let Connected {
-            endpoint: TunnelEndpoint { tunnel_type, .. },
-            ..
-        } = self.tunnel_state
-        {
-            Some(tunnel_type)
-        } else {
-            None
-        }
+        self.tunnel_state
+            .connected_endpoint()
+            .map(|endpoint| endpoint.tunnel_type)
     }
 
     fn send_tunnel_command(&mut self, command: TunnelCommand) {
@@ -1950,6 +3606,98 @@ impl DaemonShutdownHandle {
     }
 }
 
+/// Returns whether the tunnel has to be reconnected for a change from `old` to `new` settings to
+/// take effect. The MTU and mssfix only matter if a tunnel of that type is connected.
+fn settings_change_requires_reconnect(
+    old: &Settings,
+    new: &Settings,
+    connected_tunnel_type: Option<TunnelType>,
+) -> bool {
+    let tunnel_type_setting_changed = match connected_tunnel_type {
+        Some(TunnelType::Wireguard) => {
+            old.tunnel_options.wireguard.mtu != new.tunnel_options.wireguard.mtu
+        }
+        Some(TunnelType::OpenVpn) => {
+            old.tunnel_options.openvpn.mssfix != new.tunnel_options.openvpn.mssfix
+        }
+        None => false,
+    };
+    tunnel_type_setting_changed
+        || old.get_relay_settings() != new.get_relay_settings()
+        || old.bridge_settings != new.bridge_settings
+        || old.relay_filter != new.relay_filter
+        || old.get_bridge_state() != new.get_bridge_state()
+        || old.tunnel_options.generic.enable_ipv6 != new.tunnel_options.generic.enable_ipv6
+}
+
+/// Removes the API cache files from the cache directory. Files that do not exist are ignored.
+fn remove_api_cache_files(cache_dir: &Path) -> Result<(), Error> {
+    for file_name in API_CACHE_FILES {
+        let path = cache_dir.join(file_name);
+        match fs::remove_file(&path) {
+            Err(error) if error.kind() != io::ErrorKind::NotFound => {
+                return Err(Error::RemoveCacheFileError(
+                    path.display().to_string(),
+                    error,
+                ));
+            }
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+/// Saves the target state in the cache directory, so that it is restored on the next startup.
+fn write_target_start_state(cache_dir: &Path, target_state: TargetState) -> io::Result<()> {
+    let cache_file = cache_dir.join(TARGET_START_STATE_FILE);
+    log::debug!("Saving tunnel target state to {}", cache_file.display());
+    let handle = File::create(&cache_file)?;
+    serde_json::to_writer(io::BufWriter::new(handle), &target_state).map_err(io::Error::from)
+}
+
+/// Returns the relay settings to generate the next tunnel from. A one-shot relay is only used
+/// for a single tunnel, after which the saved relay settings apply again.
+fn next_relay_settings(
+    one_shot_relay: &mut Option<CustomTunnelEndpoint>,
+    saved: RelaySettings,
+) -> RelaySettings {
+    match one_shot_relay.take() {
+        Some(relay) => RelaySettings::CustomTunnelEndpoint(relay),
+        None => saved,
+    }
+}
+
+/// Returns what the relay selector should probe selected relays with, if probing is enabled.
+fn relay_prober(probe_before_connect: bool) -> Option<Arc<dyn latency::LatencyMeasurer>> {
+    if probe_before_connect {
+        Some(Arc::new(latency::TcpLatencyMeasurer))
+    } else {
+        None
+    }
+}
+
+/// Points all API requests made through `factory` at `endpoint`, or at the default API if it's
+/// `None`.
+fn apply_api_endpoint(factory: &mullvad_rpc::rest::RequestFactory, endpoint: Option<SocketAddr>) {
+    match endpoint {
+        Some(endpoint) => info!("Using custom API endpoint {}", endpoint),
+        None => info!("Using the default API endpoint"),
+    }
+    factory.set_endpoint_override(endpoint);
+}
+
+/// Sends `InternalDaemonEvent::ConnectTimeout` once `timeout` has passed, unless the returned
+/// sender is used or dropped before that.
+fn spawn_connect_timer(daemon_tx: DaemonEventSender, timeout: Duration) -> mpsc::Sender<()> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        if let Err(mpsc::RecvTimeoutError::Timeout) = rx.recv_timeout(timeout) {
+            let _ = daemon_tx.send(InternalDaemonEvent::ConnectTimeout);
+        }
+    });
+    tx
+}
+
 struct MullvadTunnelParametersGenerator {
     tx: DaemonEventSender,
 }
@@ -1981,3 +3729,256 @@ impl TunnelParametersGenerator for MullvadTunnelParametersGenerator {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use talpid_types::net::Endpoint;
+
+    #[test]
+    fn test_write_target_start_state() {
+        let cache_dir = std::env::temp_dir().join(format!(
+            "mullvad-daemon-target-state-test-{}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&cache_dir).unwrap();
+
+        write_target_start_state(&cache_dir, TargetState::Secured).unwrap();
+        let handle = File::open(cache_dir.join(TARGET_START_STATE_FILE)).unwrap();
+        let target_state: TargetState =
+            serde_json::from_reader(io::BufReader::new(handle)).unwrap();
+        fs::remove_dir_all(&cache_dir).unwrap();
+
+        assert_eq!(target_state, TargetState::Secured);
+    }
+
+    #[test]
+    fn test_settings_patch_reconnects_once() {
+        let old_settings = Settings::default();
+        let mut new_settings = old_settings.clone();
+        assert!(new_settings.apply_patch(SettingsPatch {
+            relay_settings: Some(RelaySettingsUpdate::Normal(RelayConstraintsUpdate {
+                location: Some(Constraint::Any),
+                ..Default::default()
+            })),
+            enable_ipv6: Some(!old_settings.tunnel_options.generic.enable_ipv6),
+            allow_lan: Some(true),
+            ..SettingsPatch::default()
+        }));
+        assert!(settings_change_requires_reconnect(
+            &old_settings,
+            &new_settings,
+            None
+        ));
+
+        let mut lan_only = old_settings.clone();
+        lan_only.allow_lan = true;
+        assert!(!settings_change_requires_reconnect(
+            &old_settings,
+            &lan_only,
+            None
+        ));
+
+        let mut mtu_only = old_settings.clone();
+        mtu_only.tunnel_options.wireguard.mtu = Some(1380);
+        assert!(!settings_change_requires_reconnect(
+            &old_settings,
+            &mtu_only,
+            Some(TunnelType::OpenVpn)
+        ));
+        assert!(settings_change_requires_reconnect(
+            &old_settings,
+            &mtu_only,
+            Some(TunnelType::Wireguard)
+        ));
+    }
+
+    #[test]
+    fn test_mssfix_is_ignored_by_wireguard_tunnels() {
+        let old_settings = Settings::default();
+        let mut mssfix_only = old_settings.clone();
+        mssfix_only.tunnel_options.openvpn.mssfix = Some(1300);
+        assert!(settings_change_requires_reconnect(
+            &old_settings,
+            &mssfix_only,
+            Some(TunnelType::OpenVpn)
+        ));
+        assert!(!settings_change_requires_reconnect(
+            &old_settings,
+            &mssfix_only,
+            Some(TunnelType::Wireguard)
+        ));
+        assert!(!settings_change_requires_reconnect(
+            &old_settings,
+            &mssfix_only,
+            None
+        ));
+    }
+
+    #[test]
+    fn test_api_endpoint_setting_is_applied_to_factory() {
+        let factory = mullvad_rpc::rest::RequestFactory::new(
+            "api.mullvad.net".to_owned(),
+            Some("193.138.218.78".parse().unwrap()),
+            None,
+        );
+        let mut settings = Settings::default();
+        let endpoint: SocketAddr = "10.0.0.1:8443".parse().unwrap();
+
+        settings.api_endpoint = Some(endpoint);
+        apply_api_endpoint(&factory, settings.api_endpoint);
+        assert_eq!(factory.endpoint_override(), Some(endpoint));
+
+        settings.api_endpoint = None;
+        apply_api_endpoint(&factory, settings.api_endpoint);
+        assert_eq!(factory.endpoint_override(), None);
+    }
+
+    #[test]
+    fn test_one_shot_relay_is_used_once() {
+        let saved = Settings::default().get_relay_settings();
+        let relay = CustomTunnelEndpoint::new(
+            "10.0.0.1".to_owned(),
+            mullvad_types::ConnectionConfig::OpenVpn(openvpn::ConnectionConfig::new(
+                Endpoint::new([10, 0, 0, 1], 1194, TransportProtocol::Udp),
+                "user".to_owned(),
+                "password".to_owned(),
+            )),
+        );
+        let mut one_shot_relay = Some(relay.clone());
+
+        // Connect
+        assert_eq!(
+            next_relay_settings(&mut one_shot_relay, saved.clone()),
+            RelaySettings::CustomTunnelEndpoint(relay)
+        );
+        // Reconnect
+        assert_eq!(
+            next_relay_settings(&mut one_shot_relay, saved.clone()),
+            saved
+        );
+    }
+
+    #[test]
+    fn test_remove_api_cache_files_keeps_state() {
+        let cache_dir = std::env::temp_dir().join(format!(
+            "mullvad-daemon-clear-cache-test-{}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&cache_dir).unwrap();
+        let kept_files = [TARGET_START_STATE_FILE, "settings.json"];
+        for file_name in API_CACHE_FILES.iter().chain(kept_files.iter()) {
+            fs::write(cache_dir.join(file_name), b"{}").unwrap();
+        }
+
+        remove_api_cache_files(&cache_dir).unwrap();
+        // Removing files that are already gone is not an error.
+        remove_api_cache_files(&cache_dir).unwrap();
+
+        let removed = API_CACHE_FILES
+            .iter()
+            .all(|file_name| !cache_dir.join(file_name).exists());
+        let kept = kept_files
+            .iter()
+            .all(|file_name| cache_dir.join(file_name).exists());
+        fs::remove_dir_all(&cache_dir).unwrap();
+
+        assert!(removed);
+        assert!(kept);
+    }
+
+    #[test]
+    fn test_stalled_connect_times_out() {
+        let channel = DaemonCommandChannel::new();
+        let _command_sender = channel.sender();
+        let (event_tx, events) = channel.destructure();
+        let timeout = Duration::from_millis(100);
+
+        let start = Instant::now();
+        let _timer_tx = spawn_connect_timer(event_tx, timeout);
+        match events.wait().next() {
+            Some(Ok(InternalDaemonEvent::ConnectTimeout)) => (),
+            _ => panic!("Expected the connect timer to fire"),
+        }
+        assert!(start.elapsed() >= timeout);
+    }
+
+    #[test]
+    fn test_reconnect_backoff_schedule() {
+        let now = Utc::now();
+        let mut backoff = ReconnectBackoff::default();
+
+        let delays: Vec<u64> = (0..9).map(|_| backoff.schedule(now).as_secs()).collect();
+        assert_eq!(delays, vec![5, 10, 20, 40, 80, 160, 300, 300, 300]);
+        assert_eq!(
+            backoff.status(),
+            ReconnectStatus {
+                attempt: 9,
+                next_attempt: Some(now + chrono::Duration::seconds(300)),
+            }
+        );
+
+        backoff.unschedule();
+        assert_eq!(backoff.status().next_attempt, None);
+        assert_eq!(backoff.schedule(now), RECONNECT_BACKOFF_MAX);
+
+        backoff.reset();
+        assert_eq!(backoff.schedule(now), RECONNECT_BACKOFF_BASE);
+        assert_eq!(backoff.status().attempt, 1);
+    }
+
+    #[test]
+    fn test_state_history_is_capped() {
+        let start = Utc::now();
+        let mut history = StateHistory::new(3);
+        for i in 0..5 {
+            history.push(
+                start + chrono::Duration::seconds(i),
+                TunnelState::Disconnected {
+                    blocking: i % 2 == 0,
+                },
+            );
+        }
+
+        let entries = history.tail(10);
+        assert_eq!(entries.len(), 3);
+        let timestamps: Vec<_> = entries.iter().map(|entry| entry.timestamp).collect();
+        assert_eq!(
+            timestamps,
+            (2..5)
+                .map(|i| start + chrono::Duration::seconds(i))
+                .collect::<Vec<_>>()
+        );
+        match entries[2].state {
+            TunnelState::Disconnected { blocking: true } => (),
+            ref state => panic!("Unexpected state: {:?}", state),
+        }
+
+        let entries = history.tail(1);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].timestamp, start + chrono::Duration::seconds(4));
+    }
+
+    #[test]
+    fn test_offline_state_flap_is_not_notified() {
+        let start = Instant::now();
+        let mut debouncer = OfflineStateDebouncer::new(false);
+
+        assert!(debouncer.update(true, start));
+        assert!(!debouncer.update(false, start + OFFLINE_STATE_DEBOUNCE / 2));
+        assert_eq!(debouncer.poll(start + OFFLINE_STATE_DEBOUNCE), None);
+        assert_eq!(debouncer.poll(start + OFFLINE_STATE_DEBOUNCE * 2), None);
+    }
+
+    #[test]
+    fn test_lasting_offline_state_is_notified_once() {
+        let start = Instant::now();
+        let mut debouncer = OfflineStateDebouncer::new(false);
+
+        assert!(debouncer.update(true, start));
+        assert_eq!(debouncer.poll(start + OFFLINE_STATE_DEBOUNCE / 2), None);
+        assert_eq!(debouncer.poll(start + OFFLINE_STATE_DEBOUNCE), Some(true));
+        assert_eq!(debouncer.poll(start + OFFLINE_STATE_DEBOUNCE * 2), None);
+        assert!(!debouncer.update(true, start + OFFLINE_STATE_DEBOUNCE * 2));
+    }
+}
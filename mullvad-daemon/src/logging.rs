@@ -4,7 +4,9 @@ use fern::{
     Output,
 };
 use log;
-use std::{fmt, io, path::PathBuf};
+use mullvad_types::logging::{LogLevel, LogRecord};
+use parking_lot::{Mutex, RwLock};
+use std::{cell::Cell, collections::VecDeque, fmt, io, path::PathBuf};
 use talpid_core::logging::rotate_log;
 
 #[derive(err_derive::Error, Debug)]
@@ -63,12 +65,43 @@ const LINE_SEPARATOR: &str = "\r\n";
 
 const DATE_TIME_FORMAT_STR: &str = "[%Y-%m-%d %H:%M:%S%.3f]";
 
+/// Number of recent log lines kept in memory, so that they can be handed to clients without
+/// reading the log file.
+pub const RECENT_LOG_CAPACITY: usize = 1000;
+
+lazy_static::lazy_static! {
+    static ref RECENT_LOGS: Mutex<LogBuffer> = Mutex::new(LogBuffer::new(RECENT_LOG_CAPACITY));
+    static ref LOG_LISTENER: RwLock<Option<Box<dyn LogListener>>> = RwLock::new(None);
+}
+
+thread_local! {
+    /// Set while a record is passed to the `LogListener`, so that records logged by the
+    /// listener itself are dropped instead of recursing into it.
+    static NOTIFYING_LISTENER: Cell<bool> = Cell::new(false);
+}
+
+/// Receives log records as they are logged, so that they can be streamed to clients.
+pub trait LogListener: Send + Sync {
+    /// Returns whether anyone wants log records. Records aren't collected otherwise.
+    fn wants_logs(&self) -> bool;
+
+    /// Called with every record while `wants_logs` returns true.
+    fn notify_log(&self, record: LogRecord);
+}
+
+/// Sets the listener that log records are streamed to, replacing any previous one.
+pub fn set_log_listener(listener: Box<dyn LogListener>) {
+    *LOG_LISTENER.write() = Some(listener);
+}
+
 pub fn init_logger(
     log_level: log::LevelFilter,
     log_file: Option<&PathBuf>,
     output_timestamp: bool,
 ) -> Result<(), Error> {
-    let mut top_dispatcher = fern::Dispatch::new().level(log_level);
+    // The dispatcher lets everything through, and `log::set_max_level` does the filtering, so
+    // that the level can be raised at runtime with `set_log_level`.
+    let mut top_dispatcher = fern::Dispatch::new().level(log::LevelFilter::Trace);
     for silenced_crate in SILENCED_CRATES {
         top_dispatcher = top_dispatcher.level_for(*silenced_crate, log::LevelFilter::Warn);
     }
@@ -100,6 +133,19 @@ pub fn init_logger(
             .chain(Output::file(f, LINE_SEPARATOR));
         top_dispatcher = top_dispatcher.chain(file_dispatcher);
     }
+    let recent_logs_formatter = Formatter {
+        output_timestamp: true,
+        output_color: false,
+    };
+    let recent_logs: Box<dyn log::Log> = Box::new(RecentLogs);
+    let recent_logs_dispatcher = fern::Dispatch::new()
+        .format(move |out, message, record| recent_logs_formatter.output_msg(out, message, record))
+        .chain(recent_logs);
+    top_dispatcher = top_dispatcher.chain(recent_logs_dispatcher);
+
+    let log_stream: Box<dyn log::Log> = Box::new(LogStream);
+    top_dispatcher = top_dispatcher.chain(log_stream);
+
     #[cfg(all(target_os = "android", debug_assertions))]
     {
         use android_logger::{AndroidLogger, Config};
@@ -109,9 +155,93 @@ pub fn init_logger(
         top_dispatcher = top_dispatcher.chain(logger);
     }
     top_dispatcher.apply().map_err(Error::SetLoggerError)?;
+    log::set_max_level(log_level);
     Ok(())
 }
 
+/// Sets the most verbose level that is logged.
+pub fn set_log_level(level: LogLevel) {
+    log::set_max_level(level.into());
+}
+
+/// Returns the most verbose level that is logged, or `None` if logging is turned off.
+pub fn get_log_level() -> Option<LogLevel> {
+    log::max_level().to_level().map(LogLevel::from)
+}
+
+/// Returns up to `max_lines` of the most recent log lines, oldest first.
+pub fn recent_log_lines(max_lines: usize) -> Vec<String> {
+    RECENT_LOGS.lock().tail(max_lines)
+}
+
+/// Keeps the most recent lines, dropping the oldest ones when full.
+struct LogBuffer {
+    lines: VecDeque<String>,
+    capacity: usize,
+}
+
+impl LogBuffer {
+    fn new(capacity: usize) -> Self {
+        LogBuffer {
+            lines: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    fn push(&mut self, line: String) {
+        if self.lines.len() >= self.capacity {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line);
+    }
+
+    fn tail(&self, max_lines: usize) -> Vec<String> {
+        let skip = self.lines.len().saturating_sub(max_lines);
+        self.lines.iter().skip(skip).cloned().collect()
+    }
+}
+
+/// Logger that stores formatted lines in `RECENT_LOGS`.
+struct RecentLogs;
+
+impl log::Log for RecentLogs {
+    fn enabled(&self, _: &log::Metadata<'_>) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        RECENT_LOGS.lock().push(record.args().to_string());
+    }
+
+    fn flush(&self) {}
+}
+
+/// Logger that passes records on to the `LogListener`, if there is one.
+struct LogStream;
+
+impl log::Log for LogStream {
+    fn enabled(&self, _: &log::Metadata<'_>) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        NOTIFYING_LISTENER.with(|notifying| {
+            if notifying.get() {
+                return;
+            }
+            if let Some(listener) = LOG_LISTENER.read().as_ref() {
+                if listener.wants_logs() {
+                    notifying.set(true);
+                    listener.notify_log(LogRecord::from(record));
+                    notifying.set(false);
+                }
+            }
+        });
+    }
+
+    fn flush(&self) {}
+}
+
 fn one_level_quieter(level: log::LevelFilter) -> log::LevelFilter {
     use log::LevelFilter::*;
     match level {
@@ -174,3 +304,61 @@ fn escape_newlines(text: String) -> String {
 fn escape_newlines(text: String) -> String {
     text.replace("\n", LINE_SEPARATOR)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_log_buffer_tail() {
+        let mut buffer = LogBuffer::new(3);
+        for i in 0..5 {
+            buffer.push(i.to_string());
+        }
+
+        assert_eq!(buffer.tail(2), vec!["3", "4"]);
+        assert_eq!(buffer.tail(10), vec!["2", "3", "4"]);
+        assert!(buffer.tail(0).is_empty());
+    }
+
+    #[test]
+    fn test_log_listener_does_not_recurse() {
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
+
+        struct RelogListener(Arc<AtomicUsize>);
+
+        impl LogListener for RelogListener {
+            fn wants_logs(&self) -> bool {
+                true
+            }
+
+            fn notify_log(&self, record: LogRecord) {
+                self.0.fetch_add(1, Ordering::SeqCst);
+                log::Log::log(
+                    &LogStream,
+                    &log::Record::builder()
+                        .args(format_args!("Forwarded {}", record.message))
+                        .level(log::Level::Debug)
+                        .target("listener")
+                        .build(),
+                );
+            }
+        }
+
+        let notifications = Arc::new(AtomicUsize::new(0));
+        set_log_listener(Box::new(RelogListener(notifications.clone())));
+        log::Log::log(
+            &LogStream,
+            &log::Record::builder()
+                .args(format_args!("hello"))
+                .level(log::Level::Info)
+                .target("test")
+                .build(),
+        );
+
+        assert_eq!(notifications.load(Ordering::SeqCst), 1);
+    }
+}
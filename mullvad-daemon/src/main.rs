@@ -4,9 +4,10 @@ use log::{debug, error, info, warn};
 use mullvad_daemon::{
     logging,
     management_interface::{ManagementInterfaceEventBroadcaster, ManagementInterfaceServer},
+    metrics::MetricsServer,
     rpc_uniqueness_check, version, Daemon, DaemonCommandChannel, DaemonCommandSender,
 };
-use std::{path::PathBuf, thread, time::Duration};
+use std::{net::SocketAddr, path::PathBuf, thread, time::Duration};
 use talpid_types::ErrorExt;
 
 mod cli;
@@ -117,7 +118,8 @@ fn create_daemon(
         .map_err(|e| e.display_chain_with_msg("Unable to get cache dir"))?;
 
     let command_channel = DaemonCommandChannel::new();
-    let event_listener = spawn_management_interface(command_channel.sender())?;
+    let event_listener =
+        spawn_management_interface(command_channel.sender(), cli::get_config().metrics_address)?;
 
     Daemon::start(
         log_dir,
@@ -130,13 +132,25 @@ fn create_daemon(
     .map_err(|e| e.display_chain_with_msg("Unable to initialize daemon"))
 }
 
+/// Starts the management interface, and the metrics server if `metrics_address` is set.
 fn spawn_management_interface(
     command_sender: DaemonCommandSender,
+    metrics_address: Option<SocketAddr>,
 ) -> Result<ManagementInterfaceEventBroadcaster, String> {
+    if let Some(address) = metrics_address {
+        let metrics_server = MetricsServer::start(command_sender.clone(), address)
+            .map_err(|error| format!("Unable to start metrics server: {}", error))?;
+        info!(
+            "Serving metrics on http://{}/metrics",
+            metrics_server.address()
+        );
+    }
+
     let server = ManagementInterfaceServer::start(command_sender).map_err(|error| {
         error.display_chain_with_msg("Unable to start management interface server")
     })?;
     let event_broadcaster = server.event_broadcaster();
+    logging::set_log_listener(Box::new(server.log_broadcaster()));
 
     info!("Management interface listening on {}", server.socket_path());
 
@@ -1,41 +1,165 @@
-use crate::{BoxFuture, DaemonCommand, DaemonCommandSender, EventListener};
+use crate::{
+    event_loop, logging, version_check, wireguard::Error as WireguardKeyError, BoxFuture,
+    DaemonCommand, DaemonCommandSender, EventListener, STATE_HISTORY_CAPACITY,
+};
 use jsonrpc_core::{
-    futures::{future, sync, Future},
+    futures::{future, stream, sync, Future, Stream},
     Error, ErrorCode, MetaIoHandler, Metadata,
 };
 use jsonrpc_ipc_server;
-use jsonrpc_macros::{build_rpc_trait, metadata, pubsub};
+use jsonrpc_macros::{build_rpc_trait, metadata, pubsub, Trailing};
 use jsonrpc_pubsub::{PubSubHandler, PubSubMetadata, Session, SubscriptionId};
 use mullvad_paths;
 use mullvad_rpc::{rest::Error as RestError, StatusCode};
 use mullvad_types::{
-    account::{AccountData, AccountToken, VoucherSubmission},
-    location::GeoIpLocation,
-    relay_constraints::{BridgeSettings, BridgeState, RelaySettingsUpdate},
-    relay_list::RelayList,
-    settings::Settings,
-    states::{TargetState, TunnelState},
-    version, wireguard, DaemonEvent,
+    account::{self, AccountData, AccountExpiry, AccountToken, VoucherSubmission},
+    endpoint::RelayEndpoint,
+    location::{CountryCode, GeoIpLocation, PublicIp},
+    logging::{LogLevel, LogRecord},
+    relay_constraints::{
+        BridgeSettings, BridgeState, Constraint, LocationConstraint, OpenVpnConstraints,
+        RelayFilter, RelaySettings, RelaySettingsUpdate, SelectionMode,
+    },
+    relay_list::{CountryRelayCount, RelayLatency, RelayList},
+    settings::{CustomRelayError, DnsBlockers, Settings, SettingsPatch},
+    states::{
+        ConnectionProgress, Connectivity, FirewallState, ReconnectStatus, StateHistoryEntry,
+        StatusSnapshot, TargetState, TunnelState, TunnelStats,
+    },
+    version, wireguard, CustomTunnelEndpoint, DaemonEvent, DaemonEventSubscribeOptions,
 };
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
+use serde::Serialize;
 use std::{
-    collections::{hash_map::Entry, HashMap},
-    sync::Arc,
+    collections::{BTreeMap, HashMap, HashSet},
+    net::{IpAddr, SocketAddr},
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
 };
 use talpid_ipc;
-use talpid_types::ErrorExt;
-use uuid;
+use talpid_types::{
+    net::{openvpn, TunnelEndpoint},
+    tunnel::ErrorState,
+    ErrorExt,
+};
 
 pub const INVALID_VOUCHER_CODE: i64 = -400;
 pub const VOUCHER_USED_ALREADY_CODE: i64 = -401;
 pub const INVALID_ACCOUNT_CODE: i64 = -200;
+pub const NO_RESPONSIVE_RELAY_CODE: i64 = -300;
+pub const CONNECTION_FAILED_CODE: i64 = -301;
+pub const API_UNREACHABLE_CODE: i64 = -500;
+pub const NO_WIREGUARD_KEY_CODE: i64 = -600;
+pub const NO_ACCOUNT_TOKEN_CODE: i64 = -900;
+pub const DAEMON_SHUTTING_DOWN_CODE: i64 = -901;
+pub const DAEMON_NO_RESPONSE_CODE: i64 = -902;
+pub const DAEMON_BUSY_CODE: i64 = -903;
+
+/// MTUs accepted for the tunnel interface.
+const MTU_RANGE: RangeInclusive<u16> = 576..=1500;
+
+/// Values accepted for OpenVPN's mssfix parameter.
+const MSSFIX_RANGE: RangeInclusive<u16> = 100..=1500;
+
+/// Settings changes made within this long of each other are sent to `settings` subscribers as
+/// a single notification.
+const SETTINGS_COALESCE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Tunnel states entered within this long of each other are sent to `tunnel_state` subscribers
+/// as a single notification carrying the latest state.
+const TUNNEL_STATE_COALESCE_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Maximum number of subscriptions a single connection can hold across all pubsub channels.
+const MAX_SUBSCRIPTIONS_PER_CONNECTION: usize = 32;
+
+/// How long `connect_and_wait` waits for the tunnel to connect. This also bounds the wait when no
+/// connect timeout is configured, in which case the daemon keeps retrying forever.
+const CONNECT_AND_WAIT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How many events can be waiting to be sent to a single subscriber. A subscriber that falls
+/// this far behind is dropped, rather than letting its events pile up in the daemon.
+const SUBSCRIBER_BUFFER_SIZE: usize = 512;
+
+/// Senders feeding the forwarding task of each subscriber of a pubsub channel.
+type SubscriptionSenders<T> = RwLock<HashMap<SubscriptionId, sync::mpsc::Sender<T>>>;
+
+/// All subscriptions to the pubsub channels of the management interface.
+#[derive(Default)]
+struct ActiveSubscriptions {
+    daemon_event: SubscriptionSenders<DaemonEvent>,
+    connection_progress: SubscriptionSenders<ConnectionProgress>,
+    tunnel_stats: SubscriptionSenders<TunnelStats>,
+    connectivity: SubscriptionSenders<Connectivity>,
+    relay_list: SubscriptionSenders<RelayList>,
+    account: SubscriptionSenders<Option<AccountToken>>,
+    settings: SubscriptionSenders<Settings>,
+    tunnel_state: SubscriptionSenders<TunnelState>,
+    log: SubscriptionSenders<LogRecord>,
+    /// The latest settings that have not yet been sent to `settings` subscribers.
+    pending_settings: Mutex<Option<Settings>>,
+    /// The latest tunnel state that has not yet been sent to `tunnel_state` subscribers.
+    pending_tunnel_state: Mutex<Option<TunnelState>>,
+}
+
+impl ActiveSubscriptions {
+    /// Removes the subscription with the given id from whichever channel it belongs to. Ids are
+    /// unique across all channels.
+    fn remove(&self, id: &SubscriptionId) {
+        self.daemon_event.write().remove(id);
+        self.connection_progress.write().remove(id);
+        self.tunnel_stats.write().remove(id);
+        self.connectivity.write().remove(id);
+        self.relay_list.write().remove(id);
+        self.account.write().remove(id);
+        self.settings.write().remove(id);
+        self.tunnel_state.write().remove(id);
+        self.log.write().remove(id);
+    }
+}
+
+/// A subscription counted against a connection's limit. The slot is released when dropped.
+struct SubscriptionSlot(Arc<AtomicUsize>);
 
+impl Drop for SubscriptionSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// The optional argument of `daemon_event_subscribe`. Older clients only send whether the
+/// initial state should be sent.
+#[derive(Debug, serde::Deserialize)]
+#[serde(untagged)]
+pub enum DaemonEventSubscribeArgs {
+    SendInitialState(bool),
+    Options(DaemonEventSubscribeOptions),
+}
+
+impl From<DaemonEventSubscribeArgs> for DaemonEventSubscribeOptions {
+    fn from(args: DaemonEventSubscribeArgs) -> Self {
+        match args {
+            DaemonEventSubscribeArgs::SendInitialState(send_initial_state) => {
+                DaemonEventSubscribeOptions {
+                    send_initial_state,
+                    ..DaemonEventSubscribeOptions::default()
+                }
+            }
+            DaemonEventSubscribeArgs::Options(options) => options,
+        }
+    }
+}
 
 build_rpc_trait! {
     pub trait ManagementInterfaceApi {
         type Metadata;
 
-        /// Creates and sets a new account
+        /// Creates a new account through the API, sets it as the active account and returns its
+        /// token. Fails with `API_UNREACHABLE_CODE` if the API could not be reached.
         #[rpc(meta, name = "create_new_account")]
         fn create_new_account(&self, Self::Metadata) -> BoxFuture<String, Error>;
 
@@ -44,6 +168,12 @@ build_rpc_trait! {
         #[rpc(meta, name = "get_account_data")]
         fn get_account_data(&self, Self::Metadata, AccountToken) -> BoxFuture<AccountData, Error>;
 
+        /// Returns the expiry of the current account and the time left until then. Served from
+        /// the most recently fetched account data when possible. Returns `null` if no account is
+        /// set.
+        #[rpc(meta, name = "get_account_expiry")]
+        fn get_account_expiry(&self, Self::Metadata) -> BoxFuture<Option<AccountExpiry>, Error>;
+
         #[rpc(meta, name = "get_www_auth_token")]
         fn get_www_auth_token(&self, Self::Metadata) -> BoxFuture<String, Error>;
 
@@ -55,14 +185,54 @@ build_rpc_trait! {
         #[rpc(meta, name = "get_relay_locations")]
         fn get_relay_locations(&self, Self::Metadata) -> BoxFuture<RelayList, Error>;
 
+        /// Returns the names of all countries with at least one active relay, keyed by country
+        /// code.
+        #[rpc(meta, name = "get_relay_countries")]
+        fn get_relay_countries(
+            &self,
+            Self::Metadata
+            ) -> BoxFuture<BTreeMap<CountryCode, String>, Error>;
+
+        /// Returns the number of active relays in each city, grouped by country code. Cities
+        /// without any active relays are left out.
+        #[rpc(meta, name = "get_relay_location_counts")]
+        fn get_relay_location_counts(
+            &self,
+            Self::Metadata
+            ) -> BoxFuture<BTreeMap<CountryCode, CountryRelayCount>, Error>;
+
+        /// Measures the round-trip time to every active relay in the given location. Relays
+        /// that don't respond in time are reported without a latency.
+        #[rpc(meta, name = "measure_relay_latency")]
+        fn measure_relay_latency(
+            &self,
+            Self::Metadata,
+            Constraint<LocationConstraint>
+        ) -> BoxFuture<Vec<RelayLatency>, Error>;
+
         /// Triggers a relay list update
         #[rpc(meta, name = "update_relay_locations")]
         fn update_relay_locations(&self, Self::Metadata) -> BoxFuture<(), Error>;
 
-        /// Set which account to connect with.
+        /// Downloads a new relay list and resolves once the download has completed. Fails if
+        /// the relay list could not be fetched.
+        #[rpc(meta, name = "update_relay_list")]
+        fn update_relay_list(&self, Self::Metadata) -> BoxFuture<(), Error>;
+
+        /// Set which account to connect with. Whitespace and dashes are stripped from the token
+        /// before it is validated. Passing `null` logs out.
         #[rpc(meta, name = "set_account")]
         fn set_account(&self, Self::Metadata, Option<AccountToken>) -> BoxFuture<(), Error>;
 
+        /// Disconnects, clears the active account and forgets any cached account data.
+        #[rpc(meta, name = "logout")]
+        fn logout(&self, Self::Metadata) -> BoxFuture<(), Error>;
+
+        /// Returns the relay settings, which are either a custom tunnel endpoint or the
+        /// constraints used to pick a relay from the relay list.
+        #[rpc(meta, name = "get_relay_settings")]
+        fn get_relay_settings(&self, Self::Metadata) -> BoxFuture<RelaySettings, Error>;
+
         /// Update constraints put on the type of tunnel connection to use
         #[rpc(meta, name = "update_relay_settings")]
         fn update_relay_settings(
@@ -70,6 +240,55 @@ build_rpc_trait! {
             Self::Metadata, RelaySettingsUpdate
             ) -> BoxFuture<(), Error>;
 
+        /// Changes all settings that are set in the patch at once, so that the tunnel is
+        /// reconnected at most once. Each field is validated like by its individual setter, and
+        /// nothing is changed if any field is invalid.
+        #[rpc(meta, name = "apply_settings")]
+        fn apply_settings(&self, Self::Metadata, SettingsPatch) -> BoxFuture<(), Error>;
+
+        /// Stores a custom relay under a new name. Fails if the name is already taken.
+        #[rpc(meta, name = "add_custom_relay")]
+        fn add_custom_relay(
+            &self,
+            Self::Metadata,
+            String,
+            CustomTunnelEndpoint
+            ) -> BoxFuture<(), Error>;
+
+        /// Removes a named custom relay. Does not change the relay settings, even if the relay is
+        /// currently in use.
+        #[rpc(meta, name = "remove_custom_relay")]
+        fn remove_custom_relay(&self, Self::Metadata, String) -> BoxFuture<(), Error>;
+
+        /// Returns all named custom relays. A custom relay set through `update_relay_settings` is
+        /// listed as `default`.
+        #[rpc(meta, name = "list_custom_relays")]
+        fn list_custom_relays(
+            &self,
+            Self::Metadata
+            ) -> BoxFuture<BTreeMap<String, CustomTunnelEndpoint>, Error>;
+
+        /// Makes the daemon connect through the named custom relay.
+        #[rpc(meta, name = "set_active_custom_relay")]
+        fn set_active_custom_relay(&self, Self::Metadata, String) -> BoxFuture<(), Error>;
+
+        /// Makes the daemon pick relays in the given country or city, or any relay if the
+        /// constraint is `null`. Stored custom relays are kept but not used.
+        #[rpc(meta, name = "set_location_constraint")]
+        fn set_location_constraint(
+            &self,
+            Self::Metadata,
+            Option<LocationConstraint>
+            ) -> BoxFuture<(), Error>;
+
+        /// Limits the transport protocol and port the daemon may pick for OpenVPN tunnels.
+        #[rpc(meta, name = "set_openvpn_constraints")]
+        fn set_openvpn_constraints(
+            &self,
+            Self::Metadata,
+            OpenVpnConstraints
+            ) -> BoxFuture<(), Error>;
+
         /// Set if the client should allow communication with the LAN while in secured state.
         #[rpc(meta, name = "set_allow_lan")]
         fn set_allow_lan(&self, Self::Metadata, bool) -> BoxFuture<(), Error>;
@@ -86,35 +305,146 @@ build_rpc_trait! {
         #[rpc(meta, name = "set_auto_connect")]
         fn set_auto_connect(&self, Self::Metadata, bool) -> BoxFuture<(), Error>;
 
+        /// Returns whether the daemon automatically establishes a tunnel on start.
+        #[rpc(meta, name = "get_auto_connect")]
+        fn get_auto_connect(&self, Self::Metadata) -> BoxFuture<bool, Error>;
+
+        /// Set how many seconds a connection attempt may take before it is aborted and the
+        /// daemon blocks with the `connect_timeout` error. Zero means no timeout.
+        #[rpc(meta, name = "set_connect_timeout")]
+        fn set_connect_timeout(&self, Self::Metadata, u32) -> BoxFuture<(), Error>;
+
+        /// Returns how many seconds a connection attempt may take, or zero if there is no limit.
+        #[rpc(meta, name = "get_connect_timeout")]
+        fn get_connect_timeout(&self, Self::Metadata) -> BoxFuture<u32, Error>;
+
         /// Try to connect if disconnected, or do nothing if already connecting/connected.
         #[rpc(meta, name = "connect")]
         fn connect(&self, Self::Metadata) -> BoxFuture<(), Error>;
 
+        /// Like `connect`, but only resolves once the tunnel is connected. Fails if the
+        /// daemon enters the error state, for example because of the connect timeout, if the
+        /// tunnel is disconnected before it connects, or if it hasn't connected within a minute.
+        /// When failing because of the error state, the error data is its serialized cause.
+        #[rpc(meta, name = "connect_and_wait")]
+        fn connect_and_wait(&self, Self::Metadata) -> BoxFuture<(), Error>;
+
         /// Disconnect the VPN tunnel if it is connecting/connected. Does nothing if already
         /// disconnected.
         #[rpc(meta, name = "disconnect")]
         fn disconnect(&self, Self::Metadata) -> BoxFuture<(), Error>;
 
-        /// Reconnect if connecting/connected, or do nothing if disconnected.
+        /// Sets the state the daemon strives towards. `secured` is the same as `connect` and
+        /// `unsecured` the same as `disconnect`. Unknown states are rejected with
+        /// `InvalidParams`.
+        #[rpc(meta, name = "set_target_state")]
+        fn set_target_state(&self, Self::Metadata, TargetState) -> BoxFuture<(), Error>;
+
+        /// Tear down the current tunnel and establish a new one. Connects if disconnected. Fails
+        /// if no account token is configured.
         #[rpc(meta, name = "reconnect")]
         fn reconnect(&self, Self::Metadata) -> BoxFuture<(), Error>;
 
+        /// Restrict the relay selection to the given country, city or hostname and connect.
+        /// Fails with an `InvalidParams` error if there are no active relays in the location.
+        #[rpc(meta, name = "connect_to")]
+        fn connect_to(
+            &self,
+            Self::Metadata,
+            Constraint<LocationConstraint>
+        ) -> BoxFuture<(), Error>;
+
+        /// Connect to the given relay without saving it as the custom relay. Only the next tunnel
+        /// uses it, so the relay settings apply again on reconnect. The relay is validated like
+        /// in `update_relay_settings`.
+        #[rpc(meta, name = "connect_once")]
+        fn connect_once(&self, Self::Metadata, CustomTunnelEndpoint) -> BoxFuture<(), Error>;
+
+        /// Measures the latency to every active relay in the given location and connects to
+        /// the fastest one. Returns the chosen relay, or an error if none of them responded.
+        #[rpc(meta, name = "connect_fastest")]
+        fn connect_fastest(
+            &self,
+            Self::Metadata,
+            Constraint<LocationConstraint>
+        ) -> BoxFuture<RelayLatency, Error>;
+
         /// Returns the current state of the Mullvad client. Changes to this state will
         /// be announced to subscribers of `new_state`.
         #[rpc(meta, name = "get_state")]
         fn get_state(&self, Self::Metadata) -> BoxFuture<TunnelState, Error>;
 
+        /// Returns whether the firewall currently allows all traffic, only traffic through the
+        /// tunnel, or blocks everything, and whether local network traffic is let through.
+        /// Traffic can be blocked even while disconnected.
+        #[rpc(meta, name = "get_firewall_state")]
+        fn get_firewall_state(&self, Self::Metadata) -> BoxFuture<FirewallState, Error>;
+
+        /// Returns the endpoint the connected tunnel uses, including protocol and port. Unlike
+        /// the relay settings, this is the endpoint that was actually selected. Returns `null`
+        /// unless connected.
+        #[rpc(meta, name = "get_current_relay")]
+        fn get_current_relay(&self, Self::Metadata) -> BoxFuture<Option<TunnelEndpoint>, Error>;
+
+        /// Returns the relay and endpoint that connecting with the current settings would use,
+        /// without connecting. Returns `null` if no relay matches the constraints or a custom
+        /// relay is configured. Relays are picked at random, so repeated calls may differ.
+        #[rpc(meta, name = "preview_relay_selection")]
+        fn preview_relay_selection(&self, Self::Metadata) -> BoxFuture<Option<RelayEndpoint>, Error>;
+
+        /// Returns whether the computer is online, and if so, whether the last request to the
+        /// API reached it.
+        #[rpc(meta, name = "get_connectivity")]
+        fn get_connectivity(&self, Self::Metadata) -> BoxFuture<Connectivity, Error>;
+
+        /// Returns how many times the daemon has tried to reconnect since the tunnel failed, and
+        /// when it tries next. Attempts are spaced out exponentially and the count is reset once
+        /// the tunnel connects or disconnects.
+        #[rpc(meta, name = "get_reconnect_status")]
+        fn get_reconnect_status(&self, Self::Metadata) -> BoxFuture<ReconnectStatus, Error>;
+
+        /// Returns up to `max_entries` of the most recent tunnel state transitions along with
+        /// when they happened, oldest first. Only the last `STATE_HISTORY_CAPACITY` are kept.
+        #[rpc(meta, name = "get_state_history")]
+        fn get_state_history(&self, Self::Metadata, u32) -> BoxFuture<Vec<StateHistoryEntry>, Error>;
+
+        /// Returns the tunnel state, current relay, traffic statistics, account expiry and
+        /// reconnection status in one response. Meant for monitoring tools that poll the daemon.
+        /// Never makes any API requests, so the account expiry is only present once the account
+        /// data has been fetched.
+        #[rpc(meta, name = "get_status_snapshot")]
+        fn get_status_snapshot(&self, Self::Metadata) -> BoxFuture<StatusSnapshot, Error>;
+
         /// Performs a geoIP lookup and returns the current location as perceived by the public
-        /// internet.
+        /// internet. Results are cached for a short while, or until the tunnel connects or
+        /// disconnects. If the lookup fails, the last known location is returned and marked as
+        /// stale.
         #[rpc(meta, name = "get_current_location")]
         fn get_current_location(&self, Self::Metadata) -> BoxFuture<Option<GeoIpLocation>, Error>;
 
+        /// Returns the public IPv4 and IPv6 addresses of the device. Results are cached for a
+        /// short while. If the lookup fails, the last known addresses are returned and marked as
+        /// stale.
+        #[rpc(meta, name = "get_public_ip")]
+        fn get_public_ip(&self, Self::Metadata) -> BoxFuture<Option<PublicIp>, Error>;
+
+        /// Returns traffic statistics for the connected tunnel. Returns `null` when not
+        /// connected, or if the tunnel doesn't report statistics. Only WireGuard tunnels do.
+        #[rpc(meta, name = "get_tunnel_stats")]
+        fn get_tunnel_stats(&self, Self::Metadata) -> BoxFuture<Option<TunnelStats>, Error>;
+
+        /// Returns the most recent error state the daemon entered, so that clients that
+        /// subscribed too late to see it can still show the reason. Cleared when a tunnel
+        /// connects successfully.
+        #[rpc(meta, name = "get_last_error")]
+        fn get_last_error(&self, Self::Metadata) -> BoxFuture<Option<ErrorState>, Error>;
+
         /// Makes the daemon exit its main loop and quit.
         #[rpc(meta, name = "shutdown")]
         fn shutdown(&self, Self::Metadata) -> BoxFuture<(), Error>;
 
         /// Saves the target tunnel state and enters a blocking state. The state is restored
-        /// upon restart.
+        /// upon restart. Returns once the blocking state has been requested.
         #[rpc(meta, name = "prepare_restart")]
         fn prepare_restart(&self, Self::Metadata) -> BoxFuture<(), Error>;
 
@@ -130,23 +460,103 @@ build_rpc_trait! {
         #[rpc(meta, name = "clear_account_history")]
         fn clear_account_history(&self, Self::Metadata) -> BoxFuture<(), Error>;
 
-        /// Sets openvpn's mssfix parameter
+        /// Sets openvpn's mssfix parameter, or `null` for the default. Fails with
+        /// `InvalidParams` if the value is outside of the supported range. Only OpenVPN tunnels
+        /// use it, so a connected WireGuard tunnel is left alone.
         #[rpc(meta, name = "set_openvpn_mssfix")]
         fn set_openvpn_mssfix(&self, Self::Metadata, Option<u16>) -> BoxFuture<(), Error>;
 
-        /// Sets proxy details for OpenVPN
+        /// Returns openvpn's mssfix parameter, or `null` if the default is used.
+        #[rpc(meta, name = "get_openvpn_mssfix")]
+        fn get_openvpn_mssfix(&self, Self::Metadata) -> BoxFuture<Option<u16>, Error>;
+
+        /// Sets the OpenVPN data channel cipher, or `null` for the default. Fails with
+        /// `InvalidParams` if the cipher isn't supported. Takes effect on the next OpenVPN
+        /// connection and is ignored by WireGuard tunnels.
+        #[rpc(meta, name = "set_openvpn_cipher")]
+        fn set_openvpn_cipher(&self, Self::Metadata, Option<String>) -> BoxFuture<(), Error>;
+
+        /// Returns the OpenVPN data channel cipher, or `null` if the default is used.
+        #[rpc(meta, name = "get_openvpn_cipher")]
+        fn get_openvpn_cipher(&self, Self::Metadata) -> BoxFuture<Option<String>, Error>;
+
+        /// Sets proxy details for OpenVPN. A custom proxy is only used while the bridge state is
+        /// `on`. Fails with an `InvalidParams` error if the custom proxy settings are invalid.
         #[rpc(meta, name = "set_bridge_settings")]
         fn set_bridge_settings(&self, Self::Metadata, BridgeSettings) -> BoxFuture<(), Error>;
 
+        /// Returns proxy details for OpenVPN
+        #[rpc(meta, name = "get_bridge_settings")]
+        fn get_bridge_settings(&self, Self::Metadata) -> BoxFuture<BridgeSettings, Error>;
+
         /// Sets bridge state
         #[rpc(meta, name = "set_bridge_state")]
         fn set_bridge_state(&self, Self::Metadata, BridgeState) -> BoxFuture<(), Error>;
 
+        /// Returns the bridge state. In `auto`, bridges are only used after direct connection
+        /// attempts have failed.
+        #[rpc(meta, name = "get_bridge_state")]
+        fn get_bridge_state(&self, Self::Metadata) -> BoxFuture<BridgeState, Error>;
+
+        /// Limits relay selection to relays owned by Mullvad and/or hosted by certain providers.
+        /// Applies on top of the relay constraints and reconnects the tunnel if it changed. Fails
+        /// with `InvalidParams` if the provider allowlist is empty.
+        #[rpc(meta, name = "set_relay_filter")]
+        fn set_relay_filter(&self, Self::Metadata, RelayFilter) -> BoxFuture<(), Error>;
+
+        /// Returns the filter limiting relay selection by owner and hosting provider.
+        #[rpc(meta, name = "get_relay_filter")]
+        fn get_relay_filter(&self, Self::Metadata) -> BoxFuture<RelayFilter, Error>;
+
+        /// Sets how a relay is picked among the relays that match the constraints and filter.
+        /// Takes effect on the next connect and in `preview_relay_selection`.
+        #[rpc(meta, name = "set_relay_selection_mode")]
+        fn set_relay_selection_mode(&self, Self::Metadata, SelectionMode) -> BoxFuture<(), Error>;
+
+        /// Returns how a relay is picked among the relays that match the constraints and filter.
+        #[rpc(meta, name = "get_relay_selection_mode")]
+        fn get_relay_selection_mode(&self, Self::Metadata) -> BoxFuture<SelectionMode, Error>;
+
+        /// Set whether to check that a selected relay responds before connecting to it. Relays
+        /// that don't respond are skipped in favor of the next candidate.
+        #[rpc(meta, name = "set_probe_before_connect")]
+        fn set_probe_before_connect(&self, Self::Metadata, bool) -> BoxFuture<(), Error>;
+
+        /// Returns whether selected relays are checked to respond before connecting to them.
+        #[rpc(meta, name = "get_probe_before_connect")]
+        fn get_probe_before_connect(&self, Self::Metadata) -> BoxFuture<bool, Error>;
+
         /// Set if IPv6 is enabled in the tunnel
         #[rpc(meta, name = "set_enable_ipv6")]
         fn set_enable_ipv6(&self, Self::Metadata, bool) -> BoxFuture<(), Error>;
 
-        /// Set MTU for wireguard tunnels
+        /// Returns whether IPv6 is enabled in the tunnel
+        #[rpc(meta, name = "get_enable_ipv6")]
+        fn get_enable_ipv6(&self, Self::Metadata) -> BoxFuture<bool, Error>;
+
+        /// Set the DNS servers to use inside the tunnel, or `null` to use the default Mullvad DNS.
+        /// Takes effect on the next connect. Fails with `InvalidParams` if the list is empty or
+        /// contains unspecified or loopback addresses. Duplicates are removed.
+        #[rpc(meta, name = "set_dns")]
+        fn set_dns(&self, Self::Metadata, Option<Vec<IpAddr>>) -> BoxFuture<(), Error>;
+
+        /// Returns the custom DNS servers used inside the tunnel, or `null` if the default
+        /// Mullvad DNS is used.
+        #[rpc(meta, name = "get_dns")]
+        fn get_dns(&self, Self::Metadata) -> BoxFuture<Option<Vec<IpAddr>>, Error>;
+
+        /// Set which categories of domains the Mullvad DNS servers should block. Blocking is done
+        /// by using a different Mullvad DNS server, so it is ignored while custom DNS servers are
+        /// set with `set_dns`.
+        #[rpc(meta, name = "set_dns_content_blockers")]
+        fn set_dns_content_blockers(&self, Self::Metadata, DnsBlockers) -> BoxFuture<(), Error>;
+
+        /// Returns which categories of domains the Mullvad DNS servers block.
+        #[rpc(meta, name = "get_dns_content_blockers")]
+        fn get_dns_content_blockers(&self, Self::Metadata) -> BoxFuture<DnsBlockers, Error>;
+
+        /// Set MTU for wireguard tunnels, or `null` for the default. Fails with `InvalidParams`
+        /// if the MTU is outside of the supported range.
         #[rpc(meta, name = "set_wireguard_mtu")]
         fn set_wireguard_mtu(&self, Self::Metadata, Option<u16>) -> BoxFuture<(), Error>;
 
@@ -166,7 +576,8 @@ build_rpc_trait! {
         #[rpc(meta, name = "get_wireguard_key")]
         fn get_wireguard_key(&self, Self::Metadata) -> BoxFuture<Option<wireguard::PublicKey>, Error>;
 
-        /// Verify if current wireguard key is still valid
+        /// Verify if current wireguard key is still valid. Fails with `NO_WIREGUARD_KEY_CODE` if
+        /// there is no key to verify.
         #[rpc(meta, name = "verify_wireguard_key")]
         fn verify_wireguard_key(&self, Self::Metadata) -> BoxFuture<bool, Error>;
 
@@ -174,10 +585,59 @@ build_rpc_trait! {
         #[rpc(meta, name = "get_current_version")]
         fn get_current_version(&self, Self::Metadata) -> BoxFuture<String, Error>;
 
+        /// Returns up to `max_lines` of the daemon's most recent log lines, oldest first. Only
+        /// the last `RECENT_LOG_CAPACITY` lines are kept.
+        #[rpc(meta, name = "get_recent_logs")]
+        fn get_recent_logs(&self, Self::Metadata, u32) -> BoxFuture<Vec<String>, Error>;
+
+        /// Changes the most verbose level the daemon logs at, until it restarts.
+        #[rpc(meta, name = "set_log_level")]
+        fn set_log_level(&self, Self::Metadata, LogLevel) -> BoxFuture<(), Error>;
+
+        /// Returns the most verbose level the daemon logs at, or `null` if logging is off.
+        #[rpc(meta, name = "get_log_level")]
+        fn get_log_level(&self, Self::Metadata) -> BoxFuture<Option<LogLevel>, Error>;
+
+        /// Returns whether a client of the given version is able to use this daemon. Clients
+        /// should check this before relying on any other methods.
+        #[rpc(meta, name = "is_compatible")]
+        fn is_compatible(&self, Self::Metadata, String) -> BoxFuture<bool, Error>;
+
         /// Retrieve information about the currently running and latest versions of the app
         #[rpc(meta, name = "get_version_info")]
         fn get_version_info(&self, Self::Metadata) -> BoxFuture<version::AppVersionInfo, Error>;
 
+        /// Restores all settings except the account to their defaults. Changes that affect the
+        /// tunnel connection take effect the next time it connects.
+        #[rpc(meta, name = "reset_settings")]
+        fn reset_settings(&self, Self::Metadata) -> BoxFuture<(), Error>;
+
+        /// Replaces the settings with the ones in the settings file, discarding changes that
+        /// have not been written to it, and applies any differences. Lets other programs change
+        /// the settings by editing the file. Fails if the file is missing or can't be parsed, in
+        /// which case the current settings are kept. The error data then lists what caused the
+        /// failure, as `{"causes": [...]}`.
+        #[rpc(meta, name = "reload_settings")]
+        fn reload_settings(&self, Self::Metadata) -> BoxFuture<(), Error>;
+
+        /// Remove cached relay lists, version info and API responses. Settings and the account
+        /// are kept. Returns once the cache files have been removed.
+        #[rpc(meta, name = "clear_cache")]
+        fn clear_cache(&self, Self::Metadata) -> BoxFuture<(), Error>;
+
+        /// Sends all subsequent API requests to the given address instead of the default API
+        /// server, or to the default server again if `null`. Meant for testing against staging
+        /// servers, which still have to present a certificate for the API hostname. The address
+        /// is persisted. Fails with `InvalidParams` if the port is zero, or if the IP is
+        /// unspecified, multicast or the IPv4 broadcast address.
+        #[rpc(meta, name = "set_api_endpoint")]
+        fn set_api_endpoint(&self, Self::Metadata, Option<SocketAddr>) -> BoxFuture<(), Error>;
+
+        /// Returns the address used instead of the default API server, or `null` if the default
+        /// is used.
+        #[rpc(meta, name = "get_api_endpoint")]
+        fn get_api_endpoint(&self, Self::Metadata) -> BoxFuture<Option<SocketAddr>, Error>;
+
         /// Remove all configuration and cache files
         #[rpc(meta, name = "factory_reset")]
         fn factory_reset(&self, Self::Metadata) -> BoxFuture<(), Error>;
@@ -198,56 +658,233 @@ build_rpc_trait! {
         #[rpc(meta, name = "clear_split_tunnel_processes")]
         fn clear_split_tunnel_processes(&self, Self::Metadata) -> BoxFuture<(), Error>;
 
+        /// Retrieve applications to exclude from the tunnel
+        #[rpc(meta, name = "list_split_tunnel_apps")]
+        fn list_split_tunnel_apps(&self, Self::Metadata) -> BoxFuture<Vec<PathBuf>, Error>;
+
+        /// Add an application to exclude from the tunnel. Fails with `InvalidParams` unless the
+        /// path is absolute and exists. Adding an application twice has no effect.
+        #[rpc(meta, name = "add_split_tunnel_app")]
+        fn add_split_tunnel_app(&self, Self::Metadata, PathBuf) -> BoxFuture<(), Error>;
+
+        /// Remove an application excluded from the tunnel
+        #[rpc(meta, name = "remove_split_tunnel_app")]
+        fn remove_split_tunnel_app(&self, Self::Metadata, PathBuf) -> BoxFuture<(), Error>;
+
         #[pubsub(name = "daemon_event")] {
-            /// Subscribes to events from the daemon.
+            /// Subscribes to events from the daemon. The optional argument is either a
+            /// `DaemonEventSubscribeOptions` object or a boolean for `send_initial_state`. With
+            /// `send_initial_state`, the current tunnel state is sent as the first event, so that
+            /// no separate `get_state` call is needed. With `min_error_severity`, error states
+            /// that are less severe are skipped, including the initial state.
             #[rpc(name = "daemon_event_subscribe")]
             fn daemon_event_subscribe(
                 &self,
                 Self::Metadata,
-                pubsub::Subscriber<DaemonEvent>
+                pubsub::Subscriber<DaemonEvent>,
+                Trailing<DaemonEventSubscribeArgs>
             );
 
             /// Unsubscribes from the `daemon_event` event notifications.
             #[rpc(name = "daemon_event_unsubscribe")]
             fn daemon_event_unsubscribe(&self, SubscriptionId) -> BoxFuture<(), Error>;
         }
+
+        #[pubsub(name = "connection_progress")] {
+            /// Subscribes to the phases the daemon goes through while establishing a tunnel.
+            #[rpc(name = "connection_progress_subscribe")]
+            fn connection_progress_subscribe(
+                &self,
+                Self::Metadata,
+                pubsub::Subscriber<ConnectionProgress>
+            );
+
+            /// Unsubscribes from the `connection_progress` notifications.
+            #[rpc(name = "connection_progress_unsubscribe")]
+            fn connection_progress_unsubscribe(&self, SubscriptionId) -> BoxFuture<(), Error>;
+        }
+
+        #[pubsub(name = "connectivity")] {
+            /// Subscribes to the computer going offline or coming back online. Changes are only
+            /// sent once they have lasted for a couple of seconds.
+            #[rpc(name = "connectivity_subscribe")]
+            fn connectivity_subscribe(
+                &self,
+                Self::Metadata,
+                pubsub::Subscriber<Connectivity>
+            );
+
+            /// Unsubscribes from the `connectivity` notifications.
+            #[rpc(name = "connectivity_unsubscribe")]
+            fn connectivity_unsubscribe(&self, SubscriptionId) -> BoxFuture<(), Error>;
+        }
+
+        #[pubsub(name = "tunnel_stats")] {
+            /// Subscribes to tunnel stats samples, which are sent periodically while connected.
+            #[rpc(name = "tunnel_stats_subscribe")]
+            fn tunnel_stats_subscribe(
+                &self,
+                Self::Metadata,
+                pubsub::Subscriber<TunnelStats>
+            );
+
+            /// Unsubscribes from the `tunnel_stats` notifications.
+            #[rpc(name = "tunnel_stats_unsubscribe")]
+            fn tunnel_stats_unsubscribe(&self, SubscriptionId) -> BoxFuture<(), Error>;
+        }
+
+        #[pubsub(name = "relay_list")] {
+            /// Subscribes to the relay list, which is sent every time it has been refreshed.
+            #[rpc(name = "relay_list_subscribe")]
+            fn relay_list_subscribe(
+                &self,
+                Self::Metadata,
+                pubsub::Subscriber<RelayList>
+            );
+
+            /// Unsubscribes from the `relay_list` notifications.
+            #[rpc(name = "relay_list_unsubscribe")]
+            fn relay_list_unsubscribe(&self, SubscriptionId) -> BoxFuture<(), Error>;
+        }
+
+        #[pubsub(name = "account")] {
+            /// Subscribes to changes of the active account. `null` is sent when logging out.
+            #[rpc(name = "account_subscribe")]
+            fn account_subscribe(
+                &self,
+                Self::Metadata,
+                pubsub::Subscriber<Option<AccountToken>>
+            );
+
+            /// Unsubscribes from the `account` notifications.
+            #[rpc(name = "account_unsubscribe")]
+            fn account_unsubscribe(&self, SubscriptionId) -> BoxFuture<(), Error>;
+        }
+
+        #[pubsub(name = "settings")] {
+            /// Subscribes to the settings, which are sent every time they change. Changes made in
+            /// quick succession are sent as one notification.
+            #[rpc(name = "settings_subscribe")]
+            fn settings_subscribe(
+                &self,
+                Self::Metadata,
+                pubsub::Subscriber<Settings>
+            );
+
+            /// Unsubscribes from the `settings` notifications.
+            #[rpc(name = "settings_unsubscribe")]
+            fn settings_unsubscribe(&self, SubscriptionId) -> BoxFuture<(), Error>;
+        }
+
+        #[pubsub(name = "tunnel_state")] {
+            /// Subscribes to the tunnel state. States entered in quick succession, like during a
+            /// reconnect, are sent as one notification carrying the latest state. Use
+            /// `daemon_event` to be notified of every state without delay.
+            #[rpc(name = "tunnel_state_subscribe")]
+            fn tunnel_state_subscribe(
+                &self,
+                Self::Metadata,
+                pubsub::Subscriber<TunnelState>
+            );
+
+            /// Unsubscribes from the `tunnel_state` notifications.
+            #[rpc(name = "tunnel_state_unsubscribe")]
+            fn tunnel_state_unsubscribe(&self, SubscriptionId) -> BoxFuture<(), Error>;
+        }
+
+        #[pubsub(name = "log")] {
+            /// Subscribes to new log records of the daemon at the given level or more severe.
+            /// Records are only collected while there is at least one subscriber.
+            #[rpc(name = "log_subscribe")]
+            fn log_subscribe(
+                &self,
+                Self::Metadata,
+                pubsub::Subscriber<LogRecord>,
+                LogLevel
+            );
+
+            /// Unsubscribes from the `log` notifications.
+            #[rpc(name = "log_unsubscribe")]
+            fn log_unsubscribe(&self, SubscriptionId) -> BoxFuture<(), Error>;
+        }
     }
 }
 
 pub struct ManagementInterfaceServer {
     server: talpid_ipc::IpcServer,
-    subscriptions: Arc<RwLock<HashMap<SubscriptionId, pubsub::Sink<DaemonEvent>>>>,
+    /// Broadcaster that the broadcasters handed out by the server are cloned from.
+    broadcaster: ManagementInterfaceEventBroadcaster,
 }
 
 impl ManagementInterfaceServer {
     pub fn start(tunnel_tx: DaemonCommandSender) -> Result<Self, talpid_ipc::Error> {
-        let rpc = ManagementInterface::new(tunnel_tx);
-        let subscriptions = rpc.subscriptions.clone();
-
-        let mut io = PubSubHandler::default();
-        io.extend_with(rpc.to_delegate());
-        let meta_io: MetaIoHandler<Meta> = io.into();
         let path = mullvad_paths::get_rpc_socket_path();
-        let server = talpid_ipc::IpcServer::start_with_metadata(
+        Self::start_at(tunnel_tx, &path.to_string_lossy())
+    }
+
+    /// Starts the management interface on a unix domain socket at `path` that only the user
+    /// running the daemon can connect to. The socket is never accessible to other users, not
+    /// even briefly after it's created.
+    ///
+    /// Note that this locks out the GUI and CLI when they run as a different user than the
+    /// daemon, which is usually the case since the daemon runs as root. Use [`Self::start`] for a
+    /// socket that all local users can connect to.
+    #[cfg(unix)]
+    pub fn start_unix(
+        tunnel_tx: DaemonCommandSender,
+        path: &std::path::Path,
+    ) -> Result<Self, talpid_ipc::Error> {
+        let (meta_io, broadcaster) = Self::create_handler(tunnel_tx);
+        let server = talpid_ipc::IpcServer::start_with_metadata_and_mode(
             meta_io,
-            meta_extractor,
+            meta_extractor(broadcaster.subscriptions.clone()),
             &path.to_string_lossy(),
+            0o600,
         )?;
         Ok(ManagementInterfaceServer {
             server,
-            subscriptions,
+            broadcaster,
+        })
+    }
+
+    fn start_at(tunnel_tx: DaemonCommandSender, path: &str) -> Result<Self, talpid_ipc::Error> {
+        let (meta_io, broadcaster) = Self::create_handler(tunnel_tx);
+        let server = talpid_ipc::IpcServer::start_with_metadata(
+            meta_io,
+            meta_extractor(broadcaster.subscriptions.clone()),
+            path,
+        )?;
+        Ok(ManagementInterfaceServer {
+            server,
+            broadcaster,
         })
     }
 
+    fn create_handler(
+        tunnel_tx: DaemonCommandSender,
+    ) -> (MetaIoHandler<Meta>, ManagementInterfaceEventBroadcaster) {
+        let rpc = ManagementInterface::new(tunnel_tx);
+        let broadcaster = rpc.broadcaster();
+
+        let mut io = PubSubHandler::default();
+        io.extend_with(rpc.to_delegate());
+        (io.into(), broadcaster)
+    }
+
     pub fn socket_path(&self) -> &str {
         self.server.path()
     }
 
     pub fn event_broadcaster(&self) -> ManagementInterfaceEventBroadcaster {
-        ManagementInterfaceEventBroadcaster {
-            subscriptions: self.subscriptions.clone(),
-            close_handle: Some(self.server.close_handle()),
-        }
+        let mut broadcaster = self.broadcaster.clone();
+        broadcaster.close_handle = Some(self.server.close_handle());
+        broadcaster
+    }
+
+    /// Returns a broadcaster for streaming log records to `log` subscribers. Unlike
+    /// `event_broadcaster`, dropping it doesn't shut down the server.
+    pub fn log_broadcaster(&self) -> ManagementInterfaceEventBroadcaster {
+        self.broadcaster.clone()
     }
 
     /// Consumes the server and waits for it to finish. Returns an error if the server exited
@@ -260,28 +897,54 @@ impl ManagementInterfaceServer {
 /// A handle that allows broadcasting messages to all subscribers of the management interface.
 #[derive(Clone)]
 pub struct ManagementInterfaceEventBroadcaster {
-    subscriptions: Arc<RwLock<HashMap<SubscriptionId, pubsub::Sink<DaemonEvent>>>>,
+    subscriptions: Arc<ActiveSubscriptions>,
+    /// Event loop on which coalesced notifications are sent once their interval has passed.
+    event_loop: Arc<event_loop::CoreHandle>,
+    timer: tokio_timer::Timer,
+    settings_coalesce_interval: Duration,
+    tunnel_state_coalesce_interval: Duration,
     close_handle: Option<talpid_ipc::CloseHandle>,
 }
 
 impl EventListener for ManagementInterfaceEventBroadcaster {
-    /// Sends a new state update to all `new_state` subscribers of the management interface.
+    /// Sends a new state update to all `daemon_event` and `tunnel_state` subscribers of the
+    /// management interface.
     fn notify_new_state(&self, new_state: TunnelState) {
+        self.notify_coalesced(
+            new_state.clone(),
+            self.tunnel_state_coalesce_interval,
+            |subscriptions| &subscriptions.pending_tunnel_state,
+            |subscriptions| &subscriptions.tunnel_state,
+        );
         self.notify(DaemonEvent::TunnelState(new_state));
     }
 
-    /// Sends settings to all `settings` subscribers of the management interface.
+    /// Sends settings to all `daemon_event` and `settings` subscribers of the management
+    /// interface.
     fn notify_settings(&self, settings: Settings) {
         log::debug!("Broadcasting new settings");
+        self.notify_coalesced(
+            settings.clone(),
+            self.settings_coalesce_interval,
+            |subscriptions| &subscriptions.pending_settings,
+            |subscriptions| &subscriptions.settings,
+        );
         self.notify(DaemonEvent::Settings(settings));
     }
 
-    /// Sends settings to all `settings` subscribers of the management interface.
+    /// Sends the relay list to all `daemon_event` and `relay_list` subscribers of the
+    /// management interface.
     fn notify_relay_list(&self, relay_list: RelayList) {
         log::debug!("Broadcasting new relay list");
+        Self::notify_subscribers(&self.subscriptions.relay_list, relay_list.clone());
         self.notify(DaemonEvent::RelayList(relay_list));
     }
 
+    fn notify_account(&self, account_token: Option<AccountToken>) {
+        log::debug!("Broadcasting account change");
+        Self::notify_subscribers(&self.subscriptions.account, account_token);
+    }
+
     fn notify_app_version(&self, app_version_info: version::AppVersionInfo) {
         log::debug!("Broadcasting new app version info");
         self.notify(DaemonEvent::AppVersionInfo(app_version_info));
@@ -291,13 +954,105 @@ impl EventListener for ManagementInterfaceEventBroadcaster {
         log::debug!("Broadcasting new wireguard key event");
         self.notify(DaemonEvent::WireguardKey(key_event));
     }
+
+    fn notify_location(&self, location: GeoIpLocation) {
+        log::debug!("Broadcasting new location");
+        self.notify(DaemonEvent::Location(location));
+    }
+
+    fn notify_account_expiry(&self, account_expiry: AccountExpiry) {
+        log::debug!("Broadcasting new account expiry");
+        self.notify(DaemonEvent::AccountExpiry(account_expiry));
+    }
+
+    fn notify_connection_progress(&self, progress: ConnectionProgress) {
+        log::debug!("Broadcasting connection progress: {:?}", progress);
+        Self::notify_subscribers(&self.subscriptions.connection_progress, progress);
+    }
+
+    fn notify_tunnel_stats(&self, tunnel_stats: TunnelStats) {
+        Self::notify_subscribers(&self.subscriptions.tunnel_stats, tunnel_stats);
+    }
+
+    fn notify_connectivity(&self, connectivity: Connectivity) {
+        log::debug!("Broadcasting connectivity: {:?}", connectivity);
+        Self::notify_subscribers(&self.subscriptions.connectivity, connectivity);
+    }
+
+    fn has_tunnel_stats_subscribers(&self) -> bool {
+        !self.subscriptions.tunnel_stats.read().is_empty()
+    }
+}
+
+impl logging::LogListener for ManagementInterfaceEventBroadcaster {
+    fn wants_logs(&self) -> bool {
+        !self.subscriptions.log.read().is_empty()
+    }
+
+    /// Sends a log record to all `log` subscribers of the management interface.
+    fn notify_log(&self, record: LogRecord) {
+        Self::notify_subscribers(&self.subscriptions.log, record);
+    }
 }
 
 impl ManagementInterfaceEventBroadcaster {
     fn notify(&self, value: DaemonEvent) {
-        let subscriptions = self.subscriptions.read();
-        for sink in subscriptions.values() {
-            let _ = sink.notify(Ok(value.clone())).wait();
+        Self::notify_subscribers(&self.subscriptions.daemon_event, value);
+    }
+
+    /// Sends `value` to the `subscribers` after `interval`, unless a send is already scheduled,
+    /// in which case that send will carry `value` instead. The value waits in the `pending`
+    /// slot until then. Nothing is scheduled while there are no subscribers.
+    fn notify_coalesced<T: Clone + Send + 'static>(
+        &self,
+        value: T,
+        interval: Duration,
+        pending: fn(&ActiveSubscriptions) -> &Mutex<Option<T>>,
+        subscribers: fn(&ActiveSubscriptions) -> &SubscriptionSenders<T>,
+    ) {
+        if subscribers(&self.subscriptions).read().is_empty() {
+            return;
+        }
+        let send_scheduled = pending(&self.subscriptions).lock().replace(value).is_some();
+        if !send_scheduled {
+            let subscriptions = self.subscriptions.clone();
+            let send = self.timer.sleep(interval).then(move |_| {
+                if let Some(value) = pending(&subscriptions).lock().take() {
+                    Self::notify_subscribers(subscribers(&subscriptions), value);
+                }
+                Ok::<(), ()>(())
+            });
+            self.event_loop.remote.spawn(move |_| send);
+        }
+    }
+
+    fn notify_subscribers<T: Clone>(subscriptions: &SubscriptionSenders<T>, value: T) {
+        let dead_subscriptions: Vec<(SubscriptionId, bool)> = {
+            let mut subscriptions = subscriptions.write();
+            let dead_subscriptions: Vec<_> = subscriptions
+                .iter_mut()
+                .filter_map(|(id, event_tx)| {
+                    event_tx
+                        .try_send(value.clone())
+                        .err()
+                        .map(|error| (id.clone(), error.is_full()))
+                })
+                .collect();
+            for (id, _) in &dead_subscriptions {
+                subscriptions.remove(id);
+            }
+            dead_subscriptions
+        };
+
+        for (id, is_full) in dead_subscriptions {
+            if is_full {
+                log::warn!(
+                    "Removing subscription with id {:?} since it isn't keeping up",
+                    id
+                );
+            } else {
+                log::debug!("Removing closed subscription with id {:?}", id);
+            }
         }
     }
 }
@@ -311,8 +1066,16 @@ impl Drop for ManagementInterfaceEventBroadcaster {
 }
 
 struct ManagementInterface {
-    subscriptions: Arc<RwLock<HashMap<SubscriptionId, pubsub::Sink<DaemonEvent>>>>,
+    subscriptions: Arc<ActiveSubscriptions>,
     tx: DaemonCommandSender,
+    /// Event loop on which events are forwarded to subscribers.
+    event_loop: Arc<event_loop::CoreHandle>,
+    /// Timer shared by all requests that have a deadline.
+    timer: tokio_timer::Timer,
+    /// The ID assigned to the next subscription, shared by all pubsub channels.
+    next_subscription_id: AtomicU64,
+    /// The ID assigned to the next traced request.
+    next_request_id: AtomicU64,
 }
 
 impl ManagementInterface {
@@ -320,62 +1083,392 @@ impl ManagementInterface {
         ManagementInterface {
             subscriptions: Default::default(),
             tx,
+            event_loop: Arc::new(event_loop::spawn()),
+            timer: tokio_timer::Timer::default(),
+            next_subscription_id: AtomicU64::new(0),
+            next_request_id: AtomicU64::new(0),
         }
     }
 
-    /// Sends a command to the daemon and maps the error to an RPC error.
-    fn send_command_to_daemon(
-        &self,
-        command: DaemonCommand,
-    ) -> impl Future<Item = (), Error = Error> {
-        future::result(self.tx.send(command)).map_err(|_| Error::internal_error())
+    /// Returns a broadcaster that sends to the subscribers of this interface. Dropping it doesn't
+    /// shut down any server.
+    fn broadcaster(&self) -> ManagementInterfaceEventBroadcaster {
+        ManagementInterfaceEventBroadcaster {
+            subscriptions: self.subscriptions.clone(),
+            event_loop: self.event_loop.clone(),
+            timer: self.timer.clone(),
+            settings_coalesce_interval: SETTINGS_COALESCE_INTERVAL,
+            tunnel_state_coalesce_interval: TUNNEL_STATE_COALESCE_INTERVAL,
+            close_handle: None,
+        }
     }
 
-    /// Converts a REST API error for an account into a JSONRPC error for the JSONRPC client.
-    fn map_rest_account_error(error: RestError) -> Error {
-        match error {
-            RestError::ApiError(status, message)
-                if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN =>
-            {
-                Error {
-                    code: ErrorCode::from(INVALID_ACCOUNT_CODE),
-                    message,
-                    data: None,
-                }
+    /// Assigns an ID to a request and logs it, along with the outcome once `future` resolves.
+    /// Only logged at trace level.
+    fn traced<T, F>(&self, method: &'static str, future: F) -> BoxFuture<T, Error>
+    where
+        T: Send + 'static,
+        F: Future<Item = T, Error = Error> + Send + 'static,
+    {
+        let id = self.next_request_id.fetch_add(1, Ordering::Relaxed);
+        log::trace!("Request {} ({}) started", id, method);
+        Box::new(future.then(move |result| {
+            match &result {
+                Ok(_) => log::trace!("Request {} ({}) succeeded", id, method),
+                Err(error) => log::trace!("Request {} ({}) failed: {}", id, method, error.message),
             }
-            _ => Error::internal_error(),
-        }
+            result
+        }))
     }
-}
 
-impl ManagementInterfaceApi for ManagementInterface {
-    type Metadata = Meta;
+    /// Forwards events to a single subscriber, one at a time. A slow subscriber only delays its
+    /// own events, which are still delivered in the order they were broadcast, until it falls
+    /// `SUBSCRIBER_BUFFER_SIZE` events behind and is dropped.
+    fn forward_events<T: Serialize>(
+        events: impl Stream<Item = T, Error = ()>,
+        sink: pubsub::Sink<T>,
+        filter: impl Fn(&T) -> bool,
+    ) -> impl Future<Item = (), Error = ()> {
+        events
+            .filter(move |event| filter(event))
+            .for_each(move |event| sink.notify(Ok(event)).map(|_| ()).map_err(|_| ()))
+    }
 
-    fn create_new_account(&self, _: Self::Metadata) -> BoxFuture<String, Error> {
-        let (tx, rx) = sync::oneshot::channel();
-        let future = self
-            .send_command_to_daemon(DaemonCommand::CreateNewAccount(tx))
-            .and_then(|_| rx.map_err(|_| Error::internal_error()))
-            .and_then(|result| match result {
-                Ok(account_token) => Ok(account_token),
-                Err(_) => Err(Error::internal_error()),
-            });
+    /// Registers a subscriber on `subscriptions`. The assigned id is sent to the client as the
+    /// response to its subscribe request before this returns, and is what the unsubscribe method
+    /// of the same channel accepts.
+    fn subscribe<T: Serialize + Send + 'static>(
+        &self,
+        meta: &Meta,
+        subscriptions: &SubscriptionSenders<T>,
+        subscriber: pubsub::Subscriber<T>,
+    ) {
+        self.subscribe_filtered(meta, subscriptions, subscriber, |_| true)
+    }
 
-        Box::new(future)
+    /// Like `subscribe`, but only forwards the events that `filter` accepts.
+    fn subscribe_filtered<T: Serialize + Send + 'static>(
+        &self,
+        meta: &Meta,
+        subscriptions: &SubscriptionSenders<T>,
+        subscriber: pubsub::Subscriber<T>,
+        filter: impl Fn(&T) -> bool + Send + 'static,
+    ) {
+        self.subscribe_inner(meta, subscriptions, subscriber, filter, || future::ok(None))
     }
 
-    fn get_account_data(
+    /// Like `subscribe_filtered`, but first sends the value that `initial` resolves to, if
+    /// `filter` accepts it. `initial` is only called once the subscription is registered, so
+    /// events broadcast while it resolves are delivered after it instead of being lost.
+    fn subscribe_filtered_with_initial<T, F>(
         &self,
-        _: Self::Metadata,
-        account_token: AccountToken,
-    ) -> BoxFuture<AccountData, Error> {
-        log::debug!("get_account_data");
-        let (tx, rx) = sync::oneshot::channel();
-        let future = self
-            .send_command_to_daemon(DaemonCommand::GetAccountData(tx, account_token))
-            .and_then(|_| rx.map_err(|_| Error::internal_error()))
-            .and_then(|rpc_future| {
-                rpc_future.map_err(|error: RestError| {
+        meta: &Meta,
+        subscriptions: &SubscriptionSenders<T>,
+        subscriber: pubsub::Subscriber<T>,
+        filter: impl Fn(&T) -> bool + Send + 'static,
+        initial: impl FnOnce() -> F,
+    ) where
+        T: Serialize + Send + 'static,
+        F: Future<Item = T, Error = Error> + Send + 'static,
+    {
+        self.subscribe_inner(meta, subscriptions, subscriber, filter, || {
+            initial().map(Some).or_else(|error| {
+                log::error!(
+                    "Failed to get initial subscription value: {}",
+                    error.message
+                );
+                Ok(None)
+            })
+        })
+    }
+
+    fn subscribe_inner<T, F>(
+        &self,
+        meta: &Meta,
+        subscriptions: &SubscriptionSenders<T>,
+        subscriber: pubsub::Subscriber<T>,
+        filter: impl Fn(&T) -> bool + Send + 'static,
+        initial: impl FnOnce() -> F,
+    ) where
+        T: Serialize + Send + 'static,
+        F: Future<Item = Option<T>, Error = ()> + Send + 'static,
+    {
+        let subscription_count = meta.subscription_count.fetch_add(1, Ordering::SeqCst);
+        let slot = SubscriptionSlot(meta.subscription_count.clone());
+        if subscription_count >= MAX_SUBSCRIPTIONS_PER_CONNECTION {
+            log::warn!(
+                "Rejecting subscription since the connection already holds {} subscriptions",
+                MAX_SUBSCRIPTIONS_PER_CONNECTION
+            );
+            let _ = subscriber.reject(Error {
+                code: ErrorCode::InvalidRequest,
+                message: "Too many subscriptions".to_owned(),
+                data: None,
+            });
+            return;
+        }
+
+        let id = SubscriptionId::Number(self.next_subscription_id.fetch_add(1, Ordering::Relaxed));
+        // Register the subscription before its id is sent, so that the client can unsubscribe
+        // as soon as it has received the id.
+        let (event_tx, event_rx) = sync::mpsc::channel(SUBSCRIBER_BUFFER_SIZE);
+        subscriptions.write().insert(id.clone(), event_tx);
+        let sink = match subscriber.assign_id(id.clone()) {
+            Ok(sink) => sink,
+            Err(()) => {
+                // Assigning an id only fails if the client stopped waiting for the response to
+                // its subscribe request, so there is no one left to send an error to.
+                log::warn!(
+                    "Dropping subscription {:?} since the client is no longer waiting for it",
+                    id
+                );
+                subscriptions.write().remove(&id);
+                return;
+            }
+        };
+        log::debug!("Accepting new subscription with id {:?}", id);
+        meta.subscription_ids.lock().insert(id.clone());
+        let subscription_ids = meta.subscription_ids.clone();
+        let events = initial()
+            .map(|initial| stream::iter_ok(initial).chain(event_rx))
+            .flatten_stream();
+        self.event_loop.remote.spawn(move |_| {
+            Self::forward_events(events, sink, filter).then(move |result| {
+                subscription_ids.lock().remove(&id);
+                drop(slot);
+                result
+            })
+        });
+    }
+
+    fn unsubscribe<T>(
+        subscriptions: &SubscriptionSenders<T>,
+        id: SubscriptionId,
+    ) -> BoxFuture<(), Error> {
+        let was_removed = subscriptions.write().remove(&id).is_some();
+        let result = if was_removed {
+            log::debug!("Unsubscribing id {:?}", id);
+            future::ok(())
+        } else {
+            future::err(Error {
+                code: ErrorCode::InvalidParams,
+                message: "Invalid subscription".to_owned(),
+                data: None,
+            })
+        };
+        Box::new(result)
+    }
+
+    /// Sends a command to the daemon and maps the error to an RPC error.
+    fn send_command_to_daemon(
+        &self,
+        command: DaemonCommand,
+    ) -> impl Future<Item = (), Error = Error> {
+        future::result(self.tx.send(command)).map_err(Self::map_daemon_error)
+    }
+
+    /// Resolves once a connected tunnel state is received on `events`, or fails if the
+    /// connection attempt ends in any other way or takes longer than `CONNECT_AND_WAIT_TIMEOUT`.
+    /// `current_state` is the tunnel state after connecting was requested.
+    ///
+    /// Since `events` is subscribed to before connecting, it can start with states that are left
+    /// over from before. A disconnect only counts once the attempt is seen to have started, and
+    /// so does an error if the daemon was already in the error state.
+    fn wait_until_connected(
+        events: sync::mpsc::Receiver<DaemonEvent>,
+        current_state: TunnelState,
+        timer: &tokio_timer::Timer,
+    ) -> impl Future<Item = (), Error = Error> {
+        let mut attempt_started = match current_state {
+            TunnelState::Connecting { .. } => true,
+            _ => false,
+        };
+        let was_in_error_state = current_state.is_in_error_state();
+        let outcome = events
+            .filter_map(move |event| match event {
+                DaemonEvent::TunnelState(TunnelState::Connected { .. }) => Some(Ok(())),
+                DaemonEvent::TunnelState(TunnelState::Connecting { .. }) => {
+                    attempt_started = true;
+                    None
+                }
+                DaemonEvent::TunnelState(TunnelState::Error(error_state))
+                    if attempt_started || !was_in_error_state =>
+                {
+                    Some(Err(Error {
+                        code: ErrorCode::ServerError(CONNECTION_FAILED_CODE),
+                        message: error_state.cause().to_string(),
+                        data: serde_json::to_value(error_state.cause()).ok(),
+                    }))
+                }
+                DaemonEvent::TunnelState(TunnelState::Disconnected { .. }) if attempt_started => {
+                    Some(Err(Error {
+                        code: ErrorCode::ServerError(CONNECTION_FAILED_CODE),
+                        message: "The tunnel was disconnected before it connected".to_owned(),
+                        data: None,
+                    }))
+                }
+                _ => None,
+            })
+            .into_future()
+            .map_err(|_| Error::internal_error())
+            .and_then(|(outcome, _)| outcome.unwrap_or_else(|| Err(Error::internal_error())));
+        let timeout = timer.sleep(CONNECT_AND_WAIT_TIMEOUT).then(|_| {
+            Err::<(), Error>(Error {
+                code: ErrorCode::ServerError(CONNECTION_FAILED_CODE),
+                message: "Timed out waiting for the tunnel to connect".to_owned(),
+                data: None,
+            })
+        });
+        outcome
+            .select(timeout)
+            .map(|(result, _)| result)
+            .map_err(|(error, _)| error)
+    }
+
+    /// Converts a failure to reach the daemon into an RPC error. A closed command channel means
+    /// the daemon is shutting down, which clients shouldn't retry. A full one means that the
+    /// daemon is busy, and that the command can be retried later.
+    fn map_daemon_error(error: crate::Error) -> Error {
+        match error {
+            crate::Error::DaemonUnavailable => Error {
+                code: ErrorCode::ServerError(DAEMON_SHUTTING_DOWN_CODE),
+                message: "The daemon is shutting down".to_owned(),
+                data: None,
+            },
+            crate::Error::DaemonBusy => Error {
+                code: ErrorCode::ServerError(DAEMON_BUSY_CODE),
+                message: "The daemon is busy, try again later".to_owned(),
+                data: None,
+            },
+            _ => Error::internal_error(),
+        }
+    }
+
+    /// Rejects custom relays that could never be connected to.
+    fn validate_custom_relay(endpoint: &CustomTunnelEndpoint) -> Result<(), Error> {
+        endpoint
+            .validate()
+            .map_err(|reason| invalid_params_with("relay_settings", reason))
+    }
+
+    /// Validates every field set in `patch` like its individual setter would.
+    fn validate_settings_patch(patch: &mut SettingsPatch) -> Result<(), Error> {
+        if let Some(RelaySettingsUpdate::CustomTunnelEndpoint(endpoint)) = &patch.relay_settings {
+            Self::validate_custom_relay(endpoint)?;
+        }
+        if let Some(bridge_settings) = &patch.bridge_settings {
+            validate_bridge_settings(bridge_settings)?;
+        }
+        if let Some(Some(mtu)) = patch.wireguard_mtu {
+            validate_mtu(mtu)?;
+        }
+        if let Some(Some(mssfix)) = patch.openvpn_mssfix {
+            validate_mssfix(mssfix)?;
+        }
+        if let Some(Some(servers)) = &mut patch.custom_dns {
+            *servers = validate_dns_servers(std::mem::replace(servers, Vec::new()))?;
+        }
+        Ok(())
+    }
+
+    /// Converts a rejected custom relay change into a JSONRPC error for the JSONRPC client.
+    fn map_custom_relay_error(result: Result<(), CustomRelayError>) -> Result<(), Error> {
+        result.map_err(|error| invalid_params_with("relay_settings", error.to_string()))
+    }
+
+    /// Converts a REST API error for an account into a JSONRPC error for the JSONRPC client.
+    fn map_rest_account_error(error: RestError) -> Error {
+        match error {
+            RestError::ApiError(status, message)
+                if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN =>
+            {
+                Error {
+                    code: ErrorCode::from(INVALID_ACCOUNT_CODE),
+                    message,
+                    data: None,
+                }
+            }
+            error => Self::map_rest_error(error),
+        }
+    }
+
+    /// Converts a failed WireGuard key operation into a JSONRPC error for the JSONRPC client.
+    fn map_wireguard_key_error(error: WireguardKeyError) -> Error {
+        match error {
+            WireguardKeyError::NoKey => Error {
+                code: ErrorCode::from(NO_WIREGUARD_KEY_CODE),
+                message: error.to_string(),
+                data: None,
+            },
+            WireguardKeyError::RestError(error) => Self::map_rest_error(error),
+            _ => Error::internal_error(),
+        }
+    }
+
+    /// Converts a REST error into an RPC error, telling connectivity problems apart from other
+    /// failures.
+    fn map_rest_error(error: RestError) -> Error {
+        match error {
+            RestError::HyperError(_) | RestError::TimeoutError(_) => Error {
+                code: ErrorCode::from(API_UNREACHABLE_CODE),
+                message: "Unable to reach the Mullvad API".to_owned(),
+                data: None,
+            },
+            _ => Error::internal_error(),
+        }
+    }
+}
+
+impl ManagementInterfaceApi for ManagementInterface {
+    type Metadata = Meta;
+
+    fn create_new_account(&self, _: Self::Metadata) -> BoxFuture<String, Error> {
+        log::debug!("create_new_account");
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::CreateNewAccount(tx))
+            .and_then(|_| recv_or_error(rx))
+            .and_then(|result| match result {
+                Ok(account_token) => Ok(account_token),
+                Err(error) => {
+                    log::error!("Unable to create a new account: {}", error.display_chain());
+                    Err(Self::map_rest_error(error))
+                }
+            });
+
+        self.traced("create_new_account", future)
+    }
+
+    fn get_account_data(
+        &self,
+        _: Self::Metadata,
+        account_token: AccountToken,
+    ) -> BoxFuture<AccountData, Error> {
+        log::debug!("get_account_data");
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::GetAccountData(tx, account_token))
+            .and_then(|_| recv_or_error(rx))
+            .and_then(|rpc_future| {
+                rpc_future.map_err(|error: RestError| {
+                    log::error!(
+                        "Unable to get account data from API: {}",
+                        error.display_chain()
+                    );
+                    Self::map_rest_account_error(error)
+                })
+            });
+        self.traced("get_account_data", future)
+    }
+
+    fn get_account_expiry(&self, _: Self::Metadata) -> BoxFuture<Option<AccountExpiry>, Error> {
+        log::debug!("get_account_expiry");
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::GetAccountExpiry(tx))
+            .and_then(|_| recv_or_error(rx))
+            .and_then(|rpc_future| {
+                rpc_future.map_err(|error: RestError| {
                     log::error!(
                         "Unable to get account data from API: {}",
                         error.display_chain()
@@ -383,25 +1476,26 @@ impl ManagementInterfaceApi for ManagementInterface {
                     Self::map_rest_account_error(error)
                 })
             });
-        Box::new(future)
+        self.traced("get_account_expiry", future)
     }
 
     fn get_www_auth_token(&self, _: Self::Metadata) -> BoxFuture<String, Error> {
-        log::debug!("get_account_data");
+        log::debug!("get_www_auth_token");
         let (tx, rx) = sync::oneshot::channel();
         let future = self
             .send_command_to_daemon(DaemonCommand::GetWwwAuthToken(tx))
-            .and_then(|_| rx.map_err(|_| Error::internal_error()))
+            .and_then(|_| recv_or_error(rx))
+            .and_then(|rpc_future| rpc_future.ok_or_else(no_account_token_error))
             .and_then(|rpc_future| {
                 rpc_future.map_err(|error: mullvad_rpc::rest::Error| {
                     log::error!(
-                        "Unable to get account data from API: {}",
+                        "Unable to get www auth token from API: {}",
                         error.display_chain()
                     );
                     Self::map_rest_account_error(error)
                 })
             });
-        Box::new(future)
+        self.traced("get_www_auth_token", future)
     }
 
     fn submit_voucher(
@@ -413,7 +1507,7 @@ impl ManagementInterfaceApi for ManagementInterface {
         let (tx, rx) = sync::oneshot::channel();
         let future = self
             .send_command_to_daemon(DaemonCommand::SubmitVoucher(tx, voucher))
-            .and_then(|_| rx.map_err(|_| Error::internal_error()))
+            .and_then(|_| recv_or_error(rx))
             .and_then(|f| {
                 f.map_err(|e| match e {
                     RestError::ApiError(StatusCode::BAD_REQUEST, message) => {
@@ -436,7 +1530,7 @@ impl ManagementInterfaceApi for ManagementInterface {
                     _ => Error::internal_error(),
                 })
             });
-        Box::new(future)
+        self.traced("submit_voucher", future)
     }
 
     fn get_relay_locations(&self, _: Self::Metadata) -> BoxFuture<RelayList, Error> {
@@ -444,13 +1538,77 @@ impl ManagementInterfaceApi for ManagementInterface {
         let (tx, rx) = sync::oneshot::channel();
         let future = self
             .send_command_to_daemon(DaemonCommand::GetRelayLocations(tx))
-            .and_then(|_| rx.map_err(|_| Error::internal_error()));
-        Box::new(future)
+            .and_then(|_| recv_or_error(rx));
+        self.traced("get_relay_locations", future)
+    }
+
+    fn get_relay_countries(
+        &self,
+        _: Self::Metadata,
+    ) -> BoxFuture<BTreeMap<CountryCode, String>, Error> {
+        log::debug!("get_relay_countries");
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::GetRelayCountries(tx))
+            .and_then(|_| recv_or_error(rx));
+        self.traced("get_relay_countries", future)
+    }
+
+    fn get_relay_location_counts(
+        &self,
+        _: Self::Metadata,
+    ) -> BoxFuture<BTreeMap<CountryCode, CountryRelayCount>, Error> {
+        log::debug!("get_relay_location_counts");
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::GetRelayLocationCounts(tx))
+            .and_then(|_| recv_or_error(rx));
+        self.traced("get_relay_location_counts", future)
+    }
+
+    fn measure_relay_latency(
+        &self,
+        _: Self::Metadata,
+        location: Constraint<LocationConstraint>,
+    ) -> BoxFuture<Vec<RelayLatency>, Error> {
+        log::debug!("measure_relay_latency({:?})", location);
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::MeasureRelayLatency(tx, location))
+            .and_then(|_| recv_or_error(rx));
+        self.traced("measure_relay_latency", future)
     }
 
     fn update_relay_locations(&self, _: Self::Metadata) -> BoxFuture<(), Error> {
         log::debug!("update_relay_locations");
-        Box::new(self.send_command_to_daemon(DaemonCommand::UpdateRelayLocations))
+        let future = self.send_command_to_daemon(DaemonCommand::UpdateRelayLocations);
+        self.traced("update_relay_locations", future)
+    }
+
+    fn update_relay_list(&self, _: Self::Metadata) -> BoxFuture<(), Error> {
+        log::debug!("update_relay_list");
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::UpdateRelayList(tx))
+            .and_then(|_| recv_or_error(rx))
+            .and_then(|result| match result {
+                Ok(()) => Ok(()),
+                Err(()) => Err(Error {
+                    code: ErrorCode::InternalError,
+                    message: "Failed to update the relay list".to_owned(),
+                    data: None,
+                }),
+            });
+        self.traced("update_relay_list", future)
+    }
+
+    fn logout(&self, _: Self::Metadata) -> BoxFuture<(), Error> {
+        log::debug!("logout");
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::Logout(tx))
+            .and_then(|_| recv_or_error(rx));
+        self.traced("logout", future)
     }
 
     fn set_account(
@@ -459,11 +1617,23 @@ impl ManagementInterfaceApi for ManagementInterface {
         account_token: Option<AccountToken>,
     ) -> BoxFuture<(), Error> {
         log::debug!("set_account");
+        let account_token = match account_token
+            .map(|token| account::normalize_account_token(&token))
+            .transpose()
+        {
+            Ok(account_token) => account_token,
+            Err(error) => {
+                return Box::new(future::err(invalid_params_with(
+                    "account_token",
+                    error.to_string(),
+                )));
+            }
+        };
         let (tx, rx) = sync::oneshot::channel();
         let future = self
             .send_command_to_daemon(DaemonCommand::SetAccount(tx, account_token))
-            .and_then(|_| rx.map_err(|_| Error::internal_error()));
-        Box::new(future)
+            .and_then(|_| recv_or_error(rx));
+        self.traced("set_account", future)
     }
 
     fn update_relay_settings(
@@ -472,13 +1642,106 @@ impl ManagementInterfaceApi for ManagementInterface {
         constraints_update: RelaySettingsUpdate,
     ) -> BoxFuture<(), Error> {
         log::debug!("update_relay_settings");
+        if let RelaySettingsUpdate::CustomTunnelEndpoint(endpoint) = &constraints_update {
+            if let Err(error) = Self::validate_custom_relay(endpoint) {
+                return Box::new(future::err(error));
+            }
+        }
         let (tx, rx) = sync::oneshot::channel();
 
         let message = DaemonCommand::UpdateRelaySettings(tx, constraints_update);
         let future = self
             .send_command_to_daemon(message)
-            .and_then(|_| rx.map_err(|_| Error::internal_error()));
-        Box::new(future)
+            .and_then(|_| recv_or_error(rx));
+        self.traced("update_relay_settings", future)
+    }
+
+    fn apply_settings(&self, _: Self::Metadata, mut patch: SettingsPatch) -> BoxFuture<(), Error> {
+        log::debug!("apply_settings({:?})", patch);
+        if let Err(error) = Self::validate_settings_patch(&mut patch) {
+            return Box::new(future::err(error));
+        }
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::ApplySettings(tx, patch))
+            .and_then(|_| recv_or_error(rx));
+        self.traced("apply_settings", future)
+    }
+
+    fn add_custom_relay(
+        &self,
+        _: Self::Metadata,
+        name: String,
+        endpoint: CustomTunnelEndpoint,
+    ) -> BoxFuture<(), Error> {
+        log::debug!("add_custom_relay({})", name);
+        if let Err(error) = Self::validate_custom_relay(&endpoint) {
+            return Box::new(future::err(error));
+        }
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::AddCustomRelay(tx, name, endpoint))
+            .and_then(|_| recv_or_error(rx))
+            .and_then(Self::map_custom_relay_error);
+        self.traced("add_custom_relay", future)
+    }
+
+    fn remove_custom_relay(&self, _: Self::Metadata, name: String) -> BoxFuture<(), Error> {
+        log::debug!("remove_custom_relay({})", name);
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::RemoveCustomRelay(tx, name))
+            .and_then(|_| recv_or_error(rx))
+            .and_then(Self::map_custom_relay_error);
+        self.traced("remove_custom_relay", future)
+    }
+
+    fn list_custom_relays(
+        &self,
+        _: Self::Metadata,
+    ) -> BoxFuture<BTreeMap<String, CustomTunnelEndpoint>, Error> {
+        log::debug!("list_custom_relays");
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::GetCustomRelays(tx))
+            .and_then(|_| recv_or_error(rx));
+        self.traced("list_custom_relays", future)
+    }
+
+    fn set_active_custom_relay(&self, _: Self::Metadata, name: String) -> BoxFuture<(), Error> {
+        log::debug!("set_active_custom_relay({})", name);
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::SetActiveCustomRelay(tx, name))
+            .and_then(|_| recv_or_error(rx))
+            .and_then(Self::map_custom_relay_error);
+        self.traced("set_active_custom_relay", future)
+    }
+
+    fn set_location_constraint(
+        &self,
+        _: Self::Metadata,
+        location: Option<LocationConstraint>,
+    ) -> BoxFuture<(), Error> {
+        log::debug!("set_location_constraint({:?})", location);
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::SetLocationConstraint(tx, location))
+            .and_then(|_| recv_or_error(rx));
+        self.traced("set_location_constraint", future)
+    }
+
+    fn set_openvpn_constraints(
+        &self,
+        _: Self::Metadata,
+        constraints: OpenVpnConstraints,
+    ) -> BoxFuture<(), Error> {
+        log::debug!("set_openvpn_constraints({})", constraints);
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::SetOpenVpnConstraints(tx, constraints))
+            .and_then(|_| recv_or_error(rx));
+        self.traced("set_openvpn_constraints", future)
     }
 
     fn set_allow_lan(&self, _: Self::Metadata, allow_lan: bool) -> BoxFuture<(), Error> {
@@ -486,8 +1749,8 @@ impl ManagementInterfaceApi for ManagementInterface {
         let (tx, rx) = sync::oneshot::channel();
         let future = self
             .send_command_to_daemon(DaemonCommand::SetAllowLan(tx, allow_lan))
-            .and_then(|_| rx.map_err(|_| Error::internal_error()));
-        Box::new(future)
+            .and_then(|_| recv_or_error(rx));
+        self.traced("set_allow_lan", future)
     }
 
     fn set_show_beta_releases(&self, _: Self::Metadata, enabled: bool) -> BoxFuture<(), Error> {
@@ -495,8 +1758,8 @@ impl ManagementInterfaceApi for ManagementInterface {
         let (tx, rx) = sync::oneshot::channel();
         let future = self
             .send_command_to_daemon(DaemonCommand::SetShowBetaReleases(tx, enabled))
-            .and_then(|_| rx.map_err(|_| Error::internal_error()));
-        Box::new(future)
+            .and_then(|_| recv_or_error(rx));
+        self.traced("set_show_beta_releases", future)
     }
 
     fn set_block_when_disconnected(
@@ -511,8 +1774,8 @@ impl ManagementInterfaceApi for ManagementInterface {
                 tx,
                 block_when_disconnected,
             ))
-            .and_then(|_| rx.map_err(|_| Error::internal_error()));
-        Box::new(future)
+            .and_then(|_| recv_or_error(rx));
+        self.traced("set_block_when_disconnected", future)
     }
 
     fn set_auto_connect(&self, _: Self::Metadata, auto_connect: bool) -> BoxFuture<(), Error> {
@@ -520,90 +1783,336 @@ impl ManagementInterfaceApi for ManagementInterface {
         let (tx, rx) = sync::oneshot::channel();
         let future = self
             .send_command_to_daemon(DaemonCommand::SetAutoConnect(tx, auto_connect))
-            .and_then(|_| rx.map_err(|_| Error::internal_error()));
-        Box::new(future)
+            .and_then(|_| recv_or_error(rx));
+        self.traced("set_auto_connect", future)
     }
 
-    fn connect(&self, _: Self::Metadata) -> BoxFuture<(), Error> {
-        log::debug!("connect");
+    fn get_auto_connect(&self, _: Self::Metadata) -> BoxFuture<bool, Error> {
+        log::debug!("get_auto_connect");
         let (tx, rx) = sync::oneshot::channel();
         let future = self
-            .send_command_to_daemon(DaemonCommand::SetTargetState(tx, TargetState::Secured))
-            .and_then(|_| rx.map_err(|_| Error::internal_error()))
-            .and_then(|result| match result {
-                Ok(()) => future::ok(()),
-                Err(()) => future::err(Error {
-                    code: ErrorCode::ServerError(-900),
-                    message: "No account token configured".to_owned(),
-                    data: None,
-                }),
-            });
-        Box::new(future)
+            .send_command_to_daemon(DaemonCommand::GetAutoConnect(tx))
+            .and_then(|_| recv_or_error(rx));
+        self.traced("get_auto_connect", future)
     }
 
-    fn disconnect(&self, _: Self::Metadata) -> BoxFuture<(), Error> {
-        log::debug!("disconnect");
-        let (tx, _) = sync::oneshot::channel();
+    fn set_connect_timeout(&self, _: Self::Metadata, connect_timeout: u32) -> BoxFuture<(), Error> {
+        log::debug!("set_connect_timeout({})", connect_timeout);
+        let (tx, rx) = sync::oneshot::channel();
         let future = self
-            .send_command_to_daemon(DaemonCommand::SetTargetState(tx, TargetState::Unsecured))
-            .then(|_| future::ok(()));
-        Box::new(future)
-    }
-
-    fn reconnect(&self, _: Self::Metadata) -> BoxFuture<(), Error> {
-        log::debug!("reconnect");
-        let future = self.send_command_to_daemon(DaemonCommand::Reconnect);
-        Box::new(future)
+            .send_command_to_daemon(DaemonCommand::SetConnectTimeout(tx, connect_timeout))
+            .and_then(|_| recv_or_error(rx));
+        self.traced("set_connect_timeout", future)
     }
 
-    fn get_state(&self, _: Self::Metadata) -> BoxFuture<TunnelState, Error> {
-        log::debug!("get_state");
-        let (state_tx, state_rx) = sync::oneshot::channel();
+    fn get_connect_timeout(&self, _: Self::Metadata) -> BoxFuture<u32, Error> {
+        log::debug!("get_connect_timeout");
+        let (tx, rx) = sync::oneshot::channel();
         let future = self
-            .send_command_to_daemon(DaemonCommand::GetState(state_tx))
-            .and_then(|_| state_rx.map_err(|_| Error::internal_error()));
-        Box::new(future)
+            .send_command_to_daemon(DaemonCommand::GetConnectTimeout(tx))
+            .and_then(|_| recv_or_error(rx));
+        self.traced("get_connect_timeout", future)
     }
 
-    fn get_current_location(&self, _: Self::Metadata) -> BoxFuture<Option<GeoIpLocation>, Error> {
-        log::debug!("get_current_location");
+    fn connect(&self, _: Self::Metadata) -> BoxFuture<(), Error> {
+        log::debug!("connect");
         let (tx, rx) = sync::oneshot::channel();
         let future = self
-            .send_command_to_daemon(DaemonCommand::GetCurrentLocation(tx))
-            .and_then(|_| rx.map_err(|_| Error::internal_error()));
-        Box::new(future)
+            .send_command_to_daemon(DaemonCommand::SetTargetState(tx, TargetState::Secured))
+            .and_then(|_| recv_or_error(rx))
+            .and_then(|result| result.map_err(|()| no_account_token_error()));
+        self.traced("connect", future)
     }
 
-    fn shutdown(&self, _: Self::Metadata) -> BoxFuture<(), Error> {
-        log::debug!("shutdown");
-        Box::new(self.send_command_to_daemon(DaemonCommand::Shutdown))
-    }
+    fn connect_and_wait(&self, _: Self::Metadata) -> BoxFuture<(), Error> {
+        log::debug!("connect_and_wait");
+        // Listen for tunnel states before connecting, so that none can be missed.
+        let id = SubscriptionId::Number(self.next_subscription_id.fetch_add(1, Ordering::Relaxed));
+        let (events_tx, events_rx) = sync::mpsc::channel(SUBSCRIBER_BUFFER_SIZE);
+        self.subscriptions
+            .daemon_event
+            .write()
+            .insert(id.clone(), events_tx);
 
-    fn prepare_restart(&self, _: Self::Metadata) -> BoxFuture<(), Error> {
-        log::debug!("prepare_restart");
-        Box::new(self.send_command_to_daemon(DaemonCommand::PrepareRestart))
+        let (connect_tx, connect_rx) = sync::oneshot::channel();
+        let (state_tx, state_rx) = sync::oneshot::channel();
+        let connect = self
+            .send_command_to_daemon(DaemonCommand::SetTargetState(
+                connect_tx,
+                TargetState::Secured,
+            ))
+            .and_then(|_| recv_or_error(connect_rx))
+            .and_then(|result| result.map_err(|()| no_account_token_error()));
+        // The daemon handles commands in order, so this is the state after connecting was
+        // requested.
+        let current_state = self
+            .send_command_to_daemon(DaemonCommand::GetState(state_tx))
+            .and_then(|_| recv_or_error(state_rx));
+
+        let subscriptions = self.subscriptions.clone();
+        let timer = self.timer.clone();
+        let future = connect
+            .and_then(|_| current_state)
+            .and_then(move |current_state| match current_state {
+                TunnelState::Connected { .. } => future::Either::A(future::ok(())),
+                current_state => {
+                    future::Either::B(Self::wait_until_connected(events_rx, current_state, &timer))
+                }
+            })
+            .then(move |result| {
+                subscriptions.daemon_event.write().remove(&id);
+                result
+            });
+        self.traced("connect_and_wait", future)
     }
 
-    fn get_account_history(&self, _: Self::Metadata) -> BoxFuture<Vec<AccountToken>, Error> {
-        log::debug!("get_account_history");
+    fn disconnect(&self, _: Self::Metadata) -> BoxFuture<(), Error> {
+        log::debug!("disconnect");
         let (tx, rx) = sync::oneshot::channel();
         let future = self
-            .send_command_to_daemon(DaemonCommand::GetAccountHistory(tx))
-            .and_then(|_| rx.map_err(|_| Error::internal_error()));
-        Box::new(future)
+            .send_command_to_daemon(DaemonCommand::SetTargetState(tx, TargetState::Unsecured))
+            .and_then(|_| recv_or_error(rx))
+            .and_then(|result| result.map_err(|()| no_account_token_error()));
+        self.traced("disconnect", future)
     }
 
-    fn remove_account_from_history(
+    fn set_target_state(
         &self,
         _: Self::Metadata,
-        account_token: AccountToken,
+        target_state: TargetState,
     ) -> BoxFuture<(), Error> {
-        log::debug!("remove_account_from_history");
+        log::debug!("set_target_state({:?})", target_state);
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::SetTargetState(tx, target_state))
+            .and_then(|_| recv_or_error(rx))
+            .and_then(|result| result.map_err(|()| no_account_token_error()));
+        self.traced("set_target_state", future)
+    }
+
+    fn reconnect(&self, _: Self::Metadata) -> BoxFuture<(), Error> {
+        log::debug!("reconnect");
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::Reconnect(tx))
+            .and_then(|_| recv_or_error(rx))
+            .and_then(|result| result.map_err(|()| no_account_token_error()));
+        self.traced("reconnect", future)
+    }
+
+    fn connect_fastest(
+        &self,
+        _: Self::Metadata,
+        location: Constraint<LocationConstraint>,
+    ) -> BoxFuture<RelayLatency, Error> {
+        log::debug!("connect_fastest({:?})", location);
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::ConnectFastest(tx, location.clone()))
+            .and_then(|_| recv_or_error(rx))
+            .and_then(move |result| match result {
+                Some(relay) => future::ok(relay),
+                None => future::err(Error {
+                    code: ErrorCode::ServerError(NO_RESPONSIVE_RELAY_CODE),
+                    message: match location {
+                        Constraint::Only(location) => {
+                            format!("None of the relays in {} responded", location)
+                        }
+                        Constraint::Any => "None of the relays responded".to_owned(),
+                    },
+                    data: None,
+                }),
+            });
+        self.traced("connect_fastest", future)
+    }
+
+    fn connect_to(
+        &self,
+        _: Self::Metadata,
+        location: Constraint<LocationConstraint>,
+    ) -> BoxFuture<(), Error> {
+        log::debug!("connect_to({:?})", location);
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::ConnectTo(tx, location.clone()))
+            .and_then(|_| recv_or_error(rx))
+            .and_then(move |result| match result {
+                Ok(()) => future::ok(()),
+                Err(()) => future::err(invalid_params_with(
+                    "location",
+                    match location {
+                        Constraint::Only(location) => format!("No active relays in {}", location),
+                        Constraint::Any => "No active relays available".to_owned(),
+                    },
+                )),
+            });
+        self.traced("connect_to", future)
+    }
+
+    fn connect_once(&self, _: Self::Metadata, relay: CustomTunnelEndpoint) -> BoxFuture<(), Error> {
+        log::debug!("connect_once({})", relay.endpoint());
+        if let Err(error) = Self::validate_custom_relay(&relay) {
+            return Box::new(future::err(error));
+        }
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::ConnectOnce(tx, relay))
+            .and_then(|_| recv_or_error(rx))
+            .and_then(|result| result.map_err(|()| no_account_token_error()));
+        self.traced("connect_once", future)
+    }
+
+    fn get_state(&self, _: Self::Metadata) -> BoxFuture<TunnelState, Error> {
+        log::debug!("get_state");
+        let (state_tx, state_rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::GetState(state_tx))
+            .and_then(|_| recv_or_error(state_rx));
+        self.traced("get_state", future)
+    }
+
+    fn get_firewall_state(&self, _: Self::Metadata) -> BoxFuture<FirewallState, Error> {
+        log::debug!("get_firewall_state");
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::GetFirewallState(tx))
+            .and_then(|_| recv_or_error(rx));
+        self.traced("get_firewall_state", future)
+    }
+
+    fn get_current_relay(&self, _: Self::Metadata) -> BoxFuture<Option<TunnelEndpoint>, Error> {
+        log::debug!("get_current_relay");
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::GetCurrentRelay(tx))
+            .and_then(|_| recv_or_error(rx));
+        self.traced("get_current_relay", future)
+    }
+
+    fn preview_relay_selection(
+        &self,
+        _: Self::Metadata,
+    ) -> BoxFuture<Option<RelayEndpoint>, Error> {
+        log::debug!("preview_relay_selection");
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::PreviewRelaySelection(tx))
+            .and_then(|_| recv_or_error(rx));
+        self.traced("preview_relay_selection", future)
+    }
+
+    fn get_connectivity(&self, _: Self::Metadata) -> BoxFuture<Connectivity, Error> {
+        log::debug!("get_connectivity");
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::GetConnectivity(tx))
+            .and_then(|_| recv_or_error(rx));
+        self.traced("get_connectivity", future)
+    }
+
+    fn get_reconnect_status(&self, _: Self::Metadata) -> BoxFuture<ReconnectStatus, Error> {
+        log::debug!("get_reconnect_status");
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::GetReconnectStatus(tx))
+            .and_then(|_| recv_or_error(rx));
+        self.traced("get_reconnect_status", future)
+    }
+
+    fn get_state_history(
+        &self,
+        _: Self::Metadata,
+        max_entries: u32,
+    ) -> BoxFuture<Vec<StateHistoryEntry>, Error> {
+        log::debug!("get_state_history({})", max_entries);
+        let max_entries = (max_entries as usize).min(STATE_HISTORY_CAPACITY);
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::GetStateHistory(tx, max_entries))
+            .and_then(|_| recv_or_error(rx));
+        self.traced("get_state_history", future)
+    }
+
+    fn get_status_snapshot(&self, _: Self::Metadata) -> BoxFuture<StatusSnapshot, Error> {
+        log::debug!("get_status_snapshot");
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::GetStatusSnapshot(tx))
+            .and_then(|_| recv_or_error(rx));
+        self.traced("get_status_snapshot", future)
+    }
+
+    fn get_current_location(&self, _: Self::Metadata) -> BoxFuture<Option<GeoIpLocation>, Error> {
+        log::debug!("get_current_location");
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::GetCurrentLocation(tx))
+            .and_then(|_| recv_or_error(rx));
+        self.traced("get_current_location", future)
+    }
+
+    fn get_public_ip(&self, _: Self::Metadata) -> BoxFuture<Option<PublicIp>, Error> {
+        log::debug!("get_public_ip");
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::GetPublicIp(tx))
+            .and_then(|_| recv_or_error(rx));
+        self.traced("get_public_ip", future)
+    }
+
+    fn get_tunnel_stats(&self, _: Self::Metadata) -> BoxFuture<Option<TunnelStats>, Error> {
+        log::debug!("get_tunnel_stats");
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::GetTunnelStats(tx))
+            .and_then(|_| recv_or_error(rx));
+        self.traced("get_tunnel_stats", future)
+    }
+
+    fn get_last_error(&self, _: Self::Metadata) -> BoxFuture<Option<ErrorState>, Error> {
+        log::debug!("get_last_error");
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::GetLastError(tx))
+            .and_then(|_| recv_or_error(rx));
+        self.traced("get_last_error", future)
+    }
+
+    fn shutdown(&self, _: Self::Metadata) -> BoxFuture<(), Error> {
+        log::debug!("shutdown");
+        let future = self.send_command_to_daemon(DaemonCommand::Shutdown);
+        self.traced("shutdown", future)
+    }
+
+    fn prepare_restart(&self, _: Self::Metadata) -> BoxFuture<(), Error> {
+        log::debug!("prepare_restart");
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::PrepareRestart(tx))
+            .and_then(|_| recv_or_error(rx));
+        self.traced("prepare_restart", future)
+    }
+
+    fn get_account_history(&self, _: Self::Metadata) -> BoxFuture<Vec<AccountToken>, Error> {
+        log::debug!("get_account_history");
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::GetAccountHistory(tx))
+            .and_then(|_| recv_or_error(rx));
+        self.traced("get_account_history", future)
+    }
+
+    fn remove_account_from_history(
+        &self,
+        _: Self::Metadata,
+        account_token: AccountToken,
+    ) -> BoxFuture<(), Error> {
+        log::debug!("remove_account_from_history");
         let (tx, rx) = sync::oneshot::channel();
         let future = self
             .send_command_to_daemon(DaemonCommand::RemoveAccountFromHistory(tx, account_token))
-            .and_then(|_| rx.map_err(|_| Error::internal_error()));
-        Box::new(future)
+            .and_then(|_| recv_or_error(rx));
+        self.traced("remove_account_from_history", future)
     }
 
     fn clear_account_history(&self, _: Self::Metadata) -> BoxFuture<(), Error> {
@@ -611,18 +2120,59 @@ impl ManagementInterfaceApi for ManagementInterface {
         let (tx, rx) = sync::oneshot::channel();
         let future = self
             .send_command_to_daemon(DaemonCommand::ClearAccountHistory(tx))
-            .and_then(|_| rx.map_err(|_| Error::internal_error()));
-        Box::new(future)
+            .and_then(|_| recv_or_error(rx));
+        self.traced("clear_account_history", future)
     }
 
     fn set_openvpn_mssfix(&self, _: Self::Metadata, mssfix: Option<u16>) -> BoxFuture<(), Error> {
         log::debug!("set_openvpn_mssfix({:?})", mssfix);
+        if let Some(mssfix) = mssfix {
+            if let Err(error) = validate_mssfix(mssfix) {
+                return Box::new(future::err(error));
+            }
+        }
         let (tx, rx) = sync::oneshot::channel();
         let future = self
             .send_command_to_daemon(DaemonCommand::SetOpenVpnMssfix(tx, mssfix))
-            .and_then(|_| rx.map_err(|_| Error::internal_error()));
+            .and_then(|_| recv_or_error(rx));
+
+        self.traced("set_openvpn_mssfix", future)
+    }
+
+    fn get_openvpn_mssfix(&self, _: Self::Metadata) -> BoxFuture<Option<u16>, Error> {
+        log::debug!("get_openvpn_mssfix");
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::GetOpenVpnMssfix(tx))
+            .and_then(|_| recv_or_error(rx));
+        self.traced("get_openvpn_mssfix", future)
+    }
+
+    fn set_openvpn_cipher(
+        &self,
+        _: Self::Metadata,
+        cipher: Option<String>,
+    ) -> BoxFuture<(), Error> {
+        log::debug!("set_openvpn_cipher({:?})", cipher);
+        if let Some(ref cipher) = cipher {
+            if let Err(error) = validate_openvpn_cipher(cipher) {
+                return Box::new(future::err(error));
+            }
+        }
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::SetOpenVpnCipher(tx, cipher))
+            .and_then(|_| recv_or_error(rx));
+        self.traced("set_openvpn_cipher", future)
+    }
 
-        Box::new(future)
+    fn get_openvpn_cipher(&self, _: Self::Metadata) -> BoxFuture<Option<String>, Error> {
+        log::debug!("get_openvpn_cipher");
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::GetOpenVpnCipher(tx))
+            .and_then(|_| recv_or_error(rx));
+        self.traced("get_openvpn_cipher", future)
     }
 
     fn set_bridge_settings(
@@ -631,13 +2181,25 @@ impl ManagementInterfaceApi for ManagementInterface {
         bridge_settings: BridgeSettings,
     ) -> BoxFuture<(), Error> {
         log::debug!("set_bridge_settings({:?})", bridge_settings);
+        if let Err(error) = validate_bridge_settings(&bridge_settings) {
+            return Box::new(future::err(error));
+        }
         let (tx, rx) = sync::oneshot::channel();
         let future = self
             .send_command_to_daemon(DaemonCommand::SetBridgeSettings(tx, bridge_settings))
-            .and_then(|_| rx.map_err(|_| Error::internal_error()))
+            .and_then(|_| recv_or_error(rx))
             .and_then(|settings_result| settings_result.map_err(|_| Error::internal_error()));
 
-        Box::new(future)
+        self.traced("set_bridge_settings", future)
+    }
+
+    fn get_bridge_settings(&self, _: Self::Metadata) -> BoxFuture<BridgeSettings, Error> {
+        log::debug!("get_bridge_settings");
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::GetBridgeSettings(tx))
+            .and_then(|_| recv_or_error(rx));
+        self.traced("get_bridge_settings", future)
     }
 
     fn set_bridge_state(
@@ -649,10 +2211,93 @@ impl ManagementInterfaceApi for ManagementInterface {
         let (tx, rx) = sync::oneshot::channel();
         let future = self
             .send_command_to_daemon(DaemonCommand::SetBridgeState(tx, bridge_state))
-            .and_then(|_| rx.map_err(|_| Error::internal_error()))
+            .and_then(|_| recv_or_error(rx))
             .and_then(|settings_result| settings_result.map_err(|_| Error::internal_error()));
 
-        Box::new(future)
+        self.traced("set_bridge_state", future)
+    }
+
+    fn get_bridge_state(&self, _: Self::Metadata) -> BoxFuture<BridgeState, Error> {
+        log::debug!("get_bridge_state");
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::GetBridgeState(tx))
+            .and_then(|_| recv_or_error(rx));
+        self.traced("get_bridge_state", future)
+    }
+
+    fn set_relay_filter(
+        &self,
+        _: Self::Metadata,
+        relay_filter: RelayFilter,
+    ) -> BoxFuture<(), Error> {
+        log::debug!("set_relay_filter({:?})", relay_filter);
+        if let Err(error) = validate_relay_filter(&relay_filter) {
+            return Box::new(future::err(error));
+        }
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::SetRelayFilter(tx, relay_filter))
+            .and_then(|_| recv_or_error(rx));
+
+        self.traced("set_relay_filter", future)
+    }
+
+    fn get_relay_filter(&self, _: Self::Metadata) -> BoxFuture<RelayFilter, Error> {
+        log::debug!("get_relay_filter");
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::GetRelayFilter(tx))
+            .and_then(|_| recv_or_error(rx));
+        self.traced("get_relay_filter", future)
+    }
+
+    fn set_relay_selection_mode(
+        &self,
+        _: Self::Metadata,
+        mode: SelectionMode,
+    ) -> BoxFuture<(), Error> {
+        log::debug!("set_relay_selection_mode({:?})", mode);
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::SetRelaySelectionMode(tx, mode))
+            .and_then(|_| recv_or_error(rx));
+
+        self.traced("set_relay_selection_mode", future)
+    }
+
+    fn get_relay_selection_mode(&self, _: Self::Metadata) -> BoxFuture<SelectionMode, Error> {
+        log::debug!("get_relay_selection_mode");
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::GetRelaySelectionMode(tx))
+            .and_then(|_| recv_or_error(rx));
+        self.traced("get_relay_selection_mode", future)
+    }
+
+    fn set_probe_before_connect(
+        &self,
+        _: Self::Metadata,
+        probe_before_connect: bool,
+    ) -> BoxFuture<(), Error> {
+        log::debug!("set_probe_before_connect({})", probe_before_connect);
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::SetProbeBeforeConnect(
+                tx,
+                probe_before_connect,
+            ))
+            .and_then(|_| recv_or_error(rx));
+        self.traced("set_probe_before_connect", future)
+    }
+
+    fn get_probe_before_connect(&self, _: Self::Metadata) -> BoxFuture<bool, Error> {
+        log::debug!("get_probe_before_connect");
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::GetProbeBeforeConnect(tx))
+            .and_then(|_| recv_or_error(rx));
+        self.traced("get_probe_before_connect", future)
     }
 
     fn set_enable_ipv6(&self, _: Self::Metadata, enable_ipv6: bool) -> BoxFuture<(), Error> {
@@ -660,19 +2305,77 @@ impl ManagementInterfaceApi for ManagementInterface {
         let (tx, rx) = sync::oneshot::channel();
         let future = self
             .send_command_to_daemon(DaemonCommand::SetEnableIpv6(tx, enable_ipv6))
-            .and_then(|_| rx.map_err(|_| Error::internal_error()));
+            .and_then(|_| recv_or_error(rx));
+
+        self.traced("set_enable_ipv6", future)
+    }
+
+    fn get_enable_ipv6(&self, _: Self::Metadata) -> BoxFuture<bool, Error> {
+        log::debug!("get_enable_ipv6");
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::GetEnableIpv6(tx))
+            .and_then(|_| recv_or_error(rx));
+        self.traced("get_enable_ipv6", future)
+    }
+
+    fn set_dns(&self, _: Self::Metadata, servers: Option<Vec<IpAddr>>) -> BoxFuture<(), Error> {
+        log::debug!("set_dns({:?})", servers);
+        let servers = match servers.map(validate_dns_servers).transpose() {
+            Ok(servers) => servers,
+            Err(error) => return Box::new(future::err(error)),
+        };
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::SetCustomDns(tx, servers))
+            .and_then(|_| recv_or_error(rx));
+        self.traced("set_dns", future)
+    }
 
-        Box::new(future)
+    fn get_dns(&self, _: Self::Metadata) -> BoxFuture<Option<Vec<IpAddr>>, Error> {
+        log::debug!("get_dns");
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::GetCustomDns(tx))
+            .and_then(|_| recv_or_error(rx));
+        self.traced("get_dns", future)
+    }
+
+    fn set_dns_content_blockers(
+        &self,
+        _: Self::Metadata,
+        blockers: DnsBlockers,
+    ) -> BoxFuture<(), Error> {
+        log::debug!("set_dns_content_blockers({:?})", blockers);
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::SetDnsBlockers(tx, blockers))
+            .and_then(|_| recv_or_error(rx));
+        self.traced("set_dns_content_blockers", future)
+    }
+
+    fn get_dns_content_blockers(&self, _: Self::Metadata) -> BoxFuture<DnsBlockers, Error> {
+        log::debug!("get_dns_content_blockers");
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::GetDnsBlockers(tx))
+            .and_then(|_| recv_or_error(rx));
+        self.traced("get_dns_content_blockers", future)
     }
 
     /// Set MTU for wireguard tunnels
     fn set_wireguard_mtu(&self, _: Self::Metadata, mtu: Option<u16>) -> BoxFuture<(), Error> {
         log::debug!("set_wireguard_mtu({:?})", mtu);
+        if let Some(mtu) = mtu {
+            if let Err(error) = validate_mtu(mtu) {
+                return Box::new(future::err(error));
+            }
+        }
         let (tx, rx) = sync::oneshot::channel();
         let future = self
             .send_command_to_daemon(DaemonCommand::SetWireguardMtu(tx, mtu))
-            .and_then(|_| rx.map_err(|_| Error::internal_error()));
-        Box::new(future)
+            .and_then(|_| recv_or_error(rx));
+        self.traced("set_wireguard_mtu", future)
     }
 
     /// Set automatic key rotation interval for wireguard tunnels
@@ -685,8 +2388,17 @@ impl ManagementInterfaceApi for ManagementInterface {
         let (tx, rx) = sync::oneshot::channel();
         let future = self
             .send_command_to_daemon(DaemonCommand::SetWireguardRotationInterval(tx, interval))
-            .and_then(|_| rx.map_err(|_| Error::internal_error()));
-        Box::new(future)
+            .and_then(|_| recv_or_error(rx));
+        self.traced("set_wireguard_rotation_interval", future)
+    }
+
+    fn get_relay_settings(&self, _: Self::Metadata) -> BoxFuture<RelaySettings, Error> {
+        log::debug!("get_relay_settings");
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::GetRelaySettings(tx))
+            .and_then(|_| recv_or_error(rx));
+        self.traced("get_relay_settings", future)
     }
 
     fn get_settings(&self, _: Self::Metadata) -> BoxFuture<Settings, Error> {
@@ -694,8 +2406,8 @@ impl ManagementInterfaceApi for ManagementInterface {
         let (tx, rx) = sync::oneshot::channel();
         let future = self
             .send_command_to_daemon(DaemonCommand::GetSettings(tx))
-            .and_then(|_| rx.map_err(|_| Error::internal_error()));
-        Box::new(future)
+            .and_then(|_| recv_or_error(rx));
+        self.traced("get_settings", future)
     }
 
     fn generate_wireguard_key(
@@ -706,8 +2418,8 @@ impl ManagementInterfaceApi for ManagementInterface {
         let (tx, rx) = sync::oneshot::channel();
         let future = self
             .send_command_to_daemon(DaemonCommand::GenerateWireguardKey(tx))
-            .and_then(|_| rx.map_err(|_| Error::internal_error()));
-        Box::new(future)
+            .and_then(|_| recv_or_error(rx));
+        self.traced("generate_wireguard_key", future)
     }
 
     fn get_wireguard_key(
@@ -718,8 +2430,8 @@ impl ManagementInterfaceApi for ManagementInterface {
         let (tx, rx) = sync::oneshot::channel();
         let future = self
             .send_command_to_daemon(DaemonCommand::GetWireguardKey(tx))
-            .and_then(|_| rx.map_err(|_| Error::internal_error()));
-        Box::new(future)
+            .and_then(|_| recv_or_error(rx));
+        self.traced("get_wireguard_key", future)
     }
 
     fn verify_wireguard_key(&self, _: Self::Metadata) -> BoxFuture<bool, Error> {
@@ -727,8 +2439,9 @@ impl ManagementInterfaceApi for ManagementInterface {
         let (tx, rx) = sync::oneshot::channel();
         let future = self
             .send_command_to_daemon(DaemonCommand::VerifyWireguardKey(tx))
-            .and_then(|_| rx.map_err(|_| Error::internal_error()));
-        Box::new(future)
+            .and_then(|_| recv_or_error(rx))
+            .and_then(|result| result.map_err(Self::map_wireguard_key_error));
+        self.traced("verify_wireguard_key", future)
     }
 
     fn get_current_version(&self, _: Self::Metadata) -> BoxFuture<String, Error> {
@@ -736,9 +2449,43 @@ impl ManagementInterfaceApi for ManagementInterface {
         let (tx, rx) = sync::oneshot::channel();
         let future = self
             .send_command_to_daemon(DaemonCommand::GetCurrentVersion(tx))
-            .and_then(|_| rx.map_err(|_| Error::internal_error()));
+            .and_then(|_| recv_or_error(rx));
+
+        self.traced("get_current_version", future)
+    }
+
+    fn get_recent_logs(&self, _: Self::Metadata, max_lines: u32) -> BoxFuture<Vec<String>, Error> {
+        log::debug!("get_recent_logs({})", max_lines);
+        let max_lines = (max_lines as usize).min(logging::RECENT_LOG_CAPACITY);
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::GetRecentLogs(tx, max_lines))
+            .and_then(|_| recv_or_error(rx));
+        self.traced("get_recent_logs", future)
+    }
+
+    fn set_log_level(&self, _: Self::Metadata, level: LogLevel) -> BoxFuture<(), Error> {
+        log::debug!("set_log_level({:?})", level);
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::SetLogLevel(tx, level))
+            .and_then(|_| recv_or_error(rx));
+        self.traced("set_log_level", future)
+    }
+
+    fn get_log_level(&self, _: Self::Metadata) -> BoxFuture<Option<LogLevel>, Error> {
+        log::debug!("get_log_level");
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::GetLogLevel(tx))
+            .and_then(|_| recv_or_error(rx));
+        self.traced("get_log_level", future)
+    }
 
-        Box::new(future)
+    fn is_compatible(&self, _: Self::Metadata, client_version: String) -> BoxFuture<bool, Error> {
+        log::debug!("is_compatible");
+        let future = future::ok(version_check::is_compatible_client_version(&client_version));
+        self.traced("is_compatible", future)
     }
 
     fn get_version_info(&self, _: Self::Metadata) -> BoxFuture<version::AppVersionInfo, Error> {
@@ -746,9 +2493,67 @@ impl ManagementInterfaceApi for ManagementInterface {
         let (tx, rx) = sync::oneshot::channel();
         let future = self
             .send_command_to_daemon(DaemonCommand::GetVersionInfo(tx))
-            .and_then(|_| rx.map_err(|_| Error::internal_error()));
+            .and_then(|_| recv_or_error(rx));
+
+        self.traced("get_version_info", future)
+    }
+
+    fn reset_settings(&self, _: Self::Metadata) -> BoxFuture<(), Error> {
+        log::debug!("reset_settings");
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::ResetSettings(tx))
+            .and_then(|_| recv_or_error(rx));
+        self.traced("reset_settings", future)
+    }
+
+    fn reload_settings(&self, _: Self::Metadata) -> BoxFuture<(), Error> {
+        log::debug!("reload_settings");
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::ReloadSettings(tx))
+            .and_then(|_| recv_or_error(rx))
+            .and_then(|result| {
+                result.map_err(|error| error_with_causes(ErrorCode::InternalError, &error))
+            });
+        self.traced("reload_settings", future)
+    }
+
+    fn clear_cache(&self, _: Self::Metadata) -> BoxFuture<(), Error> {
+        log::debug!("clear_cache");
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::ClearCache(tx))
+            .and_then(|_| recv_or_error(rx))
+            .and_then(|result| result.map_err(|_| Error::internal_error()));
+        self.traced("clear_cache", future)
+    }
 
-        Box::new(future)
+    fn set_api_endpoint(
+        &self,
+        _: Self::Metadata,
+        endpoint: Option<SocketAddr>,
+    ) -> BoxFuture<(), Error> {
+        log::debug!("set_api_endpoint({:?})", endpoint);
+        if let Some(endpoint) = endpoint {
+            if let Err(error) = validate_api_endpoint(endpoint) {
+                return Box::new(future::err(error));
+            }
+        }
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::SetApiEndpoint(tx, endpoint))
+            .and_then(|_| recv_or_error(rx));
+        self.traced("set_api_endpoint", future)
+    }
+
+    fn get_api_endpoint(&self, _: Self::Metadata) -> BoxFuture<Option<SocketAddr>, Error> {
+        log::debug!("get_api_endpoint");
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::GetApiEndpoint(tx))
+            .and_then(|_| recv_or_error(rx));
+        self.traced("get_api_endpoint", future)
     }
 
     fn factory_reset(&self, _: Self::Metadata) -> BoxFuture<(), Error> {
@@ -758,9 +2563,8 @@ impl ManagementInterfaceApi for ManagementInterface {
             let (tx, rx) = sync::oneshot::channel();
             let future = self
                 .send_command_to_daemon(DaemonCommand::FactoryReset(tx))
-                .and_then(|_| rx.map_err(|_| Error::internal_error()));
-
-            Box::new(future)
+                .and_then(|_| recv_or_error(rx));
+            self.traced("factory_reset", future)
         }
         #[cfg(target_os = "android")]
         {
@@ -775,8 +2579,8 @@ impl ManagementInterfaceApi for ManagementInterface {
             let (tx, rx) = sync::oneshot::channel();
             let future = self
                 .send_command_to_daemon(DaemonCommand::GetSplitTunnelProcesses(tx))
-                .and_then(|_| rx.map_err(|_| Error::internal_error()));
-            Box::new(future)
+                .and_then(|_| recv_or_error(rx));
+            self.traced("get_split_tunnel_processes", future)
         }
         #[cfg(not(target_os = "linux"))]
         {
@@ -790,8 +2594,8 @@ impl ManagementInterfaceApi for ManagementInterface {
         let (tx, rx) = sync::oneshot::channel();
         let future = self
             .send_command_to_daemon(DaemonCommand::AddSplitTunnelProcess(tx, pid))
-            .and_then(|_| rx.map_err(|_| Error::internal_error()));
-        Box::new(future)
+            .and_then(|_| recv_or_error(rx));
+        self.traced("add_split_tunnel_process", future)
     }
     #[cfg(not(target_os = "linux"))]
     fn add_split_tunnel_process(&self, _: Self::Metadata, _: i32) -> BoxFuture<(), Error> {
@@ -804,8 +2608,8 @@ impl ManagementInterfaceApi for ManagementInterface {
         let (tx, rx) = sync::oneshot::channel();
         let future = self
             .send_command_to_daemon(DaemonCommand::RemoveSplitTunnelProcess(tx, pid))
-            .and_then(|_| rx.map_err(|_| Error::internal_error()));
-        Box::new(future)
+            .and_then(|_| recv_or_error(rx));
+        self.traced("remove_split_tunnel_process", future)
     }
     #[cfg(not(target_os = "linux"))]
     fn remove_split_tunnel_process(&self, _: Self::Metadata, _: i32) -> BoxFuture<(), Error> {
@@ -819,8 +2623,8 @@ impl ManagementInterfaceApi for ManagementInterface {
             let (tx, rx) = sync::oneshot::channel();
             let future = self
                 .send_command_to_daemon(DaemonCommand::ClearSplitTunnelProcesses(tx))
-                .and_then(|_| rx.map_err(|_| Error::internal_error()));
-            Box::new(future)
+                .and_then(|_| recv_or_error(rx));
+            self.traced("clear_split_tunnel_processes", future)
         }
         #[cfg(not(target_os = "linux"))]
         {
@@ -828,43 +2632,363 @@ impl ManagementInterfaceApi for ManagementInterface {
         }
     }
 
+    fn list_split_tunnel_apps(&self, _: Self::Metadata) -> BoxFuture<Vec<PathBuf>, Error> {
+        log::debug!("list_split_tunnel_apps");
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::GetSplitTunnelApps(tx))
+            .and_then(|_| recv_or_error(rx));
+        self.traced("list_split_tunnel_apps", future)
+    }
 
-    fn daemon_event_subscribe(
+    fn add_split_tunnel_app(&self, _: Self::Metadata, path: PathBuf) -> BoxFuture<(), Error> {
+        log::debug!("add_split_tunnel_app({})", path.display());
+        if let Err(error) = validate_split_tunnel_app(&path) {
+            return Box::new(future::err(error));
+        }
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::AddSplitTunnelApp(tx, path))
+            .and_then(|_| recv_or_error(rx));
+        self.traced("add_split_tunnel_app", future)
+    }
+
+    fn remove_split_tunnel_app(&self, _: Self::Metadata, path: PathBuf) -> BoxFuture<(), Error> {
+        log::debug!("remove_split_tunnel_app({})", path.display());
+        let (tx, rx) = sync::oneshot::channel();
+        let future = self
+            .send_command_to_daemon(DaemonCommand::RemoveSplitTunnelApp(tx, path))
+            .and_then(|_| recv_or_error(rx));
+        self.traced("remove_split_tunnel_app", future)
+    }
+
+
+    fn daemon_event_subscribe(
         &self,
-        _: Self::Metadata,
+        meta: Self::Metadata,
         subscriber: pubsub::Subscriber<DaemonEvent>,
+        args: Trailing<DaemonEventSubscribeArgs>,
     ) {
-        log::debug!("daemon_event_subscribe");
-        let mut subscriptions = self.subscriptions.write();
-        loop {
-            let id = SubscriptionId::String(uuid::Uuid::new_v4().to_string());
-            if let Entry::Vacant(entry) = subscriptions.entry(id.clone()) {
-                if let Ok(sink) = subscriber.assign_id(id.clone()) {
-                    log::debug!("Accepting new subscription with id {:?}", id);
-                    entry.insert(sink);
-                }
-                break;
+        let args: Option<DaemonEventSubscribeArgs> = args.into();
+        let options = args
+            .map(DaemonEventSubscribeOptions::from)
+            .unwrap_or_default();
+        log::debug!("daemon_event_subscribe({:?})", options);
+        let min_error_severity = options.min_error_severity;
+        let filter = move |event: &DaemonEvent| match (event, min_error_severity) {
+            (DaemonEvent::TunnelState(TunnelState::Error(error_state)), Some(min_severity)) => {
+                error_state.severity() >= min_severity
             }
+            _ => true,
+        };
+        if options.send_initial_state {
+            self.subscribe_filtered_with_initial(
+                &meta,
+                &self.subscriptions.daemon_event,
+                subscriber,
+                filter,
+                || {
+                    let (tx, rx) = sync::oneshot::channel();
+                    self.send_command_to_daemon(DaemonCommand::GetState(tx))
+                        .and_then(|_| recv_or_error(rx))
+                        .map(DaemonEvent::TunnelState)
+                },
+            );
+        } else {
+            self.subscribe_filtered(&meta, &self.subscriptions.daemon_event, subscriber, filter);
         }
     }
 
     fn daemon_event_unsubscribe(&self, id: SubscriptionId) -> BoxFuture<(), Error> {
         log::debug!("daemon_event_unsubscribe");
-        let was_removed = self.subscriptions.write().remove(&id).is_some();
-        let result = if was_removed {
-            log::debug!("Unsubscribing id {:?}", id);
-            future::ok(())
-        } else {
-            future::err(Error {
-                code: ErrorCode::InvalidParams,
-                message: "Invalid subscription".to_owned(),
-                data: None,
-            })
-        };
-        Box::new(result)
+        Self::unsubscribe(&self.subscriptions.daemon_event, id)
+    }
+
+    fn connection_progress_subscribe(
+        &self,
+        meta: Self::Metadata,
+        subscriber: pubsub::Subscriber<ConnectionProgress>,
+    ) {
+        log::debug!("connection_progress_subscribe");
+        self.subscribe(&meta, &self.subscriptions.connection_progress, subscriber);
+    }
+
+    fn connection_progress_unsubscribe(&self, id: SubscriptionId) -> BoxFuture<(), Error> {
+        log::debug!("connection_progress_unsubscribe");
+        Self::unsubscribe(&self.subscriptions.connection_progress, id)
+    }
+
+    fn connectivity_subscribe(
+        &self,
+        meta: Self::Metadata,
+        subscriber: pubsub::Subscriber<Connectivity>,
+    ) {
+        log::debug!("connectivity_subscribe");
+        self.subscribe(&meta, &self.subscriptions.connectivity, subscriber);
+    }
+
+    fn connectivity_unsubscribe(&self, id: SubscriptionId) -> BoxFuture<(), Error> {
+        log::debug!("connectivity_unsubscribe");
+        Self::unsubscribe(&self.subscriptions.connectivity, id)
+    }
+
+    fn tunnel_stats_subscribe(
+        &self,
+        meta: Self::Metadata,
+        subscriber: pubsub::Subscriber<TunnelStats>,
+    ) {
+        log::debug!("tunnel_stats_subscribe");
+        self.subscribe(&meta, &self.subscriptions.tunnel_stats, subscriber);
+    }
+
+    fn tunnel_stats_unsubscribe(&self, id: SubscriptionId) -> BoxFuture<(), Error> {
+        log::debug!("tunnel_stats_unsubscribe");
+        Self::unsubscribe(&self.subscriptions.tunnel_stats, id)
+    }
+
+    fn relay_list_subscribe(
+        &self,
+        meta: Self::Metadata,
+        subscriber: pubsub::Subscriber<RelayList>,
+    ) {
+        log::debug!("relay_list_subscribe");
+        self.subscribe(&meta, &self.subscriptions.relay_list, subscriber);
+    }
+
+    fn relay_list_unsubscribe(&self, id: SubscriptionId) -> BoxFuture<(), Error> {
+        log::debug!("relay_list_unsubscribe");
+        Self::unsubscribe(&self.subscriptions.relay_list, id)
+    }
+
+    fn account_subscribe(
+        &self,
+        meta: Self::Metadata,
+        subscriber: pubsub::Subscriber<Option<AccountToken>>,
+    ) {
+        log::debug!("account_subscribe");
+        self.subscribe(&meta, &self.subscriptions.account, subscriber);
+    }
+
+    fn account_unsubscribe(&self, id: SubscriptionId) -> BoxFuture<(), Error> {
+        log::debug!("account_unsubscribe");
+        Self::unsubscribe(&self.subscriptions.account, id)
+    }
+
+    fn settings_subscribe(&self, meta: Self::Metadata, subscriber: pubsub::Subscriber<Settings>) {
+        log::debug!("settings_subscribe");
+        self.subscribe(&meta, &self.subscriptions.settings, subscriber);
+    }
+
+    fn settings_unsubscribe(&self, id: SubscriptionId) -> BoxFuture<(), Error> {
+        log::debug!("settings_unsubscribe");
+        Self::unsubscribe(&self.subscriptions.settings, id)
+    }
+
+    fn tunnel_state_subscribe(
+        &self,
+        meta: Self::Metadata,
+        subscriber: pubsub::Subscriber<TunnelState>,
+    ) {
+        log::debug!("tunnel_state_subscribe");
+        self.subscribe(&meta, &self.subscriptions.tunnel_state, subscriber);
+    }
+
+    fn tunnel_state_unsubscribe(&self, id: SubscriptionId) -> BoxFuture<(), Error> {
+        log::debug!("tunnel_state_unsubscribe");
+        Self::unsubscribe(&self.subscriptions.tunnel_state, id)
+    }
+
+    fn log_subscribe(
+        &self,
+        meta: Self::Metadata,
+        subscriber: pubsub::Subscriber<LogRecord>,
+        level: LogLevel,
+    ) {
+        log::debug!("log_subscribe({:?})", level);
+        self.subscribe_filtered(
+            &meta,
+            &self.subscriptions.log,
+            subscriber,
+            move |record: &LogRecord| record.level <= level,
+        );
+    }
+
+    fn log_unsubscribe(&self, id: SubscriptionId) -> BoxFuture<(), Error> {
+        log::debug!("log_unsubscribe");
+        Self::unsubscribe(&self.subscriptions.log, id)
+    }
+}
+
+/// Waits for the daemon's response to a command. If the daemon drops the command without
+/// responding, e.g. because it is too busy, this fails with `DAEMON_NO_RESPONSE_CODE` rather than
+/// a generic internal error, so that clients know the command can be retried.
+fn recv_or_error<T>(rx: sync::oneshot::Receiver<T>) -> impl Future<Item = T, Error = Error> {
+    rx.map_err(|_| Error {
+        code: ErrorCode::ServerError(DAEMON_NO_RESPONSE_CODE),
+        message: "The daemon did not respond to the command".to_owned(),
+        data: None,
+    })
+}
+
+/// The error returned by commands that need an account token when none is configured.
+fn no_account_token_error() -> Error {
+    Error {
+        code: ErrorCode::ServerError(NO_ACCOUNT_TOKEN_CODE),
+        message: "No account token configured".to_owned(),
+        data: None,
     }
 }
 
+/// Builds an `InvalidParams` error whose data names the rejected field and why it was rejected,
+/// as `{"field": ..., "reason": ...}`, so that clients can point out the offending input.
+fn invalid_params_with(field: &str, reason: String) -> Error {
+    Error {
+        code: ErrorCode::InvalidParams,
+        data: Some(serde_json::json!({
+            "field": field,
+            "reason": reason,
+        })),
+        message: reason,
+    }
+}
+
+/// Builds an error whose message only describes `error` itself. The errors that caused it are
+/// listed in the data, outermost first, as `{"causes": [...]}`.
+fn error_with_causes(code: ErrorCode, error: &dyn std::error::Error) -> Error {
+    let mut causes = Vec::new();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        causes.push(cause.to_string());
+        source = cause.source();
+    }
+    Error {
+        code,
+        message: error.to_string(),
+        data: Some(serde_json::json!({ "causes": causes })),
+    }
+}
+
+/// Checks that the given MTU is within the supported range.
+fn validate_mtu(mtu: u16) -> Result<(), Error> {
+    if MTU_RANGE.contains(&mtu) {
+        Ok(())
+    } else {
+        Err(invalid_params_with(
+            "wireguard_mtu",
+            format!(
+                "MTU must be between {} and {}",
+                MTU_RANGE.start(),
+                MTU_RANGE.end()
+            ),
+        ))
+    }
+}
+
+/// Checks that the given application path is absolute and exists.
+fn validate_split_tunnel_app(path: &Path) -> Result<(), Error> {
+    let message = if !path.is_absolute() {
+        format!("Application path must be absolute: {}", path.display())
+    } else if !path.exists() {
+        format!("Application does not exist: {}", path.display())
+    } else {
+        return Ok(());
+    };
+    Err(invalid_params_with("path", message))
+}
+
+/// Checks that custom proxy settings can be used by OpenVPN.
+fn validate_bridge_settings(bridge_settings: &BridgeSettings) -> Result<(), Error> {
+    match bridge_settings {
+        BridgeSettings::Normal(_) => Ok(()),
+        BridgeSettings::Custom(proxy) => openvpn::validate_proxy_settings(proxy)
+            .map_err(|reason| invalid_params_with("bridge_settings", reason)),
+    }
+}
+
+/// Checks that a relay filter with a provider allowlist can match at least one provider.
+fn validate_relay_filter(relay_filter: &RelayFilter) -> Result<(), Error> {
+    match relay_filter.providers {
+        Constraint::Only(ref providers) if providers.is_empty() => Err(invalid_params_with(
+            "relay_filter",
+            "The provider allowlist is empty".to_owned(),
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Checks that the given mssfix value is within the supported range.
+fn validate_mssfix(mssfix: u16) -> Result<(), Error> {
+    if MSSFIX_RANGE.contains(&mssfix) {
+        Ok(())
+    } else {
+        Err(invalid_params_with(
+            "openvpn_mssfix",
+            format!(
+                "mssfix must be between {} and {}",
+                MSSFIX_RANGE.start(),
+                MSSFIX_RANGE.end()
+            ),
+        ))
+    }
+}
+
+/// Checks that the given OpenVPN data channel cipher is supported.
+fn validate_openvpn_cipher(cipher: &str) -> Result<(), Error> {
+    if openvpn::OPENVPN_CIPHERS.contains(&cipher) {
+        Ok(())
+    } else {
+        Err(invalid_params_with(
+            "openvpn_cipher",
+            format!(
+                "Unsupported cipher, must be one of: {}",
+                openvpn::OPENVPN_CIPHERS.join(", ")
+            ),
+        ))
+    }
+}
+
+/// Checks that the given DNS servers can be used inside the tunnel and removes duplicates,
+/// keeping the order of the first occurrences.
+fn validate_dns_servers(servers: Vec<IpAddr>) -> Result<Vec<IpAddr>, Error> {
+    if servers.is_empty() {
+        return Err(invalid_params_with(
+            "custom_dns",
+            "No DNS servers given".to_owned(),
+        ));
+    }
+    let mut valid_servers = Vec::with_capacity(servers.len());
+    for server in servers {
+        if server.is_unspecified() || server.is_loopback() {
+            return Err(invalid_params_with(
+                "custom_dns",
+                format!("Invalid DNS server: {}", server),
+            ));
+        }
+        if !valid_servers.contains(&server) {
+            valid_servers.push(server);
+        }
+    }
+    Ok(valid_servers)
+}
+
+/// Rejects addresses that can never be used as an API endpoint, such as the unspecified address,
+/// multicast and broadcast addresses, and port 0. Whether the API is reachable at the address is
+/// not checked.
+fn validate_api_endpoint(endpoint: SocketAddr) -> Result<(), Error> {
+    let ip = endpoint.ip();
+    let is_broadcast = match ip {
+        IpAddr::V4(ip) => ip.is_broadcast(),
+        IpAddr::V6(_) => false,
+    };
+    if endpoint.port() == 0 || ip.is_unspecified() || ip.is_multicast() || is_broadcast {
+        Err(invalid_params_with(
+            "api_endpoint",
+            format!("Invalid API endpoint: {}", endpoint),
+        ))
+    } else {
+        Ok(())
+    }
+}
 
 /// The metadata type. There is one instance associated with each connection. In this pubsub
 /// scenario they are created by `meta_extractor` by the server on each new incoming
@@ -872,6 +2996,31 @@ impl ManagementInterfaceApi for ManagementInterface {
 #[derive(Clone, Debug, Default)]
 pub struct Meta {
     session: Option<Arc<Session>>,
+    /// Number of active subscriptions held by the connection.
+    subscription_count: Arc<AtomicUsize>,
+    /// IDs of the active subscriptions held by the connection.
+    subscription_ids: Arc<Mutex<HashSet<SubscriptionId>>>,
+}
+
+impl Meta {
+    /// Creates the metadata for a new connection. Once the connection is closed and `session` is
+    /// dropped, the subscriptions the connection still holds are removed from `subscriptions`
+    /// right away, rather than the next time an event fails to be sent to them.
+    fn with_session(session: Session, subscriptions: Arc<ActiveSubscriptions>) -> Self {
+        let subscription_ids = Arc::new(Mutex::new(HashSet::new()));
+        let closed_subscription_ids = subscription_ids.clone();
+        session.on_drop(move || {
+            for id in closed_subscription_ids.lock().drain() {
+                log::debug!("Removing subscription {:?} of closed connection", id);
+                subscriptions.remove(&id);
+            }
+        });
+        Meta {
+            session: Some(Arc::new(session)),
+            subscription_count: Arc::new(AtomicUsize::new(0)),
+            subscription_ids,
+        }
+    }
 }
 
 /// Make the `Meta` type possible to use as jsonrpc metadata type.
@@ -884,9 +3033,1092 @@ impl PubSubMetadata for Meta {
     }
 }
 
-/// Metadata extractor function for `Meta`.
-fn meta_extractor(context: &jsonrpc_ipc_server::RequestContext<'_>) -> Meta {
-    Meta {
-        session: Some(Arc::new(Session::new(context.sender.clone()))),
+/// Returns the metadata extractor for `Meta`, which removes the subscriptions of each connection
+/// from `subscriptions` when it is closed.
+fn meta_extractor(
+    subscriptions: Arc<ActiveSubscriptions>,
+) -> impl Fn(&jsonrpc_ipc_server::RequestContext<'_>) -> Meta + Send + Sync + 'static {
+    move |context: &jsonrpc_ipc_server::RequestContext<'_>| {
+        Meta::with_session(Session::new(context.sender.clone()), subscriptions.clone())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{DaemonCommandChannel, InternalDaemonEvent};
+    use futures01::Stream;
+    use talpid_types::{
+        net::{Endpoint, TransportProtocol, TunnelType},
+        tunnel::{ActionAfterDisconnect, ErrorSeverity, ErrorStateCause},
+    };
+
+    /// Calls `call` on a new management interface and returns what it returned, along with all
+    /// commands that were sent to the daemon.
+    fn dispatched_commands<T>(
+        call: impl FnOnce(&ManagementInterface) -> T,
+    ) -> (T, Vec<DaemonCommand>) {
+        let channel = DaemonCommandChannel::new();
+        let interface = ManagementInterface::new(channel.sender());
+        let (_, events) = channel.destructure();
+
+        let result = call(&interface);
+        // Close the channel so that waiting for events can't block forever.
+        drop(interface);
+
+        let commands = events
+            .wait()
+            .map(|event| match event {
+                Ok(InternalDaemonEvent::Command(command)) => command,
+                _ => panic!("Expected only daemon commands"),
+            })
+            .collect();
+        (result, commands)
+    }
+
+    /// Returns a broadcaster that sends to `subscriptions` without shutting down any server when
+    /// dropped.
+    fn test_broadcaster(
+        subscriptions: Arc<ActiveSubscriptions>,
+    ) -> ManagementInterfaceEventBroadcaster {
+        ManagementInterfaceEventBroadcaster {
+            subscriptions,
+            event_loop: Arc::new(event_loop::spawn()),
+            timer: tokio_timer::Timer::default(),
+            settings_coalesce_interval: SETTINGS_COALESCE_INTERVAL,
+            tunnel_state_coalesce_interval: TUNNEL_STATE_COALESCE_INTERVAL,
+            close_handle: None,
+        }
+    }
+
+    #[test]
+    fn test_set_auto_connect_dispatches_command() {
+        for &auto_connect in &[true, false] {
+            let (_, commands) = dispatched_commands(|interface| {
+                interface.set_auto_connect(Meta::default(), auto_connect)
+            });
+            match commands.as_slice() {
+                [DaemonCommand::SetAutoConnect(_, value)] => assert_eq!(*value, auto_connect),
+                _ => panic!("Expected a SetAutoConnect command"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_logout_dispatches_command() {
+        // Disconnecting and clearing the account are both done by the daemon when handling the
+        // single `Logout` command, so that they can't be separated by other commands.
+        let (_, commands) = dispatched_commands(|interface| interface.logout(Meta::default()));
+        match commands.as_slice() {
+            [DaemonCommand::Logout(_)] => (),
+            _ => panic!("Expected only a Logout command"),
+        }
+    }
+
+    #[test]
+    fn test_full_command_channel_reports_busy() {
+        let channel = DaemonCommandChannel::with_queue_size(0);
+        let interface = ManagementInterface::new(channel.sender());
+        let (_, _events) = channel.destructure();
+
+        // Nothing receives the commands, so the first one fills the queue.
+        let _pending = interface.get_state(Meta::default());
+        let error = interface
+            .get_state(Meta::default())
+            .wait()
+            .expect_err("Command was sent to a full channel");
+        assert_eq!(error.code, ErrorCode::ServerError(DAEMON_BUSY_CODE));
+    }
+
+    #[test]
+    fn test_closed_command_channel_reports_shutdown() {
+        let channel = DaemonCommandChannel::new();
+        let interface = ManagementInterface::new(channel.sender());
+        drop(channel.destructure());
+
+        let error = interface
+            .get_state(Meta::default())
+            .wait()
+            .expect_err("Command was sent to a closed channel");
+        assert_eq!(
+            error.code,
+            ErrorCode::ServerError(DAEMON_SHUTTING_DOWN_CODE)
+        );
+    }
+
+    #[test]
+    fn test_disconnect_reports_closed_command_channel() {
+        let channel = DaemonCommandChannel::new();
+        let interface = ManagementInterface::new(channel.sender());
+        drop(channel.destructure());
+
+        let error = interface
+            .disconnect(Meta::default())
+            .wait()
+            .expect_err("Command was sent to a closed channel");
+        assert_eq!(
+            error.code,
+            ErrorCode::ServerError(DAEMON_SHUTTING_DOWN_CODE)
+        );
+    }
+
+    #[test]
+    fn test_dropped_response_is_reported_as_no_response() {
+        let channel = DaemonCommandChannel::new();
+        let interface = ManagementInterface::new(channel.sender());
+        let (_, events) = channel.destructure();
+
+        let response = interface.get_state(Meta::default());
+        match events.wait().next() {
+            Some(Ok(InternalDaemonEvent::Command(DaemonCommand::GetState(tx)))) => drop(tx),
+            _ => panic!("Expected a GetState command"),
+        }
+
+        let error = response.wait().unwrap_err();
+        assert_eq!(error.code, ErrorCode::ServerError(DAEMON_NO_RESPONSE_CODE));
+    }
+
+    #[test]
+    fn test_shutdown_dispatches_command() {
+        // The response doesn't wait for the daemon, which may exit before it could respond.
+        let (response, commands) =
+            dispatched_commands(|interface| interface.shutdown(Meta::default()));
+        match commands.as_slice() {
+            [DaemonCommand::Shutdown] => (),
+            _ => panic!("Expected a Shutdown command"),
+        }
+        assert!(response.wait().is_ok());
+    }
+
+    #[test]
+    fn test_unknown_tunnel_protocol_is_rejected() {
+        let channel = DaemonCommandChannel::new();
+        let mut io = PubSubHandler::default();
+        io.extend_with(ManagementInterface::new(channel.sender()).to_delegate());
+        let meta_io: MetaIoHandler<Meta> = io.into();
+
+        let request = r#"{
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "update_relay_settings",
+            "params": [{ "normal": { "tunnel_protocol": { "only": "ipsec" } } }]
+        }"#;
+        let response = meta_io
+            .handle_request_sync(request, Meta::default())
+            .expect("No response to request");
+        assert!(response.contains(&format!("\"code\":{}", ErrorCode::InvalidParams.code())));
+    }
+
+    #[test]
+    fn test_set_target_state_dispatches_command() {
+        for &target_state in &[TargetState::Secured, TargetState::Unsecured] {
+            let (response, mut commands) = dispatched_commands(|interface| {
+                interface.set_target_state(Meta::default(), target_state)
+            });
+            assert_eq!(commands.len(), 1);
+            match commands.pop() {
+                Some(DaemonCommand::SetTargetState(tx, value)) => {
+                    assert_eq!(value, target_state);
+                    tx.send(Ok(())).unwrap();
+                }
+                _ => panic!("Expected a SetTargetState command"),
+            }
+            assert!(response.wait().is_ok());
+        }
+    }
+
+    #[test]
+    fn test_unknown_target_state_is_rejected() {
+        let channel = DaemonCommandChannel::new();
+        let mut io = PubSubHandler::default();
+        io.extend_with(ManagementInterface::new(channel.sender()).to_delegate());
+        let meta_io: MetaIoHandler<Meta> = io.into();
+
+        let request = r#"{
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "set_target_state",
+            "params": ["blocked"]
+        }"#;
+        let response = meta_io
+            .handle_request_sync(request, Meta::default())
+            .expect("No response to request");
+        assert!(response.contains(&format!("\"code\":{}", ErrorCode::InvalidParams.code())));
+    }
+
+    #[test]
+    fn test_invalid_params_data() {
+        let error = validate_mssfix(0).unwrap_err();
+        assert_eq!(error.code, ErrorCode::InvalidParams);
+        assert_eq!(
+            error.data,
+            Some(serde_json::json!({
+                "field": "openvpn_mssfix",
+                "reason": error.message,
+            }))
+        );
+    }
+
+    #[test]
+    fn test_error_with_causes() {
+        let error = crate::settings::Error::ReloadError(
+            "settings.json".to_owned(),
+            crate::settings::LoadSettingsError::Other(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                "Disk is unplugged",
+            )),
+        );
+
+        let error = error_with_causes(ErrorCode::InternalError, &error);
+        assert_eq!(
+            error.message,
+            "Unable to reload settings from settings.json"
+        );
+        assert_eq!(
+            error.data,
+            Some(serde_json::json!({
+                "causes": ["Unable to read settings file", "Disk is unplugged"],
+            }))
+        );
+    }
+
+    #[test]
+    fn test_validate_mtu() {
+        for &mtu in &[576, 1380, 1500] {
+            assert!(validate_mtu(mtu).is_ok());
+        }
+        for &mtu in &[0, 575, 1501, u16::max_value()] {
+            assert_eq!(
+                validate_mtu(mtu).unwrap_err().code,
+                ErrorCode::InvalidParams
+            );
+        }
+    }
+
+    #[test]
+    fn test_validate_split_tunnel_app() {
+        let existing = std::env::current_exe().unwrap();
+        assert!(validate_split_tunnel_app(&existing).is_ok());
+
+        let relative = Path::new("bin/firefox");
+        assert_eq!(
+            validate_split_tunnel_app(relative).unwrap_err().code,
+            ErrorCode::InvalidParams
+        );
+        let missing = existing.with_file_name("mullvad-split-tunnel-test-missing-app");
+        assert_eq!(
+            validate_split_tunnel_app(&missing).unwrap_err().code,
+            ErrorCode::InvalidParams
+        );
+    }
+
+    #[test]
+    fn test_relative_split_tunnel_app_is_not_dispatched() {
+        let (response, commands) = dispatched_commands(|interface| {
+            interface.add_split_tunnel_app(Meta::default(), PathBuf::from("firefox"))
+        });
+        assert_eq!(response.wait().unwrap_err().code, ErrorCode::InvalidParams);
+        assert!(commands.is_empty());
+    }
+
+    #[test]
+    fn test_logs_are_only_wanted_with_subscribers() {
+        use crate::logging::LogListener;
+
+        let subscriptions = Arc::new(ActiveSubscriptions::default());
+        let broadcaster = test_broadcaster(subscriptions.clone());
+        assert!(!broadcaster.wants_logs());
+
+        let (log_tx, _log_rx) = sync::mpsc::channel(SUBSCRIBER_BUFFER_SIZE);
+        subscriptions
+            .log
+            .write()
+            .insert(SubscriptionId::String("log".to_owned()), log_tx);
+        assert!(broadcaster.wants_logs());
+    }
+
+    #[test]
+    fn test_set_log_level_dispatches_command() {
+        for &requested_level in &[LogLevel::Trace, LogLevel::Warn] {
+            let (_, commands) = dispatched_commands(|interface| {
+                interface.set_log_level(Meta::default(), requested_level)
+            });
+            match commands.as_slice() {
+                [DaemonCommand::SetLogLevel(_, level)] => assert_eq!(*level, requested_level),
+                _ => panic!("Expected a SetLogLevel command"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_validate_mssfix() {
+        for &mssfix in &[100, 1300, 1500] {
+            assert!(validate_mssfix(mssfix).is_ok());
+        }
+        for &mssfix in &[0, 99, 1501, u16::max_value()] {
+            assert_eq!(
+                validate_mssfix(mssfix).unwrap_err().code,
+                ErrorCode::InvalidParams
+            );
+        }
+    }
+
+    #[test]
+    fn test_set_supported_openvpn_cipher() {
+        let (_, commands) = dispatched_commands(|interface| {
+            interface.set_openvpn_cipher(Meta::default(), Some("AES-256-GCM".to_owned()))
+        });
+        match commands.as_slice() {
+            [DaemonCommand::SetOpenVpnCipher(_, cipher)] => {
+                assert_eq!(cipher.as_ref().map(String::as_str), Some("AES-256-GCM"))
+            }
+            _ => panic!("Expected a SetOpenVpnCipher command"),
+        }
+    }
+
+    #[test]
+    fn test_unknown_openvpn_cipher_is_rejected() {
+        let (response, commands) = dispatched_commands(|interface| {
+            interface.set_openvpn_cipher(Meta::default(), Some("BF-CBC".to_owned()))
+        });
+        assert_eq!(response.wait().unwrap_err().code, ErrorCode::InvalidParams);
+        assert!(commands.is_empty());
+    }
+
+    #[test]
+    fn test_validate_bridge_settings() {
+        let local = openvpn::ProxySettings::Local(openvpn::LocalProxySettings {
+            port: 1080,
+            peer: "185.65.134.10:443".parse().unwrap(),
+        });
+        let remote = |address: &str| {
+            openvpn::ProxySettings::Remote(openvpn::RemoteProxySettings {
+                address: address.parse().unwrap(),
+                auth: None,
+            })
+        };
+
+        assert!(validate_bridge_settings(&BridgeSettings::Custom(local)).is_ok());
+        assert!(validate_bridge_settings(&BridgeSettings::Custom(remote("10.0.0.1:1080"))).is_ok());
+        for invalid in &[remote("127.0.0.1:1080"), remote("10.0.0.1:0")] {
+            assert_eq!(
+                validate_bridge_settings(&BridgeSettings::Custom(invalid.clone()))
+                    .unwrap_err()
+                    .code,
+                ErrorCode::InvalidParams
+            );
+        }
+    }
+
+    #[test]
+    fn test_set_openvpn_mssfix_is_protocol_agnostic() {
+        // The interface doesn't know which protocol is in use, so a valid value must always reach
+        // the daemon, which only reconnects OpenVPN tunnels.
+        for &mssfix in &[Some(1300), None] {
+            let (_, commands) = dispatched_commands(|interface| {
+                interface.set_openvpn_mssfix(Meta::default(), mssfix)
+            });
+            match commands.as_slice() {
+                [DaemonCommand::SetOpenVpnMssfix(_, value)] => assert_eq!(*value, mssfix),
+                _ => panic!("Expected a SetOpenVpnMssfix command"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_set_api_endpoint() {
+        for &endpoint in &[Some("10.0.0.1:8443".parse().unwrap()), None] {
+            let (_, commands) = dispatched_commands(|interface| {
+                interface.set_api_endpoint(Meta::default(), endpoint)
+            });
+            match commands.as_slice() {
+                [DaemonCommand::SetApiEndpoint(_, value)] => assert_eq!(*value, endpoint),
+                _ => panic!("Expected a SetApiEndpoint command"),
+            }
+        }
+
+        for invalid in &["0.0.0.0:443", "10.0.0.1:0", "[::]:443", "224.0.0.1:443"] {
+            let (response, commands) = dispatched_commands(|interface| {
+                interface.set_api_endpoint(Meta::default(), Some(invalid.parse().unwrap()))
+            });
+            assert_eq!(response.wait().unwrap_err().code, ErrorCode::InvalidParams);
+            assert!(commands.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_validate_dns_servers() {
+        let first: IpAddr = "10.64.0.1".parse().unwrap();
+        let second: IpAddr = "fc00:bbbb:bbbb:bb01::1".parse().unwrap();
+        assert_eq!(
+            validate_dns_servers(vec![first, second, first]).unwrap(),
+            vec![first, second]
+        );
+
+        for invalid in &["0.0.0.0", "127.0.0.1", "::", "::1"] {
+            let error = validate_dns_servers(vec![invalid.parse().unwrap()]).unwrap_err();
+            assert_eq!(error.code, ErrorCode::InvalidParams);
+        }
+        assert_eq!(
+            validate_dns_servers(vec![]).unwrap_err().code,
+            ErrorCode::InvalidParams
+        );
+    }
+
+    #[test]
+    fn test_set_allow_lan_dispatches_command() {
+        for &allow_lan in &[true, false] {
+            let (_, commands) = dispatched_commands(|interface| {
+                interface.set_allow_lan(Meta::default(), allow_lan)
+            });
+            match commands.as_slice() {
+                [DaemonCommand::SetAllowLan(_, value)] => assert_eq!(*value, allow_lan),
+                _ => panic!("Expected a SetAllowLan command"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_get_relay_locations_does_not_update_relay_list() {
+        let (_, commands) =
+            dispatched_commands(|interface| interface.get_relay_locations(Meta::default()));
+        match commands.as_slice() {
+            [DaemonCommand::GetRelayLocations(_)] => (),
+            _ => panic!("Expected only a GetRelayLocations command"),
+        }
+    }
+
+    #[test]
+    fn test_update_relay_list_reports_failure() {
+        let (response, mut commands) =
+            dispatched_commands(|interface| interface.update_relay_list(Meta::default()));
+        assert_eq!(commands.len(), 1);
+        match commands.pop() {
+            Some(DaemonCommand::UpdateRelayList(tx)) => tx.send(Err(())).unwrap(),
+            _ => panic!("Expected an UpdateRelayList command"),
+        }
+        assert_eq!(response.wait().unwrap_err().code, ErrorCode::InternalError);
+    }
+
+    #[test]
+    fn test_get_www_auth_token_without_account() {
+        let (response, mut commands) =
+            dispatched_commands(|interface| interface.get_www_auth_token(Meta::default()));
+        assert_eq!(commands.len(), 1);
+        match commands.pop() {
+            Some(DaemonCommand::GetWwwAuthToken(tx)) => tx.send(None).unwrap(),
+            _ => panic!("Expected a GetWwwAuthToken command"),
+        }
+        assert_eq!(
+            response.wait().unwrap_err().code,
+            ErrorCode::ServerError(NO_ACCOUNT_TOKEN_CODE)
+        );
+    }
+
+    #[test]
+    fn test_set_enable_ipv6_dispatches_command() {
+        for &enable_ipv6 in &[true, false] {
+            let (_, commands) = dispatched_commands(|interface| {
+                interface.set_enable_ipv6(Meta::default(), enable_ipv6)
+            });
+            match commands.as_slice() {
+                [DaemonCommand::SetEnableIpv6(_, value)] => assert_eq!(*value, enable_ipv6),
+                _ => panic!("Expected a SetEnableIpv6 command"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_set_city_location_constraint_dispatches_command() {
+        let city = LocationConstraint::City("se".to_owned(), "got".to_owned());
+        let (_, commands) = dispatched_commands(|interface| {
+            interface.set_location_constraint(Meta::default(), Some(city.clone()))
+        });
+        match commands.as_slice() {
+            [DaemonCommand::SetLocationConstraint(_, location)] => {
+                assert_eq!(*location, Some(city))
+            }
+            _ => panic!("Expected a SetLocationConstraint command"),
+        }
+    }
+
+    #[test]
+    fn test_notify_prunes_closed_subscriptions() {
+        let open_id = SubscriptionId::String("open".to_owned());
+        let closed_id = SubscriptionId::String("closed".to_owned());
+        let (open_tx, _open_rx) = sync::mpsc::channel(SUBSCRIBER_BUFFER_SIZE);
+        let (closed_tx, closed_rx) = sync::mpsc::channel(SUBSCRIBER_BUFFER_SIZE);
+        drop(closed_rx);
+
+        let mut subscriptions = HashMap::new();
+        subscriptions.insert(open_id.clone(), open_tx);
+        subscriptions.insert(closed_id.clone(), closed_tx);
+        let broadcaster = test_broadcaster(Arc::new(ActiveSubscriptions {
+            daemon_event: RwLock::new(subscriptions),
+            ..ActiveSubscriptions::default()
+        }));
+
+        broadcaster.notify(DaemonEvent::TunnelState(TunnelState::Disconnected {
+            blocking: false,
+        }));
+
+        let subscriptions = broadcaster.subscriptions.daemon_event.read();
+        assert!(subscriptions.contains_key(&open_id));
+        assert!(!subscriptions.contains_key(&closed_id));
+    }
+
+    #[test]
+    fn test_notify_drops_subscribers_that_fall_behind() {
+        let slow_id = SubscriptionId::String("slow".to_owned());
+        let id = SubscriptionId::String("id".to_owned());
+        // A channel without a buffer only fits the one message that each sender is guaranteed.
+        let (slow_tx, _slow_rx) = sync::mpsc::channel(0);
+        let (event_tx, _event_rx) = sync::mpsc::channel(SUBSCRIBER_BUFFER_SIZE);
+
+        let mut subscriptions = HashMap::new();
+        subscriptions.insert(slow_id.clone(), slow_tx);
+        subscriptions.insert(id.clone(), event_tx);
+        let broadcaster = test_broadcaster(Arc::new(ActiveSubscriptions {
+            daemon_event: RwLock::new(subscriptions),
+            ..ActiveSubscriptions::default()
+        }));
+
+        for _ in 0..2 {
+            broadcaster.notify(DaemonEvent::TunnelState(TunnelState::Disconnected {
+                blocking: false,
+            }));
+        }
+
+        let subscriptions = broadcaster.subscriptions.daemon_event.read();
+        assert!(!subscriptions.contains_key(&slow_id));
+        assert!(subscriptions.contains_key(&id));
+    }
+
+    #[test]
+    fn test_notify_relay_list_prunes_closed_subscriptions() {
+        let open_id = SubscriptionId::Number(0);
+        let closed_id = SubscriptionId::Number(1);
+        let (open_tx, open_rx) = sync::mpsc::channel(SUBSCRIBER_BUFFER_SIZE);
+        let (closed_tx, closed_rx) = sync::mpsc::channel(SUBSCRIBER_BUFFER_SIZE);
+        drop(closed_rx);
+
+        let mut subscriptions = HashMap::new();
+        subscriptions.insert(open_id.clone(), open_tx);
+        subscriptions.insert(closed_id.clone(), closed_tx);
+        let broadcaster = test_broadcaster(Arc::new(ActiveSubscriptions {
+            relay_list: RwLock::new(subscriptions),
+            ..ActiveSubscriptions::default()
+        }));
+
+        broadcaster.notify_relay_list(RelayList::empty());
+
+        {
+            let subscriptions = broadcaster.subscriptions.relay_list.read();
+            assert!(subscriptions.contains_key(&open_id));
+            assert!(!subscriptions.contains_key(&closed_id));
+        }
+        drop(broadcaster);
+        let received: Vec<_> = open_rx.wait().collect();
+        assert_eq!(received.len(), 1);
+    }
+
+    #[test]
+    fn test_notify_account_includes_logout() {
+        let channel = DaemonCommandChannel::new();
+        let interface = ManagementInterface::new(channel.sender());
+        let (subscriber, _id_rx, transport_rx) = jsonrpc_pubsub::Subscriber::new_test("account");
+        interface.account_subscribe(Meta::default(), pubsub::Subscriber::new(subscriber));
+        let broadcaster = test_broadcaster(interface.subscriptions.clone());
+
+        broadcaster.notify_account(Some("1234567890".to_owned()));
+        broadcaster.notify_account(None);
+
+        let messages: Vec<String> = transport_rx
+            .wait()
+            .take(2)
+            .map(|message| message.expect("Failed to receive event"))
+            .collect();
+        assert!(messages[0].contains("\"1234567890\""));
+        assert!(messages[1].contains("null"));
+    }
+
+    #[test]
+    fn test_settings_changes_are_coalesced() {
+        let (settings_tx, settings_rx) = sync::mpsc::channel(SUBSCRIBER_BUFFER_SIZE);
+        let mut subscriptions = HashMap::new();
+        subscriptions.insert(SubscriptionId::Number(0), settings_tx);
+        let mut broadcaster = test_broadcaster(Arc::new(ActiveSubscriptions {
+            settings: RwLock::new(subscriptions),
+            ..ActiveSubscriptions::default()
+        }));
+        // Keep the changes pending for as long as the test runs.
+        broadcaster.settings_coalesce_interval = Duration::from_secs(60 * 60);
+
+        let mut settings = Settings::default();
+        settings.allow_lan = true;
+        broadcaster.notify_settings(settings.clone());
+        settings.block_when_disconnected = true;
+        broadcaster.notify_settings(settings.clone());
+        settings.auto_connect = true;
+        broadcaster.notify_settings(settings.clone());
+
+        assert_eq!(
+            *broadcaster.subscriptions.pending_settings.lock(),
+            Some(settings)
+        );
+        let mut received = futures01::executor::spawn(settings_rx);
+        assert!(received
+            .poll_stream_notify(&Arc::new(NoopNotify), 0)
+            .unwrap()
+            .is_not_ready());
+    }
+
+    #[test]
+    fn test_coalesced_settings_are_sent_after_interval() {
+        let (settings_tx, settings_rx) = sync::mpsc::channel(SUBSCRIBER_BUFFER_SIZE);
+        let mut subscriptions = HashMap::new();
+        subscriptions.insert(SubscriptionId::Number(0), settings_tx);
+        let broadcaster = test_broadcaster(Arc::new(ActiveSubscriptions {
+            settings: RwLock::new(subscriptions),
+            ..ActiveSubscriptions::default()
+        }));
+
+        let mut settings = Settings::default();
+        settings.allow_lan = true;
+        broadcaster.notify_settings(settings.clone());
+
+        let received = settings_rx.wait().next().unwrap().unwrap();
+        assert_eq!(received, settings);
+        assert!(broadcaster.subscriptions.pending_settings.lock().is_none());
+    }
+
+    #[test]
+    fn test_tunnel_states_are_coalesced() {
+        let (state_tx, state_rx) = sync::mpsc::channel(SUBSCRIBER_BUFFER_SIZE);
+        let (event_tx, event_rx) = sync::mpsc::channel(SUBSCRIBER_BUFFER_SIZE);
+        let mut state_subscriptions = HashMap::new();
+        state_subscriptions.insert(SubscriptionId::Number(0), state_tx);
+        let mut event_subscriptions = HashMap::new();
+        event_subscriptions.insert(SubscriptionId::Number(1), event_tx);
+        let mut broadcaster = test_broadcaster(Arc::new(ActiveSubscriptions {
+            tunnel_state: RwLock::new(state_subscriptions),
+            daemon_event: RwLock::new(event_subscriptions),
+            ..ActiveSubscriptions::default()
+        }));
+        // Keep the states pending for as long as the test runs.
+        broadcaster.tunnel_state_coalesce_interval = Duration::from_secs(60 * 60);
+
+        broadcaster.notify_new_state(TunnelState::Disconnecting(ActionAfterDisconnect::Reconnect));
+        broadcaster.notify_new_state(TunnelState::Disconnected { blocking: false });
+        broadcaster.notify_new_state(TunnelState::Disconnected { blocking: true });
+
+        match *broadcaster.subscriptions.pending_tunnel_state.lock() {
+            Some(TunnelState::Disconnected { blocking: true }) => (),
+            ref state => panic!("Unexpected pending state: {:?}", state),
+        }
+        let notify = Arc::new(NoopNotify);
+        let mut states = futures01::executor::spawn(state_rx);
+        assert!(states
+            .poll_stream_notify(&notify, 0)
+            .unwrap()
+            .is_not_ready());
+        // Every state is still sent to `daemon_event` subscribers right away.
+        let mut events = futures01::executor::spawn(event_rx);
+        for _ in 0..3 {
+            assert!(events.poll_stream_notify(&notify, 0).unwrap().is_ready());
+        }
+        assert!(events
+            .poll_stream_notify(&notify, 0)
+            .unwrap()
+            .is_not_ready());
+    }
+
+    #[test]
+    fn test_nothing_is_coalesced_without_subscribers() {
+        let broadcaster = test_broadcaster(Arc::new(ActiveSubscriptions::default()));
+
+        broadcaster.notify_settings(Settings::default());
+        broadcaster.notify_new_state(TunnelState::Disconnected { blocking: false });
+
+        assert!(broadcaster.subscriptions.pending_settings.lock().is_none());
+        assert!(broadcaster
+            .subscriptions
+            .pending_tunnel_state
+            .lock()
+            .is_none());
+    }
+
+    #[test]
+    fn test_notify_preserves_order_per_subscriber() {
+        let channel = DaemonCommandChannel::new();
+        let interface = ManagementInterface::new(channel.sender());
+        let (subscriber, _id_rx, transport_rx) =
+            jsonrpc_pubsub::Subscriber::new_test("daemon_event");
+        interface.daemon_event_subscribe(
+            Meta::default(),
+            pubsub::Subscriber::new(subscriber),
+            Trailing::from(None),
+        );
+        let broadcaster = test_broadcaster(interface.subscriptions.clone());
+
+        let actions = [
+            ActionAfterDisconnect::Nothing,
+            ActionAfterDisconnect::Block,
+            ActionAfterDisconnect::Reconnect,
+        ];
+        for action in actions.iter() {
+            broadcaster.notify(DaemonEvent::TunnelState(TunnelState::Disconnecting(
+                *action,
+            )));
+        }
+
+        let messages: Vec<String> = transport_rx
+            .wait()
+            .take(actions.len() as u64)
+            .map(|message| message.expect("Failed to receive event"))
+            .collect();
+        assert!(messages[0].contains("\"nothing\""));
+        assert!(messages[1].contains("\"block\""));
+        assert!(messages[2].contains("\"reconnect\""));
+    }
+
+    #[test]
+    fn test_daemon_event_subscribe_sends_initial_state() {
+        let channel = DaemonCommandChannel::new();
+        let interface = ManagementInterface::new(channel.sender());
+        let (_, events) = channel.destructure();
+        let (subscriber, _id_rx, transport_rx) =
+            jsonrpc_pubsub::Subscriber::new_test("daemon_event");
+        interface.daemon_event_subscribe(
+            Meta::default(),
+            pubsub::Subscriber::new(subscriber),
+            Trailing::from(Some(DaemonEventSubscribeArgs::SendInitialState(true))),
+        );
+        let broadcaster = test_broadcaster(interface.subscriptions.clone());
+
+        // An event broadcast before the current state is known must come after it.
+        broadcaster.notify(DaemonEvent::TunnelState(TunnelState::Disconnecting(
+            ActionAfterDisconnect::Block,
+        )));
+        match events.wait().next() {
+            Some(Ok(InternalDaemonEvent::Command(DaemonCommand::GetState(tx)))) => tx
+                .send(TunnelState::Disconnected { blocking: false })
+                .unwrap(),
+            _ => panic!("Expected a GetState command"),
+        }
+
+        let messages: Vec<String> = transport_rx
+            .wait()
+            .take(2)
+            .map(|message| message.expect("Failed to receive event"))
+            .collect();
+        assert!(messages[0].contains("\"disconnected\""));
+        assert!(messages[1].contains("\"block\""));
+    }
+
+    #[test]
+    fn test_daemon_event_subscribe_skips_less_severe_errors() {
+        let channel = DaemonCommandChannel::new();
+        let interface = ManagementInterface::new(channel.sender());
+        let (subscriber, _id_rx, transport_rx) =
+            jsonrpc_pubsub::Subscriber::new_test("daemon_event");
+        interface.daemon_event_subscribe(
+            Meta::default(),
+            pubsub::Subscriber::new(subscriber),
+            Trailing::from(Some(DaemonEventSubscribeArgs::Options(
+                DaemonEventSubscribeOptions {
+                    send_initial_state: false,
+                    min_error_severity: Some(ErrorSeverity::Major),
+                },
+            ))),
+        );
+        let broadcaster = test_broadcaster(interface.subscriptions.clone());
+
+        broadcaster.notify(DaemonEvent::TunnelState(TunnelState::Error(
+            ErrorState::new(ErrorStateCause::IsOffline, true),
+        )));
+        broadcaster.notify(DaemonEvent::TunnelState(TunnelState::Error(
+            ErrorState::new(ErrorStateCause::SetDnsError, true),
+        )));
+        broadcaster.notify(DaemonEvent::TunnelState(TunnelState::Disconnected {
+            blocking: false,
+        }));
+
+        let messages: Vec<String> = transport_rx
+            .wait()
+            .take(2)
+            .map(|message| message.expect("Failed to receive event"))
+            .collect();
+        assert!(messages[0].contains("\"set_dns_error\""));
+        assert!(messages[1].contains("\"disconnected\""));
+    }
+
+    #[test]
+    fn test_daemon_event_subscribe_args_accept_bool() {
+        let options = DaemonEventSubscribeOptions::from(
+            serde_json::from_str::<DaemonEventSubscribeArgs>("true").unwrap(),
+        );
+        assert!(options.send_initial_state);
+        assert_eq!(options.min_error_severity, None);
+
+        let options = DaemonEventSubscribeOptions::from(
+            serde_json::from_str::<DaemonEventSubscribeArgs>(r#"{"min_error_severity":"minor"}"#)
+                .unwrap(),
+        );
+        assert!(!options.send_initial_state);
+        assert_eq!(options.min_error_severity, Some(ErrorSeverity::Minor));
+    }
+
+    #[test]
+    fn test_subscriptions_per_connection_are_capped() {
+        let channel = DaemonCommandChannel::new();
+        let interface = ManagementInterface::new(channel.sender());
+        let meta = Meta::default();
+        let mut receivers = Vec::new();
+        for _ in 0..=MAX_SUBSCRIPTIONS_PER_CONNECTION {
+            let (subscriber, id_rx, transport_rx) =
+                jsonrpc_pubsub::Subscriber::new_test("daemon_event");
+            interface.daemon_event_subscribe(
+                meta.clone(),
+                pubsub::Subscriber::new(subscriber),
+                Trailing::from(None),
+            );
+            receivers.push((id_rx, transport_rx));
+        }
+
+        let results: Vec<_> = receivers
+            .into_iter()
+            .map(|(id_rx, _)| id_rx.wait().expect("Failed to receive subscription id"))
+            .collect();
+        assert!(results[..MAX_SUBSCRIPTIONS_PER_CONNECTION]
+            .iter()
+            .all(Result::is_ok));
+        assert!(results[MAX_SUBSCRIPTIONS_PER_CONNECTION].is_err());
+        assert_eq!(
+            interface.subscriptions.daemon_event.read().len(),
+            MAX_SUBSCRIPTIONS_PER_CONNECTION
+        );
+    }
+
+    #[test]
+    fn test_failed_id_assignment_releases_subscription() {
+        let channel = DaemonCommandChannel::new();
+        let interface = ManagementInterface::new(channel.sender());
+        let meta = Meta::default();
+        let (subscriber, id_rx, _transport_rx) =
+            jsonrpc_pubsub::Subscriber::new_test("daemon_event");
+        // The client goes away before the subscription id is assigned.
+        drop(id_rx);
+        interface.daemon_event_subscribe(
+            meta.clone(),
+            pubsub::Subscriber::new(subscriber),
+            Trailing::from(Some(DaemonEventSubscribeArgs::SendInitialState(true))),
+        );
+
+        assert!(interface.subscriptions.daemon_event.read().is_empty());
+        assert_eq!(meta.subscription_count.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn test_subscription_id_only_unsubscribes_its_channel() {
+        let channel = DaemonCommandChannel::new();
+        let interface = ManagementInterface::new(channel.sender());
+        let (subscriber, id_rx, _transport_rx) = jsonrpc_pubsub::Subscriber::new_test("settings");
+        interface.settings_subscribe(Meta::default(), pubsub::Subscriber::new(subscriber));
+
+        // The id is assigned by the time the subscribe call returns.
+        let id = id_rx
+            .wait()
+            .expect("Failed to receive subscription id")
+            .expect("Subscription was rejected");
+        assert!(interface.subscriptions.settings.read().contains_key(&id));
+
+        assert!(interface
+            .tunnel_state_unsubscribe(id.clone())
+            .wait()
+            .is_err());
+        assert!(interface.settings_unsubscribe(id.clone()).wait().is_ok());
+        assert!(!interface.subscriptions.settings.read().contains_key(&id));
+    }
+
+    #[test]
+    fn test_closing_connection_removes_its_subscriptions() {
+        let channel = DaemonCommandChannel::new();
+        let interface = ManagementInterface::new(channel.sender());
+        let (transport_tx, _transport_rx) = futures01::sync::mpsc::channel(1);
+        let meta = Meta::with_session(Session::new(transport_tx), interface.subscriptions.clone());
+
+        for _ in 0..2 {
+            let (subscriber, id_rx, _transport_rx) =
+                jsonrpc_pubsub::Subscriber::new_test("settings");
+            interface.settings_subscribe(meta.clone(), pubsub::Subscriber::new(subscriber));
+            id_rx
+                .wait()
+                .expect("Failed to receive subscription id")
+                .expect("Subscription was rejected");
+        }
+        assert_eq!(interface.subscriptions.settings.read().len(), 2);
+        let (subscriber, id_rx, _transport_rx) = jsonrpc_pubsub::Subscriber::new_test("log");
+        interface.log_subscribe(
+            Meta::default(),
+            pubsub::Subscriber::new(subscriber),
+            LogLevel::Info,
+        );
+        let other_id = id_rx
+            .wait()
+            .expect("Failed to receive subscription id")
+            .expect("Subscription was rejected");
+
+        // Dropping the last reference to the session is what closing the connection does.
+        drop(meta);
+        assert!(interface.subscriptions.settings.read().is_empty());
+        assert!(interface.subscriptions.log.read().contains_key(&other_id));
+    }
+
+    #[test]
+    fn test_subscription_ids_are_sequential() {
+        let channel = DaemonCommandChannel::new();
+        let interface = ManagementInterface::new(channel.sender());
+        let mut id_receivers = Vec::new();
+        for _ in 0..2 {
+            let (subscriber, id_rx, _transport_rx) =
+                jsonrpc_pubsub::Subscriber::new_test("daemon_event");
+            interface.daemon_event_subscribe(
+                Meta::default(),
+                pubsub::Subscriber::new(subscriber),
+                Trailing::from(None),
+            );
+            id_receivers.push(id_rx);
+        }
+
+        let ids: Vec<SubscriptionId> = id_receivers
+            .into_iter()
+            .map(|id_rx| {
+                id_rx
+                    .wait()
+                    .expect("Failed to receive subscription id")
+                    .expect("Subscription was rejected")
+            })
+            .collect();
+        assert_eq!(
+            ids,
+            vec![SubscriptionId::Number(0), SubscriptionId::Number(1)]
+        );
+
+        assert!(interface
+            .daemon_event_unsubscribe(ids[0].clone())
+            .wait()
+            .is_ok());
+        assert!(interface
+            .daemon_event_unsubscribe(ids[0].clone())
+            .wait()
+            .is_err());
+        assert!(interface
+            .subscriptions
+            .daemon_event
+            .read()
+            .contains_key(&ids[1]));
+    }
+
+    #[test]
+    fn test_connect_and_wait_resolves_when_connected() {
+        let channel = DaemonCommandChannel::new();
+        let interface = ManagementInterface::new(channel.sender());
+        let (_, events) = channel.destructure();
+        let broadcaster = test_broadcaster(interface.subscriptions.clone());
+
+        let mut response = futures01::executor::spawn(interface.connect_and_wait(Meta::default()));
+        let notify = Arc::new(NoopNotify);
+        let mut events = events.wait();
+        match events.next() {
+            Some(Ok(InternalDaemonEvent::Command(DaemonCommand::SetTargetState(
+                tx,
+                TargetState::Secured,
+            )))) => tx.send(Ok(())).unwrap(),
+            _ => panic!("Expected a SetTargetState command"),
+        }
+        match events.next() {
+            Some(Ok(InternalDaemonEvent::Command(DaemonCommand::GetState(tx)))) => tx
+                .send(TunnelState::Disconnected { blocking: false })
+                .unwrap(),
+            _ => panic!("Expected a GetState command"),
+        }
+
+        let endpoint = TunnelEndpoint {
+            endpoint: Endpoint::new([1, 2, 3, 4], 51820, TransportProtocol::Udp),
+            tunnel_type: TunnelType::Wireguard,
+            proxy: None,
+        };
+        // Left over from before connecting was requested.
+        broadcaster.notify_new_state(TunnelState::Disconnected { blocking: false });
+        assert!(response
+            .poll_future_notify(&notify, 0)
+            .unwrap()
+            .is_not_ready());
+
+        for state in vec![
+            TunnelState::Connecting {
+                endpoint,
+                location: None,
+            },
+            TunnelState::Disconnecting(ActionAfterDisconnect::Reconnect),
+        ] {
+            broadcaster.notify_new_state(state);
+            assert!(response
+                .poll_future_notify(&notify, 0)
+                .unwrap()
+                .is_not_ready());
+        }
+
+        broadcaster.notify_new_state(TunnelState::Connected {
+            endpoint,
+            location: None,
+            connected_since: None,
+        });
+        assert!(response.poll_future_notify(&notify, 0).unwrap().is_ready());
+        assert!(interface.subscriptions.daemon_event.read().is_empty());
+    }
+
+    #[test]
+    fn test_connect_and_wait_fails_when_disconnected() {
+        let channel = DaemonCommandChannel::new();
+        let interface = ManagementInterface::new(channel.sender());
+        let (_, events) = channel.destructure();
+        let broadcaster = test_broadcaster(interface.subscriptions.clone());
+
+        let mut response = futures01::executor::spawn(interface.connect_and_wait(Meta::default()));
+        let notify = Arc::new(NoopNotify);
+        let mut events = events.wait();
+        match events.next() {
+            Some(Ok(InternalDaemonEvent::Command(DaemonCommand::SetTargetState(tx, _)))) => {
+                tx.send(Ok(())).unwrap()
+            }
+            _ => panic!("Expected a SetTargetState command"),
+        }
+        match events.next() {
+            Some(Ok(InternalDaemonEvent::Command(DaemonCommand::GetState(tx)))) => tx
+                .send(TunnelState::Connecting {
+                    endpoint: TunnelEndpoint {
+                        endpoint: Endpoint::new([1, 2, 3, 4], 51820, TransportProtocol::Udp),
+                        tunnel_type: TunnelType::Wireguard,
+                        proxy: None,
+                    },
+                    location: None,
+                })
+                .unwrap(),
+            _ => panic!("Expected a GetState command"),
+        }
+
+        broadcaster.notify_new_state(TunnelState::Disconnected { blocking: false });
+        let error = response.poll_future_notify(&notify, 0).unwrap_err();
+        assert_eq!(error.code, ErrorCode::ServerError(CONNECTION_FAILED_CODE));
+        assert!(interface.subscriptions.daemon_event.read().is_empty());
+    }
+
+    /// Lets tests poll futures step by step, without anything to wake up.
+    struct NoopNotify;
+
+    impl futures01::executor::Notify for NoopNotify {
+        fn notify(&self, _id: usize) {}
     }
 }
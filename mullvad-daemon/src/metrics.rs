@@ -0,0 +1,283 @@
+use crate::{DaemonCommand, DaemonCommandSender};
+use futures01::{future::Either, sync::oneshot, Future};
+use mullvad_types::states::{StatusSnapshot, TunnelState};
+use std::{
+    fmt::Write as _,
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How long a client has from connecting until it has received its response. Since requests are
+/// handled one at a time, this bounds how long a slow client can hold up everyone else.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Requests larger than this are cut off. Only the request line is of any use.
+const MAX_REQUEST_SIZE: u64 = 8 * 1024;
+
+const TUNNEL_STATES: [&str; 5] = [
+    "disconnected",
+    "connecting",
+    "connected",
+    "disconnecting",
+    "error",
+];
+
+/// Serves the status snapshot of the daemon as Prometheus metrics over plain HTTP, on
+/// `GET /metrics`. Requests are handled one at a time, each within `REQUEST_TIMEOUT`.
+pub struct MetricsServer {
+    address: SocketAddr,
+}
+
+impl MetricsServer {
+    pub fn start(command_sender: DaemonCommandSender, address: SocketAddr) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        let address = listener.local_addr()?;
+        thread::spawn(move || {
+            let timer = tokio_timer::Timer::default();
+            for stream in listener.incoming() {
+                let result = stream.and_then(|stream| serve(stream, &command_sender, &timer));
+                if let Err(error) = result {
+                    log::debug!("Failed to serve metrics request: {}", error);
+                }
+            }
+        });
+        Ok(MetricsServer { address })
+    }
+
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+}
+
+fn serve(
+    mut stream: TcpStream,
+    command_sender: &DaemonCommandSender,
+    timer: &tokio_timer::Timer,
+) -> io::Result<()> {
+    let deadline = Instant::now() + REQUEST_TIMEOUT;
+    let reader = DeadlineReader {
+        stream: stream.try_clone()?,
+        deadline,
+    };
+    let mut reader = BufReader::new(reader.take(MAX_REQUEST_SIZE));
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // The headers are not used, but must be read before responding.
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+    }
+
+    let mut request = request_line.split_whitespace();
+    let (status, body) = match (request.next(), request.next()) {
+        (Some("GET"), Some("/metrics")) => {
+            match get_status_snapshot(command_sender, timer, time_left(deadline)?) {
+                Some(snapshot) => ("200 OK", render(&snapshot)),
+                None => ("503 Service Unavailable", String::new()),
+            }
+        }
+        _ => ("404 Not Found", String::new()),
+    };
+    stream.set_write_timeout(Some(time_left(deadline)?))?;
+    write!(
+        stream,
+        "HTTP/1.0 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )
+}
+
+/// Asks the daemon for its status snapshot. Returns `None` if the daemon doesn't respond within
+/// `timeout`.
+fn get_status_snapshot(
+    command_sender: &DaemonCommandSender,
+    timer: &tokio_timer::Timer,
+    timeout: Duration,
+) -> Option<StatusSnapshot> {
+    let (tx, rx) = oneshot::channel();
+    command_sender
+        .send(DaemonCommand::GetStatusSnapshot(tx))
+        .ok()?;
+    match rx.select2(timer.sleep(timeout)).wait() {
+        Ok(Either::A((snapshot, _))) => Some(snapshot),
+        _ => None,
+    }
+}
+
+/// Reads from a client until `deadline`, after which reads fail with `TimedOut`. A read timeout
+/// alone would let a client that trickles its request in keep the connection forever.
+struct DeadlineReader {
+    stream: TcpStream,
+    deadline: Instant,
+}
+
+impl Read for DeadlineReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.stream
+            .set_read_timeout(Some(time_left(self.deadline)?))?;
+        self.stream.read(buf)
+    }
+}
+
+fn time_left(deadline: Instant) -> io::Result<Duration> {
+    let now = Instant::now();
+    if now < deadline {
+        Ok(deadline - now)
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::TimedOut,
+            "The client ran out of time",
+        ))
+    }
+}
+
+/// Formats a status snapshot in the Prometheus text exposition format. Tunnel traffic metrics are
+/// only present while connected, and the account expiry only once the account data is known.
+pub fn render(snapshot: &StatusSnapshot) -> String {
+    let mut output = String::new();
+
+    let current_state = state_name(&snapshot.state);
+    write_header(
+        &mut output,
+        "mullvad_tunnel_state",
+        "gauge",
+        "Current tunnel state. Only the current state has the value 1.",
+    );
+    for state in TUNNEL_STATES.iter() {
+        let _ = writeln!(
+            output,
+            "mullvad_tunnel_state{{state=\"{}\"}} {}",
+            state,
+            (*state == current_state) as u8
+        );
+    }
+
+    if let Some(stats) = &snapshot.tunnel_stats {
+        write_metric(
+            &mut output,
+            "mullvad_tunnel_rx_bytes_total",
+            "counter",
+            "Bytes received through the tunnel since it connected.",
+            stats.rx_bytes,
+        );
+        write_metric(
+            &mut output,
+            "mullvad_tunnel_tx_bytes_total",
+            "counter",
+            "Bytes sent through the tunnel since it connected.",
+            stats.tx_bytes,
+        );
+        write_metric(
+            &mut output,
+            "mullvad_tunnel_uptime_seconds",
+            "gauge",
+            "Seconds since the tunnel connected.",
+            stats.uptime,
+        );
+    }
+
+    write_metric(
+        &mut output,
+        "mullvad_reconnect_attempts",
+        "gauge",
+        "Reconnection attempts since the tunnel was last connected.",
+        u64::from(snapshot.reconnect_status.attempt),
+    );
+
+    if let Some(expiry) = &snapshot.account_expiry {
+        write_metric(
+            &mut output,
+            "mullvad_account_expiry_seconds",
+            "gauge",
+            "Seconds left until the account expires.",
+            expiry.seconds_left,
+        );
+    }
+
+    output
+}
+
+fn state_name(state: &TunnelState) -> &'static str {
+    match state {
+        TunnelState::Disconnected { .. } => "disconnected",
+        TunnelState::Connecting { .. } => "connecting",
+        TunnelState::Connected { .. } => "connected",
+        TunnelState::Disconnecting(..) => "disconnecting",
+        TunnelState::Error(..) => "error",
+    }
+}
+
+fn write_header(output: &mut String, name: &str, metric_type: &str, help: &str) {
+    let _ = writeln!(output, "# HELP {} {}", name, help);
+    let _ = writeln!(output, "# TYPE {} {}", name, metric_type);
+}
+
+fn write_metric(output: &mut String, name: &str, metric_type: &str, help: &str, value: u64) {
+    write_header(output, name, metric_type, help);
+    let _ = writeln!(output, "{} {}", name, value);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::DaemonCommandChannel;
+    use mullvad_types::states::{ReconnectStatus, TunnelStats};
+    use talpid_types::net::{Endpoint, TransportProtocol, TunnelEndpoint, TunnelType};
+
+    #[test]
+    fn test_render_connected_snapshot() {
+        let state = TunnelState::Connected {
+            endpoint: TunnelEndpoint {
+                endpoint: Endpoint::new([1, 2, 3, 4], 51820, TransportProtocol::Udp),
+                tunnel_type: TunnelType::Wireguard,
+                proxy: None,
+            },
+            location: None,
+            connected_since: None,
+        };
+        let stats = TunnelStats {
+            rx_bytes: 1000,
+            tx_bytes: 2000,
+            uptime: 30,
+            last_handshake: None,
+            mtu: None,
+        };
+        let reconnect_status = ReconnectStatus {
+            attempt: 0,
+            next_attempt: None,
+        };
+        let metrics = render(&StatusSnapshot::new(
+            state,
+            Some(stats),
+            None,
+            reconnect_status,
+        ));
+
+        assert!(metrics.contains("# TYPE mullvad_tunnel_state gauge\n"));
+        assert!(metrics.contains("mullvad_tunnel_state{state=\"connected\"} 1\n"));
+        assert!(metrics.contains("mullvad_tunnel_state{state=\"disconnected\"} 0\n"));
+        assert!(metrics.contains("# TYPE mullvad_tunnel_rx_bytes_total counter\n"));
+        assert!(metrics.contains("mullvad_tunnel_rx_bytes_total 1000\n"));
+        assert!(metrics.contains("mullvad_tunnel_tx_bytes_total 2000\n"));
+        assert!(metrics.contains("mullvad_tunnel_uptime_seconds 30\n"));
+        assert!(metrics.contains("mullvad_reconnect_attempts 0\n"));
+        assert!(!metrics.contains("mullvad_account_expiry_seconds"));
+    }
+
+    #[test]
+    fn test_unanswered_status_snapshot_times_out() {
+        let channel = DaemonCommandChannel::new();
+        let command_sender = channel.sender();
+        // The command is queued, but nothing ever responds to it.
+        let (_, _events) = channel.destructure();
+
+        let timer = tokio_timer::Timer::default();
+        let snapshot = get_status_snapshot(&command_sender, &timer, Duration::from_millis(10));
+        assert!(snapshot.is_none());
+    }
+}
@@ -1,20 +1,26 @@
 //! When changing relay selection, please verify if `docs/relay-selector.md` needs to be
 //! updated as well.
 
+use crate::latency::LatencyMeasurer;
 use chrono::{DateTime, Local};
-use futures01::Future;
+use futures01::{sync::oneshot, Future};
 use mullvad_rpc::{rest::MullvadRestHandle, RelayListProxy};
 use mullvad_types::{
-    endpoint::MullvadEndpoint,
-    location::Location,
+    endpoint::{MullvadEndpoint, RelayEndpoint},
+    location::{CountryCode, Location},
     relay_constraints::{
         BridgeState, Constraint, InternalBridgeConstraints, LocationConstraint, Match,
-        OpenVpnConstraints, RelayConstraints, TunnelProtocol, WireguardConstraints,
+        OpenVpnConstraints, RelayConstraints, RelayFilter, SelectionMode, TunnelProtocol,
+        WireguardConstraints,
+    },
+    relay_list::{
+        CountryRelayCount, OpenVpnEndpointData, Relay, RelayList, RelayTunnels,
+        WireguardEndpointData,
     },
-    relay_list::{OpenVpnEndpointData, Relay, RelayList, RelayTunnels, WireguardEndpointData},
 };
 use parking_lot::Mutex;
 use std::{
+    collections::BTreeMap,
     fs::File,
     io,
     net::{IpAddr, SocketAddr},
@@ -29,11 +35,11 @@ use talpid_types::{
 };
 
 use log::{debug, error, info, warn};
-use rand::{self, rngs::ThreadRng, seq::SliceRandom, Rng};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
 use tokio_timer::{TimeoutError, Timer};
 
 const DATE_TIME_FORMAT_STR: &str = "%Y-%m-%d %H:%M:%S%.3f";
-const RELAYS_FILENAME: &str = "relays.json";
+pub(crate) const RELAYS_FILENAME: &str = "relays.json";
 const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(15);
 /// How often the updater should wake up to check the cache of the in-memory cache of relays.
 /// This check is very cheap. The only reason to not have it very often is because if downloading
@@ -41,6 +47,10 @@ const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(15);
 const UPDATE_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 15);
 /// How old the cached relays need to be to trigger an update
 const UPDATE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// How long to wait for a selected relay to respond to the probe before skipping it.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(1);
+/// Maximum number of relays that are probed for a single selection.
+const MAX_PROBED_RELAYS: usize = 3;
 
 #[derive(err_derive::Error, Debug)]
 #[error(no_from)]
@@ -147,8 +157,10 @@ impl ParsedRelays {
 
 pub struct RelaySelector {
     parsed_relays: Arc<Mutex<ParsedRelays>>,
-    rng: ThreadRng,
+    rng: StdRng,
     updater: RelayListUpdaterHandle,
+    /// Checks that selected relays respond, if set.
+    relay_prober: Option<Arc<dyn LatencyMeasurer>>,
 }
 
 impl RelaySelector {
@@ -185,15 +197,30 @@ impl RelaySelector {
         );
         RelaySelector {
             parsed_relays,
-            rng: rand::thread_rng(),
+            rng: StdRng::from_entropy(),
             updater,
+            relay_prober: None,
         }
     }
 
+    /// Sets what to probe selected relays with before returning them, or `None` to not probe
+    /// them at all.
+    pub fn set_relay_prober(&mut self, relay_prober: Option<Arc<dyn LatencyMeasurer>>) {
+        self.relay_prober = relay_prober;
+    }
+
     /// Download the newest relay list.
     pub fn update(&self) {
         self.updater
-            .send(())
+            .send(None)
+            .expect("Relay list updated thread has stopped unexpectedly");
+    }
+
+    /// Download the newest relay list and report the outcome on `done_tx` once the download
+    /// has finished or failed.
+    pub fn update_and_wait(&self, done_tx: oneshot::Sender<Result<(), ()>>) {
+        self.updater
+            .send(Some(done_tx))
             .expect("Relay list updated thread has stopped unexpectedly");
     }
 
@@ -203,11 +230,45 @@ impl RelaySelector {
         self.parsed_relays.lock().locations().clone()
     }
 
-    /// Returns a random relay and relay endpoint matching the given constraints and with
-    /// preferences applied.
+    /// Returns the names of all countries that have at least one active relay, sorted by
+    /// country code.
+    pub fn get_countries(&mut self) -> BTreeMap<CountryCode, String> {
+        self.parsed_relays.lock().locations().active_countries()
+    }
+
+    /// Returns the number of active relays in each city, grouped by country code.
+    pub fn get_relay_counts(&mut self) -> BTreeMap<CountryCode, CountryRelayCount> {
+        self.parsed_relays.lock().locations().active_relay_counts()
+    }
+
+    /// Returns whether there is at least one active relay in the given location.
+    pub fn has_active_relays(&mut self, location: &Constraint<LocationConstraint>) -> bool {
+        self.parsed_relays
+            .lock()
+            .relays()
+            .iter()
+            .any(|relay| relay.active && Self::relay_matches_location(relay, location))
+    }
+
+    /// Returns every active relay in the given location.
+    pub fn get_active_relays(&mut self, location: &Constraint<LocationConstraint>) -> Vec<Relay> {
+        self.parsed_relays
+            .lock()
+            .relays()
+            .iter()
+            .filter(|relay| relay.active && Self::relay_matches_location(relay, location))
+            .cloned()
+            .collect()
+    }
+
+    /// Returns a relay and relay endpoint matching the given constraints and relay filter, with
+    /// preferences applied. The relay is picked according to `selection_mode`, skipping relays
+    /// that don't respond if a relay prober is set.
     pub fn get_tunnel_endpoint(
         &mut self,
         relay_constraints: &RelayConstraints,
+        relay_filter: &RelayFilter,
+        selection_mode: SelectionMode,
         bridge_state: &BridgeState,
         retry_attempt: u32,
         wg_key_exists: bool,
@@ -218,13 +279,16 @@ impl RelaySelector {
             retry_attempt,
             wg_key_exists,
         );
-        if let Some((relay, endpoint)) = self.get_tunnel_endpoint_internal(&preferred_constraints) {
+        if let Some((relay, endpoint)) =
+            self.get_tunnel_endpoint_internal(&preferred_constraints, relay_filter, selection_mode)
+        {
             debug!(
                 "Relay matched on highest preference for retry attempt {}",
                 retry_attempt
             );
             Ok((relay, endpoint))
-        } else if let Some((relay, endpoint)) = self.get_tunnel_endpoint_internal(relay_constraints)
+        } else if let Some((relay, endpoint)) =
+            self.get_tunnel_endpoint_internal(relay_constraints, relay_filter, selection_mode)
         {
             debug!(
                 "Relay matched on second preference for retry attempt {}",
@@ -232,11 +296,37 @@ impl RelaySelector {
             );
             Ok((relay, endpoint))
         } else {
-            warn!("No relays matching {}", relay_constraints);
+            warn!(
+                "No relays matching {} and {}",
+                relay_constraints, relay_filter
+            );
             Err(Error::NoRelay)
         }
     }
 
+    /// Returns the relay and endpoint that a first connection attempt with the given constraints
+    /// could use, or `None` if no relay matches them. Relays are picked at random, so a real
+    /// connection may use another relay matching the same constraints.
+    pub fn preview_tunnel_endpoint(
+        &mut self,
+        relay_constraints: &RelayConstraints,
+        relay_filter: &RelayFilter,
+        selection_mode: SelectionMode,
+        bridge_state: &BridgeState,
+        wg_key_exists: bool,
+    ) -> Option<RelayEndpoint> {
+        self.get_tunnel_endpoint(
+            relay_constraints,
+            relay_filter,
+            selection_mode,
+            bridge_state,
+            0,
+            wg_key_exists,
+        )
+        .ok()
+        .map(|(relay, endpoint)| RelayEndpoint::new(relay.hostname, &endpoint))
+    }
+
     fn preferred_constraints(
         &self,
         original_constraints: &RelayConstraints,
@@ -325,7 +415,7 @@ impl RelaySelector {
         location: &Location,
         retry_attempt: u32,
     ) -> Option<(ProxySettings, Relay)> {
-        if !self.should_use_bridge(retry_attempt) {
+        if !Self::should_use_bridge(retry_attempt) {
             return None;
         }
 
@@ -337,7 +427,9 @@ impl RelaySelector {
         self.get_proxy_settings(bridge_constraints, location)
     }
 
-    pub fn should_use_bridge(&self, retry_attempt: u32) -> bool {
+    /// Returns whether a bridge should be used for the given attempt when the bridge state is
+    /// `auto`. The first attempts always try a direct connection.
+    pub fn should_use_bridge(retry_attempt: u32) -> bool {
         // shouldn't use a bridge for the first 3 times
         retry_attempt > 3 &&
             // i.e. 4th and 5th with bridge, 6th & 7th without
@@ -443,29 +535,30 @@ impl RelaySelector {
         }
     }
 
-
-    /// Returns a random relay endpoint if any is matching the given constraints.
+    /// Returns a relay endpoint if any is matching the given constraints.
     fn get_tunnel_endpoint_internal(
         &mut self,
         constraints: &RelayConstraints,
+        relay_filter: &RelayFilter,
+        selection_mode: SelectionMode,
     ) -> Option<(Relay, MullvadEndpoint)> {
         let matching_relays: Vec<Relay> = self
             .parsed_relays
             .lock()
             .relays()
             .iter()
-            .filter(|relay| relay.active)
+            .filter(|relay| relay.active && relay_filter.matches(relay))
             .filter_map(|relay| Self::matching_relay(relay, constraints))
             .collect();
 
-        self.pick_random_relay(&matching_relays)
+        self.pick_responsive_relay(matching_relays, selection_mode)
             .and_then(|selected_relay| {
                 info!(
                     "Selected relay {} at {}",
                     selected_relay.hostname, selected_relay.ipv4_addr_in
                 );
                 self.get_random_tunnel(&selected_relay, &constraints)
-                    .map(|endpoint| (selected_relay.clone(), endpoint))
+                    .map(|endpoint| (selected_relay, endpoint))
             })
     }
 
@@ -604,6 +697,63 @@ impl RelaySelector {
 
     /// Pick a random relay from the given slice. Will return `None` if the given slice is empty
     /// or all relays in it has zero weight.
+    fn pick_relay<'a>(
+        &mut self,
+        relays: &'a [Relay],
+        selection_mode: SelectionMode,
+    ) -> Option<&'a Relay> {
+        match selection_mode {
+            SelectionMode::WeightedRandom => self.pick_random_relay(relays),
+            SelectionMode::Random => relays.choose(&mut self.rng),
+            SelectionMode::LeastLoaded => {
+                let least_loaded = relays
+                    .iter()
+                    .filter(|relay| relay.load.is_some())
+                    .min_by_key(|relay| relay.load);
+                if least_loaded.is_none() {
+                    debug!("No relay reports its load, picking a relay by weight instead");
+                    return self.pick_random_relay(relays);
+                }
+                least_loaded
+            }
+        }
+    }
+
+    /// Like `pick_relay`, but if a relay prober is set, picked relays that don't respond to it
+    /// are skipped, up to `MAX_PROBED_RELAYS` times. The first pick is returned if none of the
+    /// probed relays respond, since the probes may be blocked by the firewall rather than the
+    /// relays being down.
+    fn pick_responsive_relay(
+        &mut self,
+        mut relays: Vec<Relay>,
+        selection_mode: SelectionMode,
+    ) -> Option<Relay> {
+        let relay_prober = match &self.relay_prober {
+            Some(relay_prober) => relay_prober.clone(),
+            None => return self.pick_relay(&relays, selection_mode).cloned(),
+        };
+        let mut first_pick = None;
+        for _ in 0..MAX_PROBED_RELAYS {
+            let relay = match self.pick_relay(&relays, selection_mode) {
+                Some(relay) => relay.clone(),
+                None => break,
+            };
+            if relay_prober
+                .measure(IpAddr::V4(relay.ipv4_addr_in), PROBE_TIMEOUT)
+                .is_some()
+            {
+                return Some(relay);
+            }
+            warn!("Relay {} did not respond to the probe", relay.hostname);
+            relays.retain(|candidate| candidate.hostname != relay.hostname);
+            first_pick.get_or_insert(relay);
+        }
+        if first_pick.is_some() {
+            warn!("No probed relay responded, using the first one anyway");
+        }
+        first_pick
+    }
+
     fn pick_random_relay<'a>(&mut self, relays: &'a [Relay]) -> Option<&'a Relay> {
         let total_weight: u64 = relays.iter().map(|relay| relay.weight).sum();
         debug!(
@@ -780,14 +930,18 @@ impl RelaySelector {
     }
 }
 
-type RelayListUpdaterHandle = mpsc::Sender<()>;
+/// Requests an explicit relay list update. The optional sender is notified when the update
+/// has completed.
+type UpdateRequest = Option<oneshot::Sender<Result<(), ()>>>;
+
+type RelayListUpdaterHandle = mpsc::Sender<UpdateRequest>;
 
 struct RelayListUpdater {
     rpc_client: RelayListProxy,
     cache_path: PathBuf,
     parsed_relays: Arc<Mutex<ParsedRelays>>,
     on_update: Box<dyn Fn(&RelayList)>,
-    close_handle: mpsc::Receiver<()>,
+    close_handle: mpsc::Receiver<UpdateRequest>,
 }
 
 impl RelayListUpdater {
@@ -811,7 +965,7 @@ impl RelayListUpdater {
         cache_path: PathBuf,
         parsed_relays: Arc<Mutex<ParsedRelays>>,
         on_update: Box<dyn Fn(&RelayList)>,
-        close_handle: mpsc::Receiver<()>,
+        close_handle: mpsc::Receiver<UpdateRequest>,
     ) -> Self {
         let rpc_client = RelayListProxy::new(rpc_handle);
 
@@ -827,18 +981,28 @@ impl RelayListUpdater {
     fn run(&mut self) {
         debug!("Starting relay list updater thread");
         loop {
-            let should_update = match self.close_handle.recv_timeout(UPDATE_CHECK_INTERVAL) {
-                // Someone sent an explicit update command
-                Ok(()) => true,
-                // Normal timeout, check cache age
-                Err(mpsc::RecvTimeoutError::Timeout) => self.should_update(),
-                // We have been canceled
-                Err(mpsc::RecvTimeoutError::Disconnected) => break,
-            };
+            let (should_update, done_tx) =
+                match self.close_handle.recv_timeout(UPDATE_CHECK_INTERVAL) {
+                    // Someone sent an explicit update command
+                    Ok(done_tx) => (true, done_tx),
+                    // Normal timeout, check cache age
+                    Err(mpsc::RecvTimeoutError::Timeout) => (self.should_update(), None),
+                    // We have been canceled
+                    Err(mpsc::RecvTimeoutError::Disconnected) => break,
+                };
             if should_update {
-                match self.update() {
-                    Ok(()) => info!("Updated list of relays"),
-                    Err(error) => error!("{}", error.display_chain()),
+                let result = match self.update() {
+                    Ok(()) => {
+                        info!("Updated list of relays");
+                        Ok(())
+                    }
+                    Err(error) => {
+                        error!("{}", error.display_chain());
+                        Err(())
+                    }
+                };
+                if let Some(done_tx) = done_tx {
+                    let _ = done_tx.send(result);
                 }
             }
         }
@@ -894,3 +1058,282 @@ impl RelayListUpdater {
         serde_json::to_writer_pretty(io::BufWriter::new(file), relays).map_err(Error::Serialize)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use mullvad_types::relay_list::RelayBridges;
+    use std::net::Ipv4Addr;
+
+    fn openvpn_relay(endpoints: &[(TransportProtocol, u16)]) -> Relay {
+        Relay {
+            hostname: "se-got-001".to_owned(),
+            ipv4_addr_in: Ipv4Addr::new(10, 0, 0, 1),
+            ipv6_addr_in: None,
+            include_in_country: true,
+            active: true,
+            owned: true,
+            provider: "provider".to_owned(),
+            weight: 1,
+            load: None,
+            tunnels: RelayTunnels {
+                openvpn: endpoints
+                    .iter()
+                    .map(|&(protocol, port)| OpenVpnEndpointData { port, protocol })
+                    .collect(),
+                wireguard: vec![],
+            },
+            bridges: RelayBridges::default(),
+            location: None,
+        }
+    }
+
+    #[test]
+    fn test_tcp_443_constraint_narrows_openvpn_endpoints() {
+        let constraints = RelayConstraints {
+            location: Constraint::Any,
+            tunnel_protocol: Constraint::Only(TunnelProtocol::OpenVpn),
+            wireguard_constraints: WireguardConstraints::default(),
+            openvpn_constraints: OpenVpnConstraints {
+                port: Constraint::Only(443),
+                protocol: Constraint::Only(TransportProtocol::Tcp),
+            },
+        };
+
+        let relay = openvpn_relay(&[
+            (TransportProtocol::Udp, 1194),
+            (TransportProtocol::Udp, 443),
+            (TransportProtocol::Tcp, 443),
+            (TransportProtocol::Tcp, 80),
+        ]);
+        let matching = RelaySelector::matching_relay(&relay, &constraints)
+            .expect("Relay with a TCP/443 endpoint was filtered out");
+        assert_eq!(matching.tunnels.openvpn.len(), 1);
+        assert_eq!(matching.tunnels.openvpn[0].port, 443);
+        assert_eq!(matching.tunnels.openvpn[0].protocol, TransportProtocol::Tcp);
+
+        let relay = openvpn_relay(&[(TransportProtocol::Udp, 443), (TransportProtocol::Tcp, 80)]);
+        assert!(RelaySelector::matching_relay(&relay, &constraints).is_none());
+    }
+
+    fn relay_selector(relays: Vec<Relay>) -> RelaySelector {
+        let (updater, _) = mpsc::channel();
+        RelaySelector {
+            parsed_relays: Arc::new(Mutex::new(ParsedRelays {
+                last_updated: SystemTime::now(),
+                locations: RelayList::empty(),
+                relays,
+            })),
+            rng: StdRng::from_entropy(),
+            updater,
+            relay_prober: None,
+        }
+    }
+
+    #[test]
+    fn test_preview_with_unmatched_constraints() {
+        let mut selector = relay_selector(vec![openvpn_relay(&[(TransportProtocol::Udp, 1194)])]);
+        let mut constraints = RelayConstraints {
+            location: Constraint::Any,
+            tunnel_protocol: Constraint::Only(TunnelProtocol::OpenVpn),
+            wireguard_constraints: WireguardConstraints::default(),
+            openvpn_constraints: OpenVpnConstraints {
+                port: Constraint::Any,
+                protocol: Constraint::Any,
+            },
+        };
+
+        let preview = selector
+            .preview_tunnel_endpoint(
+                &constraints,
+                &RelayFilter::default(),
+                SelectionMode::default(),
+                &BridgeState::Off,
+                false,
+            )
+            .expect("No relay matched unrestricted constraints");
+        assert_eq!(preview.hostname, "se-got-001");
+        assert_eq!(preview.endpoint.address.port(), 1194);
+
+        constraints.openvpn_constraints.protocol = Constraint::Only(TransportProtocol::Tcp);
+        assert_eq!(
+            selector.preview_tunnel_endpoint(
+                &constraints,
+                &RelayFilter::default(),
+                SelectionMode::default(),
+                &BridgeState::Off,
+                false
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_owned_only_filter_narrows_candidates() {
+        let relay = |hostname: &str, owned: bool, provider: &str| Relay {
+            hostname: hostname.to_owned(),
+            owned,
+            provider: provider.to_owned(),
+            ..openvpn_relay(&[(TransportProtocol::Udp, 1194)])
+        };
+        let mut selector = relay_selector(vec![
+            relay("se-got-001", true, "31173"),
+            relay("se-got-002", false, "DataPacket"),
+            relay("se-got-003", false, "M247"),
+        ]);
+        let constraints = RelayConstraints {
+            tunnel_protocol: Constraint::Only(TunnelProtocol::OpenVpn),
+            ..RelayConstraints::default()
+        };
+
+        let mut filter = RelayFilter {
+            owned_only: true,
+            ..RelayFilter::default()
+        };
+        for _ in 0..10 {
+            let (relay, _) = selector
+                .get_tunnel_endpoint(
+                    &constraints,
+                    &filter,
+                    SelectionMode::default(),
+                    &BridgeState::Off,
+                    0,
+                    false,
+                )
+                .expect("No owned relay was selected");
+            assert_eq!(relay.hostname, "se-got-001");
+        }
+
+        filter.excluded_providers.insert("31173".to_owned());
+        assert!(selector
+            .get_tunnel_endpoint(
+                &constraints,
+                &filter,
+                SelectionMode::default(),
+                &BridgeState::Off,
+                0,
+                false,
+            )
+            .is_err());
+
+        filter.owned_only = false;
+        for _ in 0..10 {
+            let (relay, _) = selector
+                .get_tunnel_endpoint(
+                    &constraints,
+                    &filter,
+                    SelectionMode::default(),
+                    &BridgeState::Off,
+                    0,
+                    false,
+                )
+                .expect("No relay was selected");
+            assert_ne!(relay.hostname, "se-got-001");
+        }
+    }
+
+    #[test]
+    fn test_selection_modes() {
+        let relays: Vec<Relay> = [(1, Some(60)), (5, Some(20)), (3, None), (1, Some(35))]
+            .iter()
+            .enumerate()
+            .map(|(i, &(weight, load))| Relay {
+                hostname: format!("se-got-00{}", i + 1),
+                weight,
+                load,
+                ..openvpn_relay(&[(TransportProtocol::Udp, 1194)])
+            })
+            .collect();
+
+        let picks = |seed| {
+            let mut selector = relay_selector(vec![]);
+            selector.rng = StdRng::seed_from_u64(seed);
+            (0..10)
+                .map(|_| {
+                    selector
+                        .pick_relay(&relays, SelectionMode::Random)
+                        .unwrap()
+                        .hostname
+                        .clone()
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(picks(1), picks(1));
+
+        let mut selector = relay_selector(vec![]);
+        for _ in 0..10 {
+            let relay = selector
+                .pick_relay(&relays, SelectionMode::LeastLoaded)
+                .unwrap();
+            assert_eq!(relay.hostname, "se-got-002");
+        }
+    }
+
+    /// Only reaches the addresses it was given, and records every probed address.
+    struct StubProber {
+        reachable: Vec<IpAddr>,
+        probed: Mutex<Vec<IpAddr>>,
+    }
+
+    impl LatencyMeasurer for StubProber {
+        fn measure(&self, address: IpAddr, _timeout: Duration) -> Option<Duration> {
+            self.probed.lock().push(address);
+            if self.reachable.contains(&address) {
+                Some(Duration::from_millis(10))
+            } else {
+                None
+            }
+        }
+    }
+
+    #[test]
+    fn test_unresponsive_relay_is_skipped() {
+        let relay = |hostname: &str, address: [u8; 4], load: u8| Relay {
+            hostname: hostname.to_owned(),
+            ipv4_addr_in: Ipv4Addr::from(address),
+            load: Some(load),
+            ..openvpn_relay(&[(TransportProtocol::Udp, 1194)])
+        };
+        let relays = vec![
+            relay("se-got-001", [10, 0, 0, 1], 10),
+            relay("se-got-002", [10, 0, 0, 2], 50),
+        ];
+        let prober = Arc::new(StubProber {
+            reachable: vec![IpAddr::from([10, 0, 0, 2])],
+            probed: Mutex::new(vec![]),
+        });
+        let mut selector = relay_selector(vec![]);
+        selector.set_relay_prober(Some(prober.clone()));
+
+        let relay = selector
+            .pick_responsive_relay(relays.clone(), SelectionMode::LeastLoaded)
+            .unwrap();
+        assert_eq!(relay.hostname, "se-got-002");
+        assert_eq!(
+            *prober.probed.lock(),
+            vec![IpAddr::from([10, 0, 0, 1]), IpAddr::from([10, 0, 0, 2])]
+        );
+
+        // The first pick is used if no relay responds.
+        selector.set_relay_prober(Some(Arc::new(StubProber {
+            reachable: vec![],
+            probed: Mutex::new(vec![]),
+        })));
+        let relay = selector
+            .pick_responsive_relay(relays, SelectionMode::LeastLoaded)
+            .unwrap();
+        assert_eq!(relay.hostname, "se-got-001");
+    }
+
+    #[test]
+    fn test_auto_bridge_only_after_direct_failures() {
+        for retry_attempt in 0..=3 {
+            assert!(!RelaySelector::should_use_bridge(retry_attempt));
+        }
+        assert!(RelaySelector::should_use_bridge(4));
+        assert!(RelaySelector::should_use_bridge(5));
+        assert!(!RelaySelector::should_use_bridge(6));
+        assert!(!RelaySelector::should_use_bridge(7));
+        assert!(RelaySelector::should_use_bridge(8));
+    }
+}
@@ -1,11 +1,16 @@
 use log::{debug, error, info};
 use mullvad_types::{
-    relay_constraints::{BridgeSettings, BridgeState, RelaySettingsUpdate},
-    settings::Settings,
+    relay_constraints::{
+        BridgeSettings, BridgeState, LocationConstraint, OpenVpnConstraints, RelayFilter,
+        RelaySettingsUpdate, SelectionMode,
+    },
+    settings::{CustomRelayError, DnsBlockers, Settings, SettingsPatch},
+    CustomTunnelEndpoint,
 };
 use std::{
     fs::{self, File},
     io,
+    net::{IpAddr, SocketAddr},
     ops::Deref,
     path::{Path, PathBuf},
 };
@@ -29,10 +34,16 @@ pub enum Error {
 
     #[error(display = "Unable to write settings to {}", _0)]
     WriteError(String, #[error(source)] io::Error),
+
+    #[error(display = "Invalid custom relay change")]
+    CustomRelayError(#[error(source)] CustomRelayError),
+
+    #[error(display = "Unable to reload settings from {}", _0)]
+    ReloadError(String, #[error(source)] LoadSettingsError),
 }
 
 #[derive(err_derive::Error, Debug)]
-enum LoadSettingsError {
+pub enum LoadSettingsError {
     #[error(display = "Cannot find settings file")]
     FileNotFound,
 
@@ -59,12 +70,7 @@ impl SettingsPersister {
     pub fn load(settings_dir: &Path) -> Self {
         let path = settings_dir.join(SETTINGS_FILE);
         let (mut settings, mut should_save) = Self::load_settings(&path);
-
-        // Force IPv6 to be enabled on Android
-        if cfg!(target_os = "android") {
-            should_save |=
-                Self::update_field(&mut settings.tunnel_options.generic.enable_ipv6, true);
-        }
+        should_save |= Self::force_platform_settings(&mut settings);
 
         let mut persister = SettingsPersister { settings, path };
 
@@ -80,6 +86,33 @@ impl SettingsPersister {
         persister
     }
 
+    /// Replaces the current settings with the ones in the settings file, so that changes made to
+    /// the file by other programs take effect. Returns whether the settings changed. Unlike
+    /// `load`, the current settings are kept if the file is missing or can't be parsed.
+    pub fn reload(&mut self) -> Result<bool, Error> {
+        let (mut settings, mut should_save) = Self::load_settings_from_file(&self.path)
+            .map_err(|e| Error::ReloadError(self.path.display().to_string(), e))?;
+        should_save |= Self::force_platform_settings(&mut settings);
+
+        let settings_changed = settings != self.settings;
+        self.settings = settings;
+        if should_save {
+            self.save()?;
+        }
+        Ok(settings_changed)
+    }
+
+    /// Changes settings that can't be configured on the current platform. Returns whether any
+    /// setting was changed.
+    fn force_platform_settings(settings: &mut Settings) -> bool {
+        // Force IPv6 to be enabled on Android
+        if cfg!(target_os = "android") {
+            Self::update_field(&mut settings.tunnel_options.generic.enable_ipv6, true)
+        } else {
+            false
+        }
+    }
+
     fn load_settings(path: &Path) -> (Settings, bool) {
         Self::load_settings_from_file(path)
             .or_else(|error| match error {
@@ -161,6 +194,14 @@ impl SettingsPersister {
         })
     }
 
+    /// Restores all settings to their defaults, except for the account token, and saves them.
+    pub fn reset_keeping_account(&mut self) -> Result<(), Error> {
+        let mut settings = Settings::default();
+        settings.set_account_token(self.settings.get_account_token());
+        self.settings = settings;
+        self.save()
+    }
+
     pub fn to_settings(&self) -> Settings {
         self.settings.clone()
     }
@@ -177,6 +218,58 @@ impl SettingsPersister {
         self.update(should_save)
     }
 
+    pub fn add_custom_relay(
+        &mut self,
+        name: String,
+        endpoint: CustomTunnelEndpoint,
+    ) -> Result<(), Error> {
+        self.settings
+            .add_custom_relay(name, endpoint)
+            .map_err(Error::CustomRelayError)?;
+        self.save()
+    }
+
+    pub fn remove_custom_relay(&mut self, name: &str) -> Result<(), Error> {
+        self.settings
+            .remove_custom_relay(name)
+            .map_err(Error::CustomRelayError)?;
+        self.save()
+    }
+
+    pub fn set_active_custom_relay(&mut self, name: &str) -> Result<bool, Error> {
+        let should_save = self
+            .settings
+            .set_active_custom_relay(name)
+            .map_err(Error::CustomRelayError)?;
+        self.update(should_save)
+    }
+
+    pub fn set_location_constraint(
+        &mut self,
+        location: Option<LocationConstraint>,
+    ) -> Result<bool, Error> {
+        let should_save = self.settings.set_location_constraint(location);
+        self.update(should_save)
+    }
+
+    pub fn set_openvpn_constraints(
+        &mut self,
+        constraints: OpenVpnConstraints,
+    ) -> Result<bool, Error> {
+        let should_save = self.settings.set_openvpn_constraints(constraints);
+        self.update(should_save)
+    }
+
+    pub fn add_split_tunnel_app(&mut self, path: PathBuf) -> Result<bool, Error> {
+        let should_save = self.settings.add_split_tunnel_app(path);
+        self.update(should_save)
+    }
+
+    pub fn remove_split_tunnel_app(&mut self, path: &Path) -> Result<bool, Error> {
+        let should_save = self.settings.remove_split_tunnel_app(path);
+        self.update(should_save)
+    }
+
     pub fn set_allow_lan(&mut self, allow_lan: bool) -> Result<bool, Error> {
         let should_save = Self::update_field(&mut self.settings.allow_lan, allow_lan);
         self.update(should_save)
@@ -198,6 +291,11 @@ impl SettingsPersister {
         self.update(should_save)
     }
 
+    pub fn set_connect_timeout(&mut self, connect_timeout: u32) -> Result<bool, Error> {
+        let should_save = Self::update_field(&mut self.settings.connect_timeout, connect_timeout);
+        self.update(should_save)
+    }
+
     pub fn set_openvpn_mssfix(&mut self, openvpn_mssfix: Option<u16>) -> Result<bool, Error> {
         let should_save = Self::update_field(
             &mut self.settings.tunnel_options.openvpn.mssfix,
@@ -206,6 +304,14 @@ impl SettingsPersister {
         self.update(should_save)
     }
 
+    pub fn set_openvpn_cipher(&mut self, openvpn_cipher: Option<String>) -> Result<bool, Error> {
+        let should_save = Self::update_field(
+            &mut self.settings.tunnel_options.openvpn.cipher,
+            openvpn_cipher,
+        );
+        self.update(should_save)
+    }
+
     pub fn set_enable_ipv6(&mut self, enable_ipv6: bool) -> Result<bool, Error> {
         let should_save = Self::update_field(
             &mut self.settings.tunnel_options.generic.enable_ipv6,
@@ -214,6 +320,18 @@ impl SettingsPersister {
         self.update(should_save)
     }
 
+    pub fn set_custom_dns(&mut self, custom_dns: Option<Vec<IpAddr>>) -> Result<bool, Error> {
+        let should_save =
+            Self::update_field(&mut self.settings.tunnel_options.custom_dns, custom_dns);
+        self.update(should_save)
+    }
+
+    pub fn set_dns_blockers(&mut self, dns_blockers: DnsBlockers) -> Result<bool, Error> {
+        let should_save =
+            Self::update_field(&mut self.settings.tunnel_options.dns_blockers, dns_blockers);
+        self.update(should_save)
+    }
+
     pub fn set_wireguard_mtu(&mut self, mtu: Option<u16>) -> Result<bool, Error> {
         let should_save = Self::update_field(&mut self.settings.tunnel_options.wireguard.mtu, mtu);
         self.update(should_save)
@@ -236,16 +354,51 @@ impl SettingsPersister {
         self.update(should_save)
     }
 
+    pub fn set_api_endpoint(&mut self, api_endpoint: Option<SocketAddr>) -> Result<bool, Error> {
+        let should_save = Self::update_field(&mut self.settings.api_endpoint, api_endpoint);
+        self.update(should_save)
+    }
+
     pub fn set_bridge_settings(&mut self, bridge_settings: BridgeSettings) -> Result<bool, Error> {
         let should_save = Self::update_field(&mut self.settings.bridge_settings, bridge_settings);
         self.update(should_save)
     }
 
+    pub fn set_relay_filter(&mut self, relay_filter: RelayFilter) -> Result<bool, Error> {
+        let should_save = Self::update_field(&mut self.settings.relay_filter, relay_filter);
+        self.update(should_save)
+    }
+
+    pub fn set_relay_selection_mode(
+        &mut self,
+        relay_selection_mode: SelectionMode,
+    ) -> Result<bool, Error> {
+        let should_save = Self::update_field(
+            &mut self.settings.relay_selection_mode,
+            relay_selection_mode,
+        );
+        self.update(should_save)
+    }
+
+    pub fn set_probe_before_connect(&mut self, probe_before_connect: bool) -> Result<bool, Error> {
+        let should_save = Self::update_field(
+            &mut self.settings.probe_before_connect,
+            probe_before_connect,
+        );
+        self.update(should_save)
+    }
+
     pub fn set_bridge_state(&mut self, bridge_state: BridgeState) -> Result<bool, Error> {
         let should_save = self.settings.set_bridge_state(bridge_state);
         self.update(should_save)
     }
 
+    /// Applies all fields set in `patch` and saves the settings once.
+    pub fn apply_patch(&mut self, patch: SettingsPatch) -> Result<bool, Error> {
+        let should_save = self.settings.apply_patch(patch);
+        self.update(should_save)
+    }
+
     fn update_field<T: Eq>(field: &mut T, new_value: T) -> bool {
         if *field != new_value {
             *field = new_value;
@@ -322,3 +475,110 @@ mod ffi {
         ) -> WinUtilMigrationStatus;
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_reset_keeping_account_writes_defaults() {
+        let settings_dir = std::env::temp_dir().join(format!(
+            "mullvad-daemon-settings-test-{}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&settings_dir).unwrap();
+
+        let mut persister = SettingsPersister::load(&settings_dir);
+        persister
+            .set_account_token(Some("1234567890".to_owned()))
+            .unwrap();
+        persister
+            .set_allow_lan(!Settings::default().allow_lan)
+            .unwrap();
+        persister.reset_keeping_account().unwrap();
+
+        let reloaded = SettingsPersister::load(&settings_dir);
+        fs::remove_dir_all(&settings_dir).unwrap();
+
+        assert_eq!(reloaded.allow_lan, Settings::default().allow_lan);
+        assert_eq!(reloaded.get_account_token(), Some("1234567890".to_owned()));
+    }
+
+    #[test]
+    fn test_reload_reads_external_changes() {
+        let settings_dir =
+            std::env::temp_dir().join(format!("mullvad-daemon-reload-test-{}", std::process::id()));
+        fs::create_dir_all(&settings_dir).unwrap();
+
+        let mut persister = SettingsPersister::load(&settings_dir);
+        persister.set_allow_lan(false).unwrap();
+        assert!(!persister.reload().unwrap());
+
+        // Another program enables LAN access by editing the settings file
+        let mut edited = persister.to_settings();
+        edited.allow_lan = true;
+        fs::write(
+            settings_dir.join(SETTINGS_FILE),
+            serde_json::to_vec(&edited).unwrap(),
+        )
+        .unwrap();
+        assert!(persister.reload().unwrap());
+        assert!(persister.allow_lan);
+
+        // Files that can't be parsed are rejected without touching the current settings
+        fs::write(settings_dir.join(SETTINGS_FILE), b"{ not json").unwrap();
+        let result = persister.reload();
+        fs::remove_dir_all(&settings_dir).unwrap();
+
+        assert!(result.is_err());
+        assert!(persister.allow_lan);
+    }
+
+    #[test]
+    fn test_to_settings_matches_individual_values() {
+        let settings_dir = std::env::temp_dir().join(format!(
+            "mullvad-daemon-to-settings-test-{}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&settings_dir).unwrap();
+
+        let mut persister = SettingsPersister::load(&settings_dir);
+        persister.set_auto_connect(true).unwrap();
+        persister.set_connect_timeout(30).unwrap();
+        persister.set_openvpn_mssfix(Some(1300)).unwrap();
+        persister.set_enable_ipv6(true).unwrap();
+        persister
+            .set_custom_dns(Some(vec![IpAddr::from([10, 0, 0, 1])]))
+            .unwrap();
+        persister.set_wireguard_mtu(Some(1280)).unwrap();
+        persister.set_bridge_state(BridgeState::On).unwrap();
+        let settings = persister.to_settings();
+        fs::remove_dir_all(&settings_dir).unwrap();
+
+        // These are the values that the individual getters of the daemon respond with.
+        assert_eq!(
+            settings.get_relay_settings(),
+            persister.get_relay_settings()
+        );
+        assert_eq!(settings.auto_connect, persister.auto_connect);
+        assert_eq!(settings.connect_timeout, persister.connect_timeout);
+        assert_eq!(
+            settings.tunnel_options.openvpn.mssfix,
+            persister.tunnel_options.openvpn.mssfix
+        );
+        assert_eq!(
+            settings.tunnel_options.generic.enable_ipv6,
+            persister.tunnel_options.generic.enable_ipv6
+        );
+        assert_eq!(
+            settings.tunnel_options.custom_dns,
+            persister.tunnel_options.custom_dns
+        );
+        assert_eq!(
+            settings.tunnel_options.wireguard.mtu,
+            persister.tunnel_options.wireguard.mtu
+        );
+        assert_eq!(settings.bridge_settings, persister.bridge_settings);
+        assert_eq!(settings.get_bridge_state(), persister.get_bridge_state());
+    }
+}
@@ -19,7 +19,7 @@ use talpid_core::mpsc::Sender;
 use talpid_types::ErrorExt;
 use tokio02::fs::File;
 
-const VERSION_INFO_FILENAME: &str = "version-info.json";
+pub(crate) const VERSION_INFO_FILENAME: &str = "version-info.json";
 
 lazy_static::lazy_static! {
     static ref STABLE_REGEX: Regex = Regex::new(r"^(\d{4})\.(\d+)$").unwrap();
@@ -39,6 +39,9 @@ const UPDATE_INTERVAL: Duration = Duration::from_secs(60 * 60 * 24);
 /// Wait this long until next try if an update failed
 const UPDATE_INTERVAL_ERROR: Duration = Duration::from_secs(60 * 60 * 6);
 
+/// Oldest client version that is able to talk to this daemon.
+const MIN_SUPPORTED_CLIENT_VERSION: AppVersion = AppVersion::Stable(2020, 4);
+
 #[cfg(target_os = "linux")]
 const PLATFORM: &str = "linux";
 #[cfg(target_os = "macos")]
@@ -390,6 +393,27 @@ impl ToString for AppVersion {
     }
 }
 
+/// Returns whether a client of the given version can use this daemon. Clients must be at least
+/// `MIN_SUPPORTED_CLIENT_VERSION` and no newer than the daemon itself, since newer clients may
+/// rely on methods the daemon doesn't have. Development builds only match themselves.
+pub fn is_compatible_client_version(client_version: &str) -> bool {
+    is_compatible_with(client_version, PRODUCT_VERSION)
+}
+
+fn is_compatible_with(client_version: &str, daemon_version: &str) -> bool {
+    let client = match AppVersion::from_str(client_version) {
+        Some(client) => client,
+        None => return client_version == daemon_version,
+    };
+    if client < MIN_SUPPORTED_CLIENT_VERSION {
+        return false;
+    }
+    match AppVersion::from_str(daemon_version) {
+        Some(daemon) => client <= daemon,
+        None => true,
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -421,6 +445,35 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_client_compatibility() {
+        let tests = vec![
+            ("2020.3", "2020.5", false),
+            ("2020.4-beta1", "2020.5", false),
+            ("2020.4", "2020.5", true),
+            ("2020.5-beta2", "2020.5", true),
+            ("2020.5", "2020.5", true),
+            ("2020.6", "2020.5", false),
+            ("2020.5", "2020.5-beta2", false),
+            ("2020.5-beta3", "2020.5-beta2", false),
+            ("2020.6", "2020.5-dev-f16be4", true),
+            ("2020.3", "2020.5-dev-f16be4", false),
+            ("2020.5-dev-f16be4", "2020.5-dev-f16be4", true),
+            ("2020.5-dev-f16be4", "2020.5", false),
+            ("", "2020.5", false),
+        ];
+
+        for (client, daemon, expected_output) in tests {
+            assert_eq!(
+                is_compatible_with(client, daemon),
+                expected_output,
+                "client {}, daemon {}",
+                client,
+                daemon
+            );
+        }
+    }
+
     #[test]
     fn test_version_upgrade_suggestions() {
         let app_version_info = mullvad_rpc::AppVersionResponse {
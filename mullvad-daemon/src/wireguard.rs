@@ -36,6 +36,8 @@ pub enum Error {
     TooManyKeys,
     #[error(display = "Failed to create rotation timer")]
     RotationScheduleError(#[error(source)] tokio_timer::TimerError),
+    #[error(display = "No WireGuard key exists for the current account")]
+    NoKey,
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -138,16 +140,19 @@ impl KeyManager {
         key: talpid_types::net::wireguard::PublicKey,
     ) -> impl Future<Output = Result<bool>> {
         let mut rpc = mullvad_rpc::WireguardKeyProxy::new(self.http_handle.clone());
-        async move {
-            match rpc.get_wireguard_key(account, &key).await {
-                Ok(_) => Ok(true),
-                Err(mullvad_rpc::rest::Error::ApiError(status, _code))
-                    if status == mullvad_rpc::StatusCode::NOT_FOUND =>
-                {
-                    Ok(false)
-                }
-                Err(err) => Err(Self::map_rpc_error(err)),
+        async move { Self::key_validity(rpc.get_wireguard_key(account, &key).await) }
+    }
+
+    /// Interprets the API response to a key lookup. A key the API doesn't know about is invalid.
+    fn key_validity<T>(response: std::result::Result<T, RestError>) -> Result<bool> {
+        match response {
+            Ok(_) => Ok(true),
+            Err(RestError::ApiError(status, _code))
+                if status == mullvad_rpc::StatusCode::NOT_FOUND =>
+            {
+                Ok(false)
             }
+            Err(err) => Err(Self::map_rpc_error(err)),
         }
     }
 
@@ -404,3 +409,26 @@ impl KeyManager {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use mullvad_rpc::StatusCode;
+
+    #[test]
+    fn test_key_validity() {
+        assert!(KeyManager::key_validity(Ok(())).unwrap());
+        assert!(!KeyManager::key_validity::<()>(Err(RestError::ApiError(
+            StatusCode::NOT_FOUND,
+            "PUBKEY_NOT_FOUND".to_owned()
+        )))
+        .unwrap());
+        match KeyManager::key_validity::<()>(Err(RestError::ApiError(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "INTERNAL_ERROR".to_owned(),
+        ))) {
+            Err(Error::RestError(_)) => (),
+            _ => panic!("Expected other API errors to be passed on"),
+        }
+    }
+}
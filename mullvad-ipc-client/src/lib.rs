@@ -4,17 +4,32 @@ use futures::sync::oneshot;
 use jsonrpc_client_core::{Client, ClientHandle, Future};
 use jsonrpc_client_ipc::IpcTransport;
 use mullvad_types::{
-    account::{AccountData, AccountToken, VoucherSubmission},
-    location::GeoIpLocation,
-    relay_constraints::{BridgeSettings, BridgeState, RelaySettings, RelaySettingsUpdate},
-    relay_list::RelayList,
-    settings::{Settings, TunnelOptions},
-    states::TunnelState,
+    account::{AccountData, AccountExpiry, AccountToken, VoucherSubmission},
+    endpoint::RelayEndpoint,
+    location::{CountryCode, GeoIpLocation, PublicIp},
+    logging::{LogLevel, LogRecord},
+    relay_constraints::{
+        BridgeSettings, BridgeState, Constraint, LocationConstraint, OpenVpnConstraints,
+        RelayFilter, RelaySettings, RelaySettingsUpdate, SelectionMode,
+    },
+    relay_list::{CountryRelayCount, RelayLatency, RelayList},
+    settings::{DnsBlockers, Settings, SettingsPatch, TunnelOptions},
+    states::{
+        ConnectionProgress, Connectivity, FirewallState, ReconnectStatus, StateHistoryEntry,
+        StatusSnapshot, TargetState, TunnelState, TunnelStats,
+    },
     version::AppVersionInfo,
-    wireguard, DaemonEvent,
+    wireguard, CustomTunnelEndpoint, DaemonEvent, DaemonEventSubscribeOptions,
 };
 use serde::{Deserialize, Serialize};
-use std::{io, path::Path, thread};
+use std::{
+    collections::BTreeMap,
+    io,
+    net::{IpAddr, SocketAddr},
+    path::{Path, PathBuf},
+    thread,
+};
+use talpid_types::{net::TunnelEndpoint, tunnel::ErrorState};
 
 static NO_ARGS: [u8; 0] = [];
 
@@ -95,18 +110,45 @@ impl DaemonRpcClient {
         self.call("connect", &NO_ARGS)
     }
 
+    pub fn connect_and_wait(&mut self) -> Result<()> {
+        self.call("connect_and_wait", &NO_ARGS)
+    }
+
     pub fn disconnect(&mut self) -> Result<()> {
         self.call("disconnect", &NO_ARGS)
     }
 
+    pub fn set_target_state(&mut self, target_state: TargetState) -> Result<()> {
+        self.call("set_target_state", &[target_state])
+    }
+
     pub fn reconnect(&mut self) -> Result<()> {
         self.call("reconnect", &NO_ARGS)
     }
 
-    pub fn create_new_account(&mut self) -> Result<()> {
+    pub fn connect_to(&mut self, location: Constraint<LocationConstraint>) -> Result<()> {
+        self.call("connect_to", &[location])
+    }
+
+    pub fn connect_once(&mut self, relay: CustomTunnelEndpoint) -> Result<()> {
+        self.call("connect_once", &[relay])
+    }
+
+    pub fn connect_fastest(
+        &mut self,
+        location: Constraint<LocationConstraint>,
+    ) -> Result<RelayLatency> {
+        self.call("connect_fastest", &[location])
+    }
+
+    pub fn create_new_account(&mut self) -> Result<AccountToken> {
         self.call("create_new_account", &NO_ARGS)
     }
 
+    pub fn logout(&mut self) -> Result<()> {
+        self.call("logout", &NO_ARGS)
+    }
+
     pub fn get_account(&mut self) -> Result<Option<AccountToken>> {
         self.call("get_account", &NO_ARGS)
     }
@@ -115,6 +157,10 @@ impl DaemonRpcClient {
         self.call("get_account_data", &[account])
     }
 
+    pub fn get_account_expiry(&mut self) -> Result<Option<AccountExpiry>> {
+        self.call("get_account_expiry", &NO_ARGS)
+    }
+
     pub fn submit_voucher(&mut self, voucher: String) -> Result<VoucherSubmission> {
         self.call("submit_voucher", &[voucher])
     }
@@ -143,22 +189,83 @@ impl DaemonRpcClient {
         self.call("get_auto_connect", &NO_ARGS)
     }
 
+    pub fn set_connect_timeout(&mut self, connect_timeout: u32) -> Result<()> {
+        self.call("set_connect_timeout", &[connect_timeout])
+    }
+
+    pub fn get_connect_timeout(&mut self) -> Result<u32> {
+        self.call("get_connect_timeout", &NO_ARGS)
+    }
+
     pub fn get_current_location(&mut self) -> Result<Option<GeoIpLocation>> {
         self.call("get_current_location", &NO_ARGS)
     }
 
+    pub fn get_public_ip(&mut self) -> Result<Option<PublicIp>> {
+        self.call("get_public_ip", &NO_ARGS)
+    }
+
+    pub fn get_firewall_state(&mut self) -> Result<FirewallState> {
+        self.call("get_firewall_state", &NO_ARGS)
+    }
+
+    pub fn get_tunnel_stats(&mut self) -> Result<Option<TunnelStats>> {
+        self.call("get_tunnel_stats", &NO_ARGS)
+    }
+
+    pub fn get_last_error(&mut self) -> Result<Option<ErrorState>> {
+        self.call("get_last_error", &NO_ARGS)
+    }
+
     pub fn get_current_version(&mut self) -> Result<String> {
         self.call("get_current_version", &NO_ARGS)
     }
 
+    pub fn get_recent_logs(&mut self, max_lines: u32) -> Result<Vec<String>> {
+        self.call("get_recent_logs", &[max_lines])
+    }
+
+    pub fn set_log_level(&mut self, level: LogLevel) -> Result<()> {
+        self.call("set_log_level", &[level])
+    }
+
+    pub fn get_log_level(&mut self) -> Result<Option<LogLevel>> {
+        self.call("get_log_level", &NO_ARGS)
+    }
+
+    pub fn is_compatible(&mut self, client_version: String) -> Result<bool> {
+        self.call("is_compatible", &[client_version])
+    }
+
     pub fn get_relay_locations(&mut self) -> Result<RelayList> {
         self.call("get_relay_locations", &NO_ARGS)
     }
 
+    pub fn get_relay_countries(&mut self) -> Result<BTreeMap<CountryCode, String>> {
+        self.call("get_relay_countries", &NO_ARGS)
+    }
+
+    pub fn get_relay_location_counts(
+        &mut self,
+    ) -> Result<BTreeMap<CountryCode, CountryRelayCount>> {
+        self.call("get_relay_location_counts", &NO_ARGS)
+    }
+
+    pub fn measure_relay_latency(
+        &mut self,
+        location: Constraint<LocationConstraint>,
+    ) -> Result<Vec<RelayLatency>> {
+        self.call("measure_relay_latency", &[location])
+    }
+
     pub fn update_relay_locations(&mut self) -> Result<()> {
         self.call("update_relay_locations", &NO_ARGS)
     }
 
+    pub fn update_relay_list(&mut self) -> Result<()> {
+        self.call("update_relay_list", &NO_ARGS)
+    }
+
     pub fn get_relay_settings(&mut self) -> Result<RelaySettings> {
         self.call("get_relay_settings", &NO_ARGS)
     }
@@ -167,6 +274,30 @@ impl DaemonRpcClient {
         self.call("get_state", &NO_ARGS)
     }
 
+    pub fn get_current_relay(&mut self) -> Result<Option<TunnelEndpoint>> {
+        self.call("get_current_relay", &NO_ARGS)
+    }
+
+    pub fn preview_relay_selection(&mut self) -> Result<Option<RelayEndpoint>> {
+        self.call("preview_relay_selection", &NO_ARGS)
+    }
+
+    pub fn get_connectivity(&mut self) -> Result<Connectivity> {
+        self.call("get_connectivity", &NO_ARGS)
+    }
+
+    pub fn get_reconnect_status(&mut self) -> Result<ReconnectStatus> {
+        self.call("get_reconnect_status", &NO_ARGS)
+    }
+
+    pub fn get_state_history(&mut self, max_entries: u32) -> Result<Vec<StateHistoryEntry>> {
+        self.call("get_state_history", &[max_entries])
+    }
+
+    pub fn get_status_snapshot(&mut self) -> Result<StatusSnapshot> {
+        self.call("get_status_snapshot", &NO_ARGS)
+    }
+
     pub fn get_tunnel_options(&mut self) -> Result<TunnelOptions> {
         self.call("get_tunnel_options", &NO_ARGS)
     }
@@ -195,6 +326,14 @@ impl DaemonRpcClient {
         self.call("set_account", &[account])
     }
 
+    pub fn get_account_history(&mut self) -> Result<Vec<AccountToken>> {
+        self.call("get_account_history", &NO_ARGS)
+    }
+
+    pub fn remove_account_from_history(&mut self, account_token: AccountToken) -> Result<()> {
+        self.call("remove_account_from_history", &[account_token])
+    }
+
     pub fn clear_account_history(&mut self) -> Result<()> {
         self.call("clear_account_history", &NO_ARGS)
     }
@@ -203,6 +342,26 @@ impl DaemonRpcClient {
         self.call("set_enable_ipv6", &[enabled])
     }
 
+    pub fn get_enable_ipv6(&mut self) -> Result<bool> {
+        self.call("get_enable_ipv6", &NO_ARGS)
+    }
+
+    pub fn set_dns(&mut self, servers: Option<Vec<IpAddr>>) -> Result<()> {
+        self.call("set_dns", &[servers])
+    }
+
+    pub fn get_dns(&mut self) -> Result<Option<Vec<IpAddr>>> {
+        self.call("get_dns", &NO_ARGS)
+    }
+
+    pub fn set_dns_content_blockers(&mut self, blockers: DnsBlockers) -> Result<()> {
+        self.call("set_dns_content_blockers", &[blockers])
+    }
+
+    pub fn get_dns_content_blockers(&mut self) -> Result<DnsBlockers> {
+        self.call("get_dns_content_blockers", &NO_ARGS)
+    }
+
     pub fn set_wireguard_mtu(&mut self, mtu: Option<u16>) -> Result<()> {
         self.call("set_wireguard_mtu", &[mtu])
     }
@@ -215,14 +374,58 @@ impl DaemonRpcClient {
         self.call("set_openvpn_mssfix", &[mssfix])
     }
 
+    pub fn get_openvpn_mssfix(&mut self) -> Result<Option<u16>> {
+        self.call("get_openvpn_mssfix", &NO_ARGS)
+    }
+
+    pub fn set_openvpn_cipher(&mut self, cipher: Option<String>) -> Result<()> {
+        self.call("set_openvpn_cipher", &[cipher])
+    }
+
+    pub fn get_openvpn_cipher(&mut self) -> Result<Option<String>> {
+        self.call("get_openvpn_cipher", &NO_ARGS)
+    }
+
     pub fn set_bridge_settings(&mut self, settings: BridgeSettings) -> Result<()> {
         self.call("set_bridge_settings", &[settings])
     }
 
+    pub fn get_bridge_settings(&mut self) -> Result<BridgeSettings> {
+        self.call("get_bridge_settings", &NO_ARGS)
+    }
+
     pub fn set_bridge_state(&mut self, state: BridgeState) -> Result<()> {
         self.call("set_bridge_state", &[state])
     }
 
+    pub fn get_bridge_state(&mut self) -> Result<BridgeState> {
+        self.call("get_bridge_state", &NO_ARGS)
+    }
+
+    pub fn set_relay_filter(&mut self, relay_filter: RelayFilter) -> Result<()> {
+        self.call("set_relay_filter", &[relay_filter])
+    }
+
+    pub fn get_relay_filter(&mut self) -> Result<RelayFilter> {
+        self.call("get_relay_filter", &NO_ARGS)
+    }
+
+    pub fn set_relay_selection_mode(&mut self, mode: SelectionMode) -> Result<()> {
+        self.call("set_relay_selection_mode", &[mode])
+    }
+
+    pub fn get_relay_selection_mode(&mut self) -> Result<SelectionMode> {
+        self.call("get_relay_selection_mode", &NO_ARGS)
+    }
+
+    pub fn set_probe_before_connect(&mut self, probe_before_connect: bool) -> Result<()> {
+        self.call("set_probe_before_connect", &[probe_before_connect])
+    }
+
+    pub fn get_probe_before_connect(&mut self) -> Result<bool> {
+        self.call("get_probe_before_connect", &NO_ARGS)
+    }
+
     pub fn shutdown(&mut self) -> Result<()> {
         self.call("shutdown", &NO_ARGS)
     }
@@ -231,6 +434,26 @@ impl DaemonRpcClient {
         self.call("prepare_restart", &NO_ARGS)
     }
 
+    pub fn reset_settings(&mut self) -> Result<()> {
+        self.call("reset_settings", &NO_ARGS)
+    }
+
+    pub fn reload_settings(&mut self) -> Result<()> {
+        self.call("reload_settings", &NO_ARGS)
+    }
+
+    pub fn clear_cache(&mut self) -> Result<()> {
+        self.call("clear_cache", &NO_ARGS)
+    }
+
+    pub fn set_api_endpoint(&mut self, endpoint: Option<SocketAddr>) -> Result<()> {
+        self.call("set_api_endpoint", &[endpoint])
+    }
+
+    pub fn get_api_endpoint(&mut self) -> Result<Option<SocketAddr>> {
+        self.call("get_api_endpoint", &NO_ARGS)
+    }
+
     pub fn factory_reset(&mut self) -> Result<()> {
         self.call("factory_reset", &NO_ARGS)
     }
@@ -239,6 +462,34 @@ impl DaemonRpcClient {
         self.call("update_relay_settings", &[update])
     }
 
+    pub fn apply_settings(&mut self, patch: SettingsPatch) -> Result<()> {
+        self.call("apply_settings", &[patch])
+    }
+
+    pub fn add_custom_relay(&mut self, name: String, endpoint: CustomTunnelEndpoint) -> Result<()> {
+        self.call("add_custom_relay", &(name, endpoint))
+    }
+
+    pub fn remove_custom_relay(&mut self, name: String) -> Result<()> {
+        self.call("remove_custom_relay", &[name])
+    }
+
+    pub fn list_custom_relays(&mut self) -> Result<BTreeMap<String, CustomTunnelEndpoint>> {
+        self.call("list_custom_relays", &NO_ARGS)
+    }
+
+    pub fn set_active_custom_relay(&mut self, name: String) -> Result<()> {
+        self.call("set_active_custom_relay", &[name])
+    }
+
+    pub fn set_location_constraint(&mut self, location: Option<LocationConstraint>) -> Result<()> {
+        self.call("set_location_constraint", &[location])
+    }
+
+    pub fn set_openvpn_constraints(&mut self, constraints: OpenVpnConstraints) -> Result<()> {
+        self.call("set_openvpn_constraints", &[constraints])
+    }
+
     pub fn get_split_tunnel_processes(&mut self) -> Result<Vec<i32>> {
         self.call("get_split_tunnel_processes", &NO_ARGS)
     }
@@ -255,6 +506,18 @@ impl DaemonRpcClient {
         self.call("clear_split_tunnel_processes", &NO_ARGS)
     }
 
+    pub fn list_split_tunnel_apps(&mut self) -> Result<Vec<PathBuf>> {
+        self.call("list_split_tunnel_apps", &NO_ARGS)
+    }
+
+    pub fn add_split_tunnel_app(&mut self, path: PathBuf) -> Result<()> {
+        self.call("add_split_tunnel_app", &[path])
+    }
+
+    pub fn remove_split_tunnel_app(&mut self, path: PathBuf) -> Result<()> {
+        self.call("remove_split_tunnel_app", &[path])
+    }
+
 
     pub fn call<A, O>(&mut self, method: &'static str, args: &A) -> Result<O>
     where
@@ -278,4 +541,156 @@ impl DaemonRpcClient {
             &NO_ARGS,
         )
     }
+
+    pub fn daemon_event_subscribe_with_state(
+        &mut self,
+    ) -> impl Future<
+        Item = jsonrpc_client_pubsub::Subscription<DaemonEvent>,
+        Error = jsonrpc_client_pubsub::Error,
+    > {
+        self.subscriber.subscribe(
+            "daemon_event_subscribe".to_string(),
+            "daemon_event_unsubscribe".to_string(),
+            "daemon_event".to_string(),
+            0,
+            &[true],
+        )
+    }
+
+    pub fn daemon_event_subscribe_with_options(
+        &mut self,
+        options: DaemonEventSubscribeOptions,
+    ) -> impl Future<
+        Item = jsonrpc_client_pubsub::Subscription<DaemonEvent>,
+        Error = jsonrpc_client_pubsub::Error,
+    > {
+        self.subscriber.subscribe(
+            "daemon_event_subscribe".to_string(),
+            "daemon_event_unsubscribe".to_string(),
+            "daemon_event".to_string(),
+            0,
+            &[options],
+        )
+    }
+
+    pub fn connection_progress_subscribe(
+        &mut self,
+    ) -> impl Future<
+        Item = jsonrpc_client_pubsub::Subscription<ConnectionProgress>,
+        Error = jsonrpc_client_pubsub::Error,
+    > {
+        self.subscriber.subscribe(
+            "connection_progress_subscribe".to_string(),
+            "connection_progress_unsubscribe".to_string(),
+            "connection_progress".to_string(),
+            0,
+            &NO_ARGS,
+        )
+    }
+
+    pub fn connectivity_subscribe(
+        &mut self,
+    ) -> impl Future<
+        Item = jsonrpc_client_pubsub::Subscription<Connectivity>,
+        Error = jsonrpc_client_pubsub::Error,
+    > {
+        self.subscriber.subscribe(
+            "connectivity_subscribe".to_string(),
+            "connectivity_unsubscribe".to_string(),
+            "connectivity".to_string(),
+            0,
+            &NO_ARGS,
+        )
+    }
+
+    pub fn tunnel_stats_subscribe(
+        &mut self,
+    ) -> impl Future<
+        Item = jsonrpc_client_pubsub::Subscription<TunnelStats>,
+        Error = jsonrpc_client_pubsub::Error,
+    > {
+        self.subscriber.subscribe(
+            "tunnel_stats_subscribe".to_string(),
+            "tunnel_stats_unsubscribe".to_string(),
+            "tunnel_stats".to_string(),
+            0,
+            &NO_ARGS,
+        )
+    }
+
+    pub fn relay_list_subscribe(
+        &mut self,
+    ) -> impl Future<
+        Item = jsonrpc_client_pubsub::Subscription<RelayList>,
+        Error = jsonrpc_client_pubsub::Error,
+    > {
+        self.subscriber.subscribe(
+            "relay_list_subscribe".to_string(),
+            "relay_list_unsubscribe".to_string(),
+            "relay_list".to_string(),
+            0,
+            &NO_ARGS,
+        )
+    }
+
+    pub fn account_subscribe(
+        &mut self,
+    ) -> impl Future<
+        Item = jsonrpc_client_pubsub::Subscription<Option<AccountToken>>,
+        Error = jsonrpc_client_pubsub::Error,
+    > {
+        self.subscriber.subscribe(
+            "account_subscribe".to_string(),
+            "account_unsubscribe".to_string(),
+            "account".to_string(),
+            0,
+            &NO_ARGS,
+        )
+    }
+
+    pub fn settings_subscribe(
+        &mut self,
+    ) -> impl Future<
+        Item = jsonrpc_client_pubsub::Subscription<Settings>,
+        Error = jsonrpc_client_pubsub::Error,
+    > {
+        self.subscriber.subscribe(
+            "settings_subscribe".to_string(),
+            "settings_unsubscribe".to_string(),
+            "settings".to_string(),
+            0,
+            &NO_ARGS,
+        )
+    }
+
+    pub fn tunnel_state_subscribe(
+        &mut self,
+    ) -> impl Future<
+        Item = jsonrpc_client_pubsub::Subscription<TunnelState>,
+        Error = jsonrpc_client_pubsub::Error,
+    > {
+        self.subscriber.subscribe(
+            "tunnel_state_subscribe".to_string(),
+            "tunnel_state_unsubscribe".to_string(),
+            "tunnel_state".to_string(),
+            0,
+            &NO_ARGS,
+        )
+    }
+
+    pub fn log_subscribe(
+        &mut self,
+        level: LogLevel,
+    ) -> impl Future<
+        Item = jsonrpc_client_pubsub::Subscription<LogRecord>,
+        Error = jsonrpc_client_pubsub::Error,
+    > {
+        self.subscriber.subscribe(
+            "log_subscribe".to_string(),
+            "log_unsubscribe".to_string(),
+            "log".to_string(),
+            0,
+            &[level],
+        )
+    }
 }
@@ -22,6 +22,9 @@ pub enum Error {
     #[error(display = "Attempt to use daemon command sender before it was configured")]
     NoSender,
 
+    #[error(display = "No account token configured")]
+    NoAccountToken,
+
     #[error(display = "Error performing RPC with the remote API")]
     RpcError(#[error(source)] mullvad_rpc::rest::Error),
 }
@@ -154,9 +157,13 @@ impl DaemonInterface {
     }
 
     pub fn reconnect(&self) -> Result<()> {
-        self.send_command(DaemonCommand::Reconnect)?;
+        let (tx, rx) = oneshot::channel();
 
-        Ok(())
+        self.send_command(DaemonCommand::Reconnect(tx))?;
+
+        rx.wait()
+            .map_err(|_| Error::NoResponse)?
+            .map_err(|()| Error::NoAccountToken)
     }
 
     pub fn get_wireguard_key(&self) -> Result<Option<wireguard::PublicKey>> {
@@ -7,7 +7,12 @@ use jnix::{
 };
 use mullvad_daemon::EventListener;
 use mullvad_types::{
-    relay_list::RelayList, settings::Settings, states::TunnelState, version::AppVersionInfo,
+    account::{AccountExpiry, AccountToken},
+    location::GeoIpLocation,
+    relay_list::RelayList,
+    settings::Settings,
+    states::{ConnectionProgress, Connectivity, TunnelState, TunnelStats},
+    version::AppVersionInfo,
     wireguard::KeygenEvent,
 };
 use std::{sync::mpsc, thread};
@@ -60,9 +65,37 @@ impl EventListener for JniEventListener {
         let _ = self.0.send(Event::RelayList(relay_list));
     }
 
+    fn notify_account(&self, _account_token: Option<AccountToken>) {
+        // The Android app reads the account from the settings, which are broadcast as well.
+    }
+
     fn notify_app_version(&self, app_version_info: AppVersionInfo) {
         let _ = self.0.send(Event::AppVersionInfo(app_version_info));
     }
+
+    fn notify_location(&self, _location: GeoIpLocation) {
+        // The Android app fetches the location itself when the tunnel state changes.
+    }
+
+    fn notify_account_expiry(&self, _account_expiry: AccountExpiry) {
+        // The Android app schedules its own account expiry notifications.
+    }
+
+    fn notify_connection_progress(&self, _progress: ConnectionProgress) {
+        // The Android app derives its connection UI from tunnel state changes.
+    }
+
+    fn notify_tunnel_stats(&self, _tunnel_stats: TunnelStats) {
+        // The Android app doesn't display tunnel stats.
+    }
+
+    fn notify_connectivity(&self, _connectivity: Connectivity) {
+        // The Android app monitors connectivity through the system.
+    }
+
+    fn has_tunnel_stats_subscribers(&self) -> bool {
+        false
+    }
 }
 
 struct JniEventHandler<'env> {
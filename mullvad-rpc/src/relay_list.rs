@@ -285,6 +285,7 @@ fn relay(relay: Relay, location: location::Location) -> relay_list::Relay {
         owned: relay.owned,
         provider: relay.provider,
         weight: relay.weight,
+        load: relay.load,
         tunnels: Default::default(),
         bridges: Default::default(),
         location: Some(location),
@@ -314,6 +315,8 @@ struct Relay {
     provider: String,
     ipv4_addr_in: Ipv4Addr,
     weight: u64,
+    #[serde(default)]
+    load: Option<u8>,
     include_in_country: bool,
 }
 
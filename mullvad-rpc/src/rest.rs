@@ -14,9 +14,10 @@ use std::{
     collections::BTreeMap,
     future::Future,
     mem,
-    net::IpAddr,
+    net::{IpAddr, SocketAddr},
     pin::Pin,
     str::FromStr,
+    sync::{Arc, Mutex},
     task::{Context, Poll},
     time::Duration,
 };
@@ -77,6 +78,8 @@ pub(crate) struct RequestService<C> {
     handle: Handle,
     next_id: u64,
     in_flight_requests: BTreeMap<u64, CancelHandle>,
+    /// Whether the most recently finished request reached the remote host.
+    host_reachable: Arc<Mutex<Option<bool>>>,
 }
 
 impl<C: Connect + Clone + Send + Sync + 'static> RequestService<C> {
@@ -93,6 +96,7 @@ impl<C: Connect + Clone + Send + Sync + 'static> RequestService<C> {
             next_id: 0,
             connector,
             handle,
+            host_reachable: Arc::new(Mutex::new(None)),
         }
     }
 
@@ -101,6 +105,7 @@ impl<C: Connect + Clone + Send + Sync + 'static> RequestService<C> {
         RequestServiceHandle {
             tx: self.command_tx.clone(),
             handle: self.handle.clone(),
+            host_reachable: self.host_reachable.clone(),
         }
     }
 
@@ -114,6 +119,7 @@ impl<C: Connect + Clone + Send + Sync + 'static> RequestService<C> {
                 let id = self.id();
                 let mut tx = self.command_tx.clone();
                 let timeout = request.timeout();
+                let host_reachable = self.host_reachable.clone();
 
                 let (request_future, cancel_handle) = Cancellable::new(
                     self.client
@@ -131,6 +137,10 @@ impl<C: Connect + Clone + Send + Sync + 'static> RequestService<C> {
 
                     let response = flatten_result(flatten_result(response));
 
+                    if let Some(reached_host) = reached_host(&response) {
+                        *host_reachable.lock().unwrap() = Some(reached_host);
+                    }
+
                     if completion_tx.send(response).is_err() {
                         log::trace!(
                             "Failed to send response to caller, caller channel is shut down"
@@ -182,6 +192,7 @@ impl<C: Connect + Clone + Send + Sync + 'static> RequestService<C> {
 pub struct RequestServiceHandle {
     tx: mpsc::Sender<RequestCommand>,
     handle: Handle,
+    host_reachable: Arc<Mutex<Option<bool>>>,
 }
 
 impl RequestServiceHandle {
@@ -194,6 +205,12 @@ impl RequestServiceHandle {
         });
     }
 
+    /// Returns whether the most recently finished request reached the remote host, or `None` if
+    /// no request has finished yet.
+    pub fn last_request_reached_host(&self) -> Option<bool> {
+        *self.host_reachable.lock().unwrap()
+    }
+
     /// Submits a `RestRequest` for exectuion to the request service.
     pub async fn request(&self, request: RestRequest) -> Result<Response> {
         let (completion_tx, completion_rx) = oneshot::channel();
@@ -339,6 +356,8 @@ pub struct ErrorResponse {
 pub struct RequestFactory {
     host: String,
     address: Option<IpAddr>,
+    /// Replaces `address` and the default port. Shared by all clones of the factory.
+    endpoint_override: Arc<Mutex<Option<SocketAddr>>>,
     path_prefix: Option<String>,
     pub timeout: Duration,
 }
@@ -349,11 +368,23 @@ impl RequestFactory {
         Self {
             host,
             address,
+            endpoint_override: Arc::new(Mutex::new(None)),
             path_prefix,
             timeout: DEFAULT_TIMEOUT,
         }
     }
 
+    /// Sends requests created by this factory, and by all of its clones, to `endpoint` instead of
+    /// the default address. The host header is left unchanged. `None` restores the default.
+    pub fn set_endpoint_override(&self, endpoint: Option<SocketAddr>) {
+        *self.endpoint_override.lock().unwrap() = endpoint;
+    }
+
+    /// Returns the address that requests are sent to instead of the default one, if any.
+    pub fn endpoint_override(&self) -> Option<SocketAddr> {
+        *self.endpoint_override.lock().unwrap()
+    }
+
     pub fn request(&self, path: &str, method: Method) -> Result<RestRequest> {
         self.hyper_request(path, method)
             .map(RestRequest::from)
@@ -409,10 +440,14 @@ impl RequestFactory {
     }
 
     fn get_uri(&self, path: &str) -> Result<Uri> {
-        let host: &dyn std::fmt::Display = &self
-            .address
-            .map(|addr| addr.to_string())
-            .unwrap_or_else(|| self.host.clone());
+        let endpoint_override = *self.endpoint_override.lock().unwrap();
+        let host = match endpoint_override {
+            Some(endpoint) => endpoint.to_string(),
+            None => self
+                .address
+                .map(|addr| addr.to_string())
+                .unwrap_or_else(|| self.host.clone()),
+        };
         let prefix = self.path_prefix.as_ref().map(AsRef::as_ref).unwrap_or("");
         let uri = format!("https://{}/{}{}", host, prefix, path);
         hyper::Uri::from_str(&uri).map_err(Error::UriError)
@@ -594,6 +629,16 @@ impl MullvadRestHandle {
     }
 }
 
+/// Returns whether a finished request got a response from the remote host. Requests that were
+/// cancelled tell nothing about the host, so `None` is returned for those.
+fn reached_host(response: &Result<Response>) -> Option<bool> {
+    match response {
+        Ok(_) => Some(true),
+        Err(Error::HyperError(_)) | Err(Error::TimeoutError(_)) => Some(false),
+        Err(_) => None,
+    }
+}
+
 fn flatten_result<T, E>(
     result: std::result::Result<std::result::Result<T, E>, E>,
 ) -> std::result::Result<T, E> {
@@ -602,3 +647,34 @@ fn flatten_result<T, E>(
         Err(err) => Err(err),
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_endpoint_override_is_shared_by_clones() {
+        let factory = RequestFactory::new(
+            "api.mullvad.net".to_owned(),
+            Some("193.138.218.78".parse().unwrap()),
+            Some("app".to_owned()),
+        );
+        let clone = factory.clone();
+        assert_eq!(
+            clone.get_uri("/v1/relays").unwrap(),
+            "https://193.138.218.78/app/v1/relays"
+        );
+
+        factory.set_endpoint_override(Some("10.0.0.1:8443".parse().unwrap()));
+        assert_eq!(
+            clone.get_uri("/v1/relays").unwrap(),
+            "https://10.0.0.1:8443/app/v1/relays"
+        );
+
+        factory.set_endpoint_override(None);
+        assert_eq!(
+            clone.get_uri("/v1/relays").unwrap(),
+            "https://193.138.218.78/app/v1/relays"
+        );
+    }
+}
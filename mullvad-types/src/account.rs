@@ -6,6 +6,45 @@ use serde::{Deserialize, Serialize};
 /// Identifier used to authenticate or identify a Mullvad account.
 pub type AccountToken = String;
 
+const MIN_ACCOUNT_TOKEN_LENGTH: usize = 10;
+const MAX_ACCOUNT_TOKEN_LENGTH: usize = 16;
+
+/// Reasons for why a string is not a valid account token.
+#[derive(err_derive::Error, Debug, Clone, Eq, PartialEq)]
+pub enum AccountTokenError {
+    #[error(display = "The account token is empty")]
+    Empty,
+    #[error(display = "The account token contains an invalid character: {:?}", _0)]
+    InvalidCharacter(char),
+    #[error(
+        display = "The account token must be between {} and {} digits long, not {}",
+        MIN_ACCOUNT_TOKEN_LENGTH,
+        MAX_ACCOUNT_TOKEN_LENGTH,
+        _0
+    )]
+    InvalidLength(usize),
+}
+
+/// Strips whitespace and dashes from an account token entered by a user, and checks that what
+/// remains looks like a Mullvad account number.
+pub fn normalize_account_token(token: &str) -> Result<AccountToken, AccountTokenError> {
+    let normalized: String = token
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .collect();
+
+    if normalized.is_empty() {
+        return Err(AccountTokenError::Empty);
+    }
+    if let Some(c) = normalized.chars().find(|c| !c.is_ascii_digit()) {
+        return Err(AccountTokenError::InvalidCharacter(c));
+    }
+    if !(MIN_ACCOUNT_TOKEN_LENGTH..=MAX_ACCOUNT_TOKEN_LENGTH).contains(&normalized.len()) {
+        return Err(AccountTokenError::InvalidLength(normalized.len()));
+    }
+    Ok(normalized)
+}
+
 /// Account expiration info returned by the API via `/v1/me`.
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
 #[cfg_attr(target_os = "android", derive(IntoJava))]
@@ -15,6 +54,52 @@ pub struct AccountData {
     pub expiry: DateTime<Utc>,
 }
 
+/// The expiry of an account along with the time left until then.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct AccountExpiry {
+    pub expiry: DateTime<Utc>,
+    /// Seconds left until the account expires. Zero if it has already expired.
+    pub seconds_left: u64,
+    pub status: ExpiryStatus,
+}
+
+impl AccountExpiry {
+    pub fn new(expiry: DateTime<Utc>, now: DateTime<Utc>) -> Self {
+        let seconds_left = expiry.signed_duration_since(now).num_seconds().max(0) as u64;
+        AccountExpiry {
+            expiry,
+            seconds_left,
+            status: ExpiryStatus::from_seconds_left(seconds_left),
+        }
+    }
+}
+
+/// How close an account is to running out of time. Frontends are notified whenever an account
+/// moves from one status to another.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExpiryStatus {
+    /// More than three days left.
+    Active,
+    /// Three days or less left.
+    ThreeDaysLeft,
+    /// One day or less left.
+    OneDayLeft,
+    Expired,
+}
+
+impl ExpiryStatus {
+    pub fn from_seconds_left(seconds_left: u64) -> Self {
+        const DAY: u64 = 24 * 60 * 60;
+        match seconds_left {
+            0 => ExpiryStatus::Expired,
+            s if s <= DAY => ExpiryStatus::OneDayLeft,
+            s if s <= 3 * DAY => ExpiryStatus::ThreeDaysLeft,
+            _ => ExpiryStatus::Active,
+        }
+    }
+}
+
 /// Data structure that's returned from successful invocation of the mullvad API's
 /// `/v1/submit-voucher` RPC.
 #[derive(Deserialize, Serialize, Debug)]
@@ -57,3 +142,95 @@ impl VoucherError {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_valid_account_token() {
+        assert_eq!(
+            normalize_account_token("1234567890123456"),
+            Ok("1234567890123456".to_owned())
+        );
+        assert_eq!(
+            normalize_account_token("1234567890"),
+            Ok("1234567890".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_padded_account_token() {
+        assert_eq!(
+            normalize_account_token("  1234 5678 9012 3456\n"),
+            Ok("1234567890123456".to_owned())
+        );
+        assert_eq!(
+            normalize_account_token("1234-5678-9012-3456"),
+            Ok("1234567890123456".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_account_expiry() {
+        let now = Utc::now();
+        let expiry = now + chrono::Duration::days(3);
+        assert_eq!(
+            AccountExpiry::new(expiry, now),
+            AccountExpiry {
+                expiry,
+                seconds_left: 3 * 24 * 60 * 60,
+                status: ExpiryStatus::ThreeDaysLeft,
+            }
+        );
+        assert_eq!(AccountExpiry::new(now, expiry).seconds_left, 0);
+    }
+
+    #[test]
+    fn test_expiry_status_thresholds() {
+        const DAY: u64 = 24 * 60 * 60;
+        assert_eq!(
+            ExpiryStatus::from_seconds_left(30 * DAY),
+            ExpiryStatus::Active
+        );
+        assert_eq!(
+            ExpiryStatus::from_seconds_left(3 * DAY + 1),
+            ExpiryStatus::Active
+        );
+        assert_eq!(
+            ExpiryStatus::from_seconds_left(3 * DAY),
+            ExpiryStatus::ThreeDaysLeft
+        );
+        assert_eq!(
+            ExpiryStatus::from_seconds_left(DAY + 1),
+            ExpiryStatus::ThreeDaysLeft
+        );
+        assert_eq!(
+            ExpiryStatus::from_seconds_left(DAY),
+            ExpiryStatus::OneDayLeft
+        );
+        assert_eq!(ExpiryStatus::from_seconds_left(1), ExpiryStatus::OneDayLeft);
+        assert_eq!(ExpiryStatus::from_seconds_left(0), ExpiryStatus::Expired);
+    }
+
+    #[test]
+    fn test_invalid_account_token() {
+        assert_eq!(normalize_account_token(""), Err(AccountTokenError::Empty));
+        assert_eq!(
+            normalize_account_token(" - "),
+            Err(AccountTokenError::Empty)
+        );
+        assert_eq!(
+            normalize_account_token("1234abcd90123456"),
+            Err(AccountTokenError::InvalidCharacter('a'))
+        );
+        assert_eq!(
+            normalize_account_token("123456"),
+            Err(AccountTokenError::InvalidLength(6))
+        );
+        assert_eq!(
+            normalize_account_token("12345678901234567890"),
+            Err(AccountTokenError::InvalidLength(20))
+        );
+    }
+}
@@ -34,6 +34,30 @@ impl CustomTunnelEndpoint {
         Self { host, config }
     }
 
+    /// Checks that the endpoint could possibly be connected to. Returns a message naming the
+    /// offending field otherwise. The transport protocol needs no checking since OpenVPN
+    /// supports both UDP and TCP, and WireGuard endpoints are always UDP.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.host.is_empty() {
+            return Err("host: must not be empty".to_owned());
+        }
+        if let Ok(ip) = self.host.parse::<IpAddr>() {
+            if ip.is_unspecified() {
+                return Err(format!("host: {} is an unspecified address", ip));
+            }
+            if ip.is_loopback() {
+                return Err(format!("host: {} is a loopback address", ip));
+            }
+            if ip.is_multicast() {
+                return Err(format!("host: {} is a multicast address", ip));
+            }
+        }
+        if self.endpoint().address.port() == 0 {
+            return Err("port: must not be 0".to_owned());
+        }
+        Ok(())
+    }
+
     pub fn endpoint(&self) -> Endpoint {
         match &self.config {
             ConnectionConfig::OpenVpn(config) => config.endpoint,
@@ -129,3 +153,68 @@ impl ConnectionConfig {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::net::Ipv4Addr;
+    use talpid_types::net::TransportProtocol;
+
+    fn openvpn_endpoint(host: &str, port: u16) -> CustomTunnelEndpoint {
+        CustomTunnelEndpoint::new(
+            host.to_owned(),
+            ConnectionConfig::OpenVpn(openvpn::ConnectionConfig::new(
+                Endpoint::new(Ipv4Addr::UNSPECIFIED, port, TransportProtocol::Tcp),
+                "user".to_owned(),
+                "password".to_owned(),
+            )),
+        )
+    }
+
+    #[test]
+    fn test_validate_accepts_valid_endpoints() {
+        assert_eq!(openvpn_endpoint("vpn.example.com", 1194).validate(), Ok(()));
+        assert_eq!(openvpn_endpoint("193.138.218.74", 443).validate(), Ok(()));
+        assert_eq!(openvpn_endpoint("2a03:1b20::1", 1194).validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_validate_rejects_port_zero() {
+        assert_eq!(
+            openvpn_endpoint("vpn.example.com", 0).validate(),
+            Err("port: must not be 0".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_validate_rejects_empty_host() {
+        assert_eq!(
+            openvpn_endpoint("", 1194).validate(),
+            Err("host: must not be empty".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_validate_rejects_unroutable_hosts() {
+        assert_eq!(
+            openvpn_endpoint("0.0.0.0", 1194).validate(),
+            Err("host: 0.0.0.0 is an unspecified address".to_owned())
+        );
+        assert_eq!(
+            openvpn_endpoint("::", 1194).validate(),
+            Err("host: :: is an unspecified address".to_owned())
+        );
+        assert_eq!(
+            openvpn_endpoint("127.0.0.1", 1194).validate(),
+            Err("host: 127.0.0.1 is a loopback address".to_owned())
+        );
+        assert_eq!(
+            openvpn_endpoint("::1", 1194).validate(),
+            Err("host: ::1 is a loopback address".to_owned())
+        );
+        assert_eq!(
+            openvpn_endpoint("224.0.0.1", 1194).validate(),
+            Err("host: 224.0.0.1 is a multicast address".to_owned())
+        );
+    }
+}
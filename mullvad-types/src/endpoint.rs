@@ -3,7 +3,7 @@ use std::{
     fmt,
     net::{Ipv4Addr, Ipv6Addr},
 };
-use talpid_types::net::{wireguard, Endpoint, TransportProtocol};
+use talpid_types::net::{wireguard, Endpoint, TransportProtocol, TunnelType};
 
 use crate::relay_list::{OpenVpnEndpointData, WireguardEndpointData};
 
@@ -31,6 +31,31 @@ impl MullvadEndpoint {
             ),
         }
     }
+
+    pub fn tunnel_type(&self) -> TunnelType {
+        match self {
+            MullvadEndpoint::OpenVpn(_) => TunnelType::OpenVpn,
+            MullvadEndpoint::Wireguard { .. } => TunnelType::Wireguard,
+        }
+    }
+}
+
+/// A relay along with the endpoint on it that a tunnel would be established to.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RelayEndpoint {
+    pub hostname: String,
+    pub endpoint: Endpoint,
+    pub tunnel_type: TunnelType,
+}
+
+impl RelayEndpoint {
+    pub fn new(hostname: String, endpoint: &MullvadEndpoint) -> Self {
+        RelayEndpoint {
+            hostname,
+            endpoint: endpoint.to_endpoint(),
+            tunnel_type: endpoint.tunnel_type(),
+        }
+    }
 }
 /// TunnelEndpointData contains data required to connect to a given tunnel endpoint.
 /// Different endpoint types can require different types of data.
@@ -4,6 +4,7 @@ pub mod account;
 pub mod auth_failed;
 pub mod endpoint;
 pub mod location;
+pub mod logging;
 pub mod relay_constraints;
 pub mod relay_list;
 pub mod settings;
@@ -32,4 +33,20 @@ pub enum DaemonEvent {
 
     /// Key event
     WireguardKey(wireguard::KeygenEvent),
+
+    /// A GeoIP lookup finished after the tunnel connected or disconnected.
+    Location(location::GeoIpLocation),
+
+    /// The account data was refreshed, or the account moved closer to expiring.
+    AccountExpiry(account::AccountExpiry),
+}
+
+/// Options for a subscription to [`DaemonEvent`]s.
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct DaemonEventSubscribeOptions {
+    /// Send the current tunnel state as the first event.
+    pub send_initial_state: bool,
+    /// Skip tunnel error states that are less severe than this.
+    pub min_error_severity: Option<talpid_types::tunnel::ErrorSeverity>,
 }
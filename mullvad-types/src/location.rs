@@ -83,6 +83,11 @@ pub struct GeoIpLocation {
     pub hostname: Option<String>,
     #[cfg_attr(target_os = "android", jnix(skip))]
     pub bridge_hostname: Option<String>,
+    /// Set if the lookup failed and this is the result of an earlier lookup. The location may no
+    /// longer be accurate.
+    #[serde(default)]
+    #[cfg_attr(target_os = "android", jnix(skip))]
+    pub stale: bool,
 }
 
 impl From<AmIMullvad> for GeoIpLocation {
@@ -102,6 +107,39 @@ impl From<AmIMullvad> for GeoIpLocation {
             mullvad_exit_ip: location.mullvad_exit_ip,
             hostname: None,
             bridge_hostname: None,
+            stale: false,
+        }
+    }
+}
+
+/// The public IP addresses of the device, as perceived by the internet.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct PublicIp {
+    pub ipv4: Option<Ipv4Addr>,
+    pub ipv6: Option<Ipv6Addr>,
+    /// Set if the lookup failed and the addresses are from an earlier lookup. They may no longer
+    /// be accurate.
+    #[serde(default)]
+    pub stale: bool,
+}
+
+impl PublicIp {
+    /// Returns all detected addresses, the IPv4 address first.
+    pub fn addresses(&self) -> Vec<IpAddr> {
+        self.ipv4
+            .map(IpAddr::from)
+            .into_iter()
+            .chain(self.ipv6.map(IpAddr::from))
+            .collect()
+    }
+}
+
+impl From<&GeoIpLocation> for PublicIp {
+    fn from(location: &GeoIpLocation) -> PublicIp {
+        PublicIp {
+            ipv4: location.ipv4,
+            ipv6: location.ipv6,
+            stale: location.stale,
         }
     }
 }
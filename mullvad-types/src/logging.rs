@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+
+/// Severity of a log record, ordered from most to least severe.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl From<log::Level> for LogLevel {
+    fn from(level: log::Level) -> Self {
+        match level {
+            log::Level::Error => LogLevel::Error,
+            log::Level::Warn => LogLevel::Warn,
+            log::Level::Info => LogLevel::Info,
+            log::Level::Debug => LogLevel::Debug,
+            log::Level::Trace => LogLevel::Trace,
+        }
+    }
+}
+
+impl From<LogLevel> for log::LevelFilter {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Error => log::LevelFilter::Error,
+            LogLevel::Warn => log::LevelFilter::Warn,
+            LogLevel::Info => log::LevelFilter::Info,
+            LogLevel::Debug => log::LevelFilter::Debug,
+            LogLevel::Trace => log::LevelFilter::Trace,
+        }
+    }
+}
+
+/// A single line of log output from the daemon.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct LogRecord {
+    pub level: LogLevel,
+    /// The module or crate that logged the record.
+    pub target: String,
+    pub message: String,
+}
+
+impl From<&log::Record<'_>> for LogRecord {
+    fn from(record: &log::Record<'_>) -> Self {
+        LogRecord {
+            level: record.level().into(),
+            target: record.target().to_owned(),
+            message: record.args().to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_log_levels_are_ordered_by_severity() {
+        assert!(LogLevel::Error < LogLevel::Warn);
+        assert!(LogLevel::Info < LogLevel::Trace);
+        assert_eq!(LogLevel::from(log::Level::Debug), LogLevel::Debug);
+        assert_eq!(
+            log::LevelFilter::from(LogLevel::Warn),
+            log::LevelFilter::Warn
+        );
+    }
+}
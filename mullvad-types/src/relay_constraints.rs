@@ -3,13 +3,13 @@
 
 use crate::{
     location::{CityCode, CountryCode, Hostname},
-    relay_list::{OpenVpnEndpointData, WireguardEndpointData},
+    relay_list::{OpenVpnEndpointData, Relay, WireguardEndpointData},
     CustomTunnelEndpoint,
 };
 #[cfg(target_os = "android")]
 use jnix::{FromJava, IntoJava};
 use serde::{Deserialize, Serialize};
-use std::fmt;
+use std::{collections::BTreeSet, fmt};
 use talpid_types::net::{openvpn::ProxySettings, TransportProtocol};
 
 
@@ -344,6 +344,93 @@ impl Match<WireguardEndpointData> for WireguardConstraints {
     }
 }
 
+/// Limits the relays that may be selected based on who owns and hosts them. The filter is applied
+/// in addition to the [`RelayConstraints`], so a relay has to match both to be selected.
+#[derive(Debug, Default, Clone, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RelayFilter {
+    /// Only select relays that are owned by Mullvad.
+    pub owned_only: bool,
+    /// Only select relays hosted by one of these providers.
+    pub providers: Constraint<BTreeSet<String>>,
+    /// Never select relays hosted by any of these providers.
+    pub excluded_providers: BTreeSet<String>,
+}
+
+impl fmt::Display for RelayFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(
+            f,
+            "{} relays",
+            if self.owned_only { "owned" } else { "any" }
+        )?;
+        if let Constraint::Only(ref providers) = self.providers {
+            write!(f, " hosted by {}", join_providers(providers))?;
+        }
+        if !self.excluded_providers.is_empty() {
+            write!(
+                f,
+                " not hosted by {}",
+                join_providers(&self.excluded_providers)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn join_providers(providers: &BTreeSet<String>) -> String {
+    providers
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Decides how a relay is picked among all relays that match the [`RelayConstraints`] and the
+/// [`RelayFilter`].
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SelectionMode {
+    /// Pick a random relay, favoring relays with a higher weight.
+    WeightedRandom,
+    /// Pick a random relay, with the same probability for every relay.
+    Random,
+    /// Pick the relay with the lowest load. Relays that don't report their load are skipped, and
+    /// if none of them do, this behaves like `WeightedRandom`.
+    LeastLoaded,
+}
+
+impl Default for SelectionMode {
+    fn default() -> Self {
+        SelectionMode::WeightedRandom
+    }
+}
+
+impl fmt::Display for SelectionMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                SelectionMode::WeightedRandom => "weighted random",
+                SelectionMode::Random => "random",
+                SelectionMode::LeastLoaded => "least loaded",
+            }
+        )
+    }
+}
+
+impl Match<Relay> for RelayFilter {
+    fn matches(&self, relay: &Relay) -> bool {
+        (relay.owned || !self.owned_only)
+            && !self.excluded_providers.contains(&relay.provider)
+            && match self.providers {
+                Constraint::Any => true,
+                Constraint::Only(ref providers) => providers.contains(&relay.provider),
+            }
+    }
+}
+
 
 /// Specifies a specific endpoint or [`BridgeConstraints`] to use when `mullvad-daemon` selects a
 /// bridge server.
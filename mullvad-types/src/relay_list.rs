@@ -6,8 +6,10 @@ use crate::{
 use jnix::IntoJava;
 use serde::{Deserialize, Serialize};
 use std::{
+    collections::BTreeMap,
     fmt,
     net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    time::Duration,
 };
 use talpid_types::net::{
     openvpn::{ProxySettings, ShadowsocksProxySettings},
@@ -30,6 +32,83 @@ impl RelayList {
             countries: Vec::new(),
         }
     }
+
+    /// Returns the names of all countries that have at least one active relay, keyed and sorted
+    /// by country code.
+    pub fn active_countries(&self) -> BTreeMap<CountryCode, String> {
+        self.countries
+            .iter()
+            .filter(|country| {
+                country
+                    .cities
+                    .iter()
+                    .any(|city| city.relays.iter().any(|relay| relay.active))
+            })
+            .map(|country| (country.code.clone(), country.name.clone()))
+            .collect()
+    }
+
+    /// Returns the number of active relays in each city, grouped by country code. Cities and
+    /// countries without any active relays are left out.
+    pub fn active_relay_counts(&self) -> BTreeMap<CountryCode, CountryRelayCount> {
+        self.countries
+            .iter()
+            .filter_map(|country| {
+                let cities: Vec<_> = country
+                    .cities
+                    .iter()
+                    .filter_map(|city| {
+                        let relay_count = city.relays.iter().filter(|relay| relay.active).count();
+                        if relay_count > 0 {
+                            Some(CityRelayCount {
+                                name: city.name.clone(),
+                                code: city.code.clone(),
+                                relay_count,
+                            })
+                        } else {
+                            None
+                        }
+                    })
+                    .collect();
+                if cities.is_empty() {
+                    None
+                } else {
+                    Some((
+                        country.code.clone(),
+                        CountryRelayCount {
+                            name: country.name.clone(),
+                            cities,
+                        },
+                    ))
+                }
+            })
+            .collect()
+    }
+}
+
+/// The cities within a country that have active relays. Used by
+/// [`RelayList::active_relay_counts`].
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+pub struct CountryRelayCount {
+    pub name: String,
+    pub cities: Vec<CityRelayCount>,
+}
+
+/// The number of active relays in a city. Used by [`CountryRelayCount`].
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+pub struct CityRelayCount {
+    pub name: String,
+    pub code: CityCode,
+    pub relay_count: usize,
+}
+
+/// The measured round-trip time to a relay. `latency` is `None` if the relay did not respond
+/// before the measurement timed out.
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+pub struct RelayLatency {
+    pub hostname: String,
+    pub address: IpAddr,
+    pub latency: Option<Duration>,
 }
 
 /// A list of [`RelayListCity`]s within a country. Used by [`RelayList`].
@@ -76,6 +155,10 @@ pub struct Relay {
     pub provider: String,
     #[cfg_attr(target_os = "android", jnix(skip))]
     pub weight: u64,
+    /// How loaded the relay is, in percent, if the API reports it.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    #[cfg_attr(target_os = "android", jnix(skip))]
+    pub load: Option<u8>,
     #[serde(skip_serializing_if = "RelayTunnels::is_empty", default)]
     pub tunnels: RelayTunnels,
     #[serde(skip_serializing_if = "RelayBridges::is_empty", default)]
@@ -193,3 +276,93 @@ impl ShadowsocksEndpointData {
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn relay(hostname: &str, active: bool) -> Relay {
+        Relay {
+            hostname: hostname.to_owned(),
+            ipv4_addr_in: Ipv4Addr::new(10, 0, 0, 1),
+            ipv6_addr_in: None,
+            include_in_country: true,
+            active,
+            owned: true,
+            provider: "provider".to_owned(),
+            weight: 1,
+            load: None,
+            tunnels: RelayTunnels::default(),
+            bridges: RelayBridges::default(),
+            location: None,
+        }
+    }
+
+    fn city(name: &str, code: &str, relays: Vec<Relay>) -> RelayListCity {
+        RelayListCity {
+            name: name.to_owned(),
+            code: code.to_owned(),
+            latitude: 0.0,
+            longitude: 0.0,
+            relays,
+        }
+    }
+
+    fn test_relay_list() -> RelayList {
+        RelayList {
+            countries: vec![
+                RelayListCountry {
+                    name: "Sweden".to_owned(),
+                    code: "se".to_owned(),
+                    cities: vec![
+                        city(
+                            "Gothenburg",
+                            "got",
+                            vec![relay("se-got-001", true), relay("se-got-002", true)],
+                        ),
+                        city("Malmo", "mma", vec![relay("se-mma-001", false)]),
+                    ],
+                },
+                RelayListCountry {
+                    name: "Germany".to_owned(),
+                    code: "de".to_owned(),
+                    cities: vec![city("Berlin", "ber", vec![relay("de-ber-001", true)])],
+                },
+                RelayListCountry {
+                    name: "Norway".to_owned(),
+                    code: "no".to_owned(),
+                    cities: vec![city("Oslo", "osl", vec![relay("no-osl-001", false)])],
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_active_countries() {
+        let countries = test_relay_list().active_countries();
+        let codes: Vec<_> = countries.keys().map(String::as_str).collect();
+        assert_eq!(codes, vec!["de", "se"]);
+        assert_eq!(countries["se"], "Sweden");
+
+        assert!(RelayList::empty().active_countries().is_empty());
+    }
+
+    #[test]
+    fn test_active_relay_counts() {
+        let counts = test_relay_list().active_relay_counts();
+        let codes: Vec<_> = counts.keys().map(String::as_str).collect();
+        assert_eq!(codes, vec!["de", "se"]);
+        assert_eq!(
+            counts["se"],
+            CountryRelayCount {
+                name: "Sweden".to_owned(),
+                cities: vec![CityRelayCount {
+                    name: "Gothenburg".to_owned(),
+                    code: "got".to_owned(),
+                    relay_count: 2,
+                }],
+            }
+        );
+        assert_eq!(counts["de"].cities[0].relay_count, 1);
+    }
+}
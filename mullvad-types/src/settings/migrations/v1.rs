@@ -66,11 +66,18 @@ impl super::SettingsMigration for Migration {
                 relay_settings: migrate_relay_settings(old.relay_settings),
                 bridge_settings: old.bridge_settings,
                 bridge_state: old.bridge_state,
+                relay_filter: Default::default(),
+                relay_selection_mode: Default::default(),
+                probe_before_connect: false,
                 allow_lan: old.allow_lan,
                 block_when_disconnected: old.block_when_disconnected,
                 auto_connect: old.auto_connect,
+                connect_timeout: 0,
                 tunnel_options: old.tunnel_options,
                 show_beta_releases: false,
+                custom_relays: Default::default(),
+                split_tunnel_apps: Default::default(),
+                api_endpoint: None,
                 settings_version: super::SettingsVersion::V2,
             }),
             VersionedSettings::V2(new) => VersionedSettings::V2(new),
@@ -1,12 +1,22 @@
-use crate::relay_constraints::{
-    BridgeConstraints, BridgeSettings, BridgeState, Constraint, LocationConstraint,
-    RelayConstraints, RelaySettings, RelaySettingsUpdate,
+use crate::{
+    relay_constraints::{
+        BridgeConstraints, BridgeSettings, BridgeState, Constraint, LocationConstraint,
+        OpenVpnConstraints, RelayConstraints, RelayConstraintsUpdate, RelayFilter, RelaySettings,
+        RelaySettingsUpdate, SelectionMode,
+    },
+    CustomTunnelEndpoint,
 };
 #[cfg(target_os = "android")]
 use jnix::IntoJava;
 use log::{debug, info};
 use serde::{Deserialize, Serialize};
 use serde_json;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    net::{IpAddr, SocketAddr},
+    path::{Path, PathBuf},
+    time::Duration,
+};
 use talpid_types::net::{openvpn, wireguard, GenericTunnelOptions};
 
 mod migrations;
@@ -23,6 +33,22 @@ pub enum Error {
     NoMatchingVersion,
 }
 
+/// Name of the entry in [`Settings::get_custom_relays`] that holds the custom relay last set
+/// through [`Settings::update_relay_settings`].
+pub const DEFAULT_CUSTOM_RELAY_NAME: &str = "default";
+
+/// Reasons for why a change to the named custom relays was rejected.
+#[derive(err_derive::Error, Debug, Clone, Eq, PartialEq)]
+pub enum CustomRelayError {
+    #[error(display = "The custom relay name is empty")]
+    EmptyName,
+
+    #[error(display = "A custom relay named {:?} already exists", _0)]
+    NameTaken(String),
+
+    #[error(display = "There is no custom relay named {:?}", _0)]
+    UnknownName(String),
+}
 
 /// Mullvad daemon settings.
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
@@ -36,6 +62,16 @@ pub struct Settings {
     pub bridge_settings: BridgeSettings,
     #[cfg_attr(target_os = "android", jnix(skip))]
     bridge_state: BridgeState,
+    /// Limits relay selection to relays with a certain owner or hosting provider.
+    #[cfg_attr(target_os = "android", jnix(skip))]
+    pub relay_filter: RelayFilter,
+    /// How to pick among the relays that match the relay constraints and filter.
+    #[cfg_attr(target_os = "android", jnix(skip))]
+    pub relay_selection_mode: SelectionMode,
+    /// Whether to check that a selected relay responds before connecting to it, and select
+    /// another relay if it doesn't.
+    #[cfg_attr(target_os = "android", jnix(skip))]
+    pub probe_before_connect: bool,
     /// If the daemon should allow communication with private (LAN) networks.
     pub allow_lan: bool,
     /// Extra level of kill switch. When this setting is on, the disconnected state will block
@@ -44,12 +80,25 @@ pub struct Settings {
     pub block_when_disconnected: bool,
     /// If the daemon should connect the VPN tunnel directly on start or not.
     pub auto_connect: bool,
+    /// Number of seconds a connection attempt may take before it is aborted. Zero means that
+    /// the daemon keeps trying indefinitely.
+    #[cfg_attr(target_os = "android", jnix(skip))]
+    pub connect_timeout: u32,
     /// Options that should be applied to tunnels of a specific type regardless of where the relays
     /// might be located.
     pub tunnel_options: TunnelOptions,
     /// Whether to notify users of beta updates.
     #[serde(deserialize_with = "deserialize_show_beta_releases")]
     pub show_beta_releases: bool,
+    /// Custom tunnel endpoints stored by name, so that users can switch between them.
+    #[cfg_attr(target_os = "android", jnix(skip))]
+    custom_relays: BTreeMap<String, CustomTunnelEndpoint>,
+    /// Absolute paths of applications whose traffic should bypass the tunnel.
+    #[cfg_attr(target_os = "android", jnix(skip))]
+    split_tunnel_apps: BTreeSet<PathBuf>,
+    /// Address of an API server to use instead of the default one. Meant for testing.
+    #[cfg_attr(target_os = "android", jnix(skip))]
+    pub api_endpoint: Option<SocketAddr>,
     /// Specifies settings schema version
     #[cfg_attr(target_os = "android", jnix(skip))]
     settings_version: migrations::SettingsVersion,
@@ -67,11 +116,18 @@ impl Default for Settings {
                 location: Constraint::Any,
             }),
             bridge_state: BridgeState::Auto,
+            relay_filter: RelayFilter::default(),
+            relay_selection_mode: SelectionMode::default(),
+            probe_before_connect: false,
             allow_lan: false,
             block_when_disconnected: false,
             auto_connect: false,
+            connect_timeout: 0,
             tunnel_options: TunnelOptions::default(),
             show_beta_releases: false,
+            custom_relays: BTreeMap::new(),
+            split_tunnel_apps: BTreeSet::new(),
+            api_endpoint: None,
             settings_version: migrations::SettingsVersion::V2,
         }
     }
@@ -116,7 +172,20 @@ impl Settings {
         self.relay_settings.clone()
     }
 
+    /// Updates the relay settings. A custom tunnel endpoint set this way is also stored as the
+    /// custom relay named [`DEFAULT_CUSTOM_RELAY_NAME`].
     pub fn update_relay_settings(&mut self, update: RelaySettingsUpdate) -> bool {
+        let mut default_relay_changed = false;
+        if let RelaySettingsUpdate::CustomTunnelEndpoint(endpoint) = &update {
+            let old_endpoint = self
+                .custom_relays
+                .insert(DEFAULT_CUSTOM_RELAY_NAME.to_owned(), endpoint.clone());
+            default_relay_changed = old_endpoint.as_ref() != Some(endpoint);
+        }
+        self.apply_relay_settings_update(update) || default_relay_changed
+    }
+
+    fn apply_relay_settings_update(&mut self, update: RelaySettingsUpdate) -> bool {
         let update_supports_bridge = update.supports_bridge();
         let new_settings = self.relay_settings.merge(update);
         if self.relay_settings != new_settings {
@@ -135,6 +204,91 @@ impl Settings {
         }
     }
 
+    pub fn get_custom_relays(&self) -> &BTreeMap<String, CustomTunnelEndpoint> {
+        &self.custom_relays
+    }
+
+    /// Stores a custom relay under a new name.
+    pub fn add_custom_relay(
+        &mut self,
+        name: String,
+        endpoint: CustomTunnelEndpoint,
+    ) -> std::result::Result<(), CustomRelayError> {
+        if name.is_empty() {
+            return Err(CustomRelayError::EmptyName);
+        }
+        if self.custom_relays.contains_key(&name) {
+            return Err(CustomRelayError::NameTaken(name));
+        }
+        self.custom_relays.insert(name, endpoint);
+        Ok(())
+    }
+
+    /// Removes a named custom relay. The relay settings are left as they are, even if the relay
+    /// is the one currently in use.
+    pub fn remove_custom_relay(&mut self, name: &str) -> std::result::Result<(), CustomRelayError> {
+        self.custom_relays
+            .remove(name)
+            .map(|_| ())
+            .ok_or_else(|| CustomRelayError::UnknownName(name.to_owned()))
+    }
+
+    /// Makes the relay settings use the named custom relay. The boolean in the result indicates
+    /// whether the relay settings changed.
+    pub fn set_active_custom_relay(
+        &mut self,
+        name: &str,
+    ) -> std::result::Result<bool, CustomRelayError> {
+        let endpoint = self
+            .custom_relays
+            .get(name)
+            .cloned()
+            .ok_or_else(|| CustomRelayError::UnknownName(name.to_owned()))?;
+        Ok(self.apply_relay_settings_update(RelaySettingsUpdate::CustomTunnelEndpoint(endpoint)))
+    }
+
+    /// Makes the daemon pick relays matching the given location, or any relay if `None` is
+    /// given. Stored custom relays are kept, but stop being used. Returns whether the relay
+    /// settings changed.
+    pub fn set_location_constraint(&mut self, location: Option<LocationConstraint>) -> bool {
+        let location = location.map(Constraint::Only).unwrap_or(Constraint::Any);
+        self.apply_relay_settings_update(RelaySettingsUpdate::Normal(RelayConstraintsUpdate {
+            location: Some(location),
+            ..Default::default()
+        }))
+    }
+
+    /// Limits the OpenVPN endpoints the daemon may pick to the given transport protocol and port.
+    /// Returns whether the relay settings changed.
+    pub fn set_openvpn_constraints(&mut self, constraints: OpenVpnConstraints) -> bool {
+        self.apply_relay_settings_update(RelaySettingsUpdate::Normal(RelayConstraintsUpdate {
+            openvpn_constraints: Some(constraints),
+            ..Default::default()
+        }))
+    }
+
+    /// Returns how long a connection attempt may take, or `None` if there is no limit.
+    pub fn get_connect_timeout(&self) -> Option<Duration> {
+        match self.connect_timeout {
+            0 => None,
+            seconds => Some(Duration::from_secs(u64::from(seconds))),
+        }
+    }
+
+    pub fn get_split_tunnel_apps(&self) -> &BTreeSet<PathBuf> {
+        &self.split_tunnel_apps
+    }
+
+    /// Excludes an application from the tunnel. Returns whether the list changed.
+    pub fn add_split_tunnel_app(&mut self, path: PathBuf) -> bool {
+        self.split_tunnel_apps.insert(path)
+    }
+
+    /// Stops excluding an application from the tunnel. Returns whether the list changed.
+    pub fn remove_split_tunnel_app(&mut self, path: &Path) -> bool {
+        self.split_tunnel_apps.remove(path)
+    }
+
     pub fn get_bridge_state(&self) -> &BridgeState {
         &self.bridge_state
     }
@@ -150,6 +304,97 @@ impl Settings {
             false
         }
     }
+
+    /// Applies all fields that are set in `patch`, in the same way as the individual setters
+    /// would. Returns whether any setting changed.
+    pub fn apply_patch(&mut self, patch: SettingsPatch) -> bool {
+        let mut changed = false;
+        if let Some(update) = patch.relay_settings {
+            changed |= self.update_relay_settings(update);
+        }
+        if let Some(bridge_settings) = patch.bridge_settings {
+            changed |= update_field(&mut self.bridge_settings, bridge_settings);
+        }
+        if let Some(bridge_state) = patch.bridge_state {
+            changed |= self.set_bridge_state(bridge_state);
+        }
+        if let Some(allow_lan) = patch.allow_lan {
+            changed |= update_field(&mut self.allow_lan, allow_lan);
+        }
+        if let Some(block_when_disconnected) = patch.block_when_disconnected {
+            changed |= update_field(&mut self.block_when_disconnected, block_when_disconnected);
+        }
+        if let Some(auto_connect) = patch.auto_connect {
+            changed |= update_field(&mut self.auto_connect, auto_connect);
+        }
+        if let Some(enable_ipv6) = patch.enable_ipv6 {
+            changed |= update_field(&mut self.tunnel_options.generic.enable_ipv6, enable_ipv6);
+        }
+        if let Some(custom_dns) = patch.custom_dns {
+            changed |= update_field(&mut self.tunnel_options.custom_dns, custom_dns);
+        }
+        if let Some(mtu) = patch.wireguard_mtu {
+            changed |= update_field(&mut self.tunnel_options.wireguard.mtu, mtu);
+        }
+        if let Some(mssfix) = patch.openvpn_mssfix {
+            changed |= update_field(&mut self.tunnel_options.openvpn.mssfix, mssfix);
+        }
+        changed
+    }
+}
+
+fn update_field<T: PartialEq>(field: &mut T, new_value: T) -> bool {
+    if *field != new_value {
+        *field = new_value;
+        true
+    } else {
+        false
+    }
+}
+
+/// A partial update of the settings, so that several settings can be changed at once. Only the
+/// fields that are set are changed. For the nullable settings, `null` resets the setting while a
+/// missing field leaves it untouched.
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SettingsPatch {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub relay_settings: Option<RelaySettingsUpdate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bridge_settings: Option<BridgeSettings>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bridge_state: Option<BridgeState>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_lan: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_when_disconnected: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auto_connect: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enable_ipv6: Option<bool>,
+    #[serde(
+        deserialize_with = "deserialize_present",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub custom_dns: Option<Option<Vec<IpAddr>>>,
+    #[serde(
+        deserialize_with = "deserialize_present",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub wireguard_mtu: Option<Option<u16>>,
+    #[serde(
+        deserialize_with = "deserialize_present",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub openvpn_mssfix: Option<Option<u16>>,
+}
+
+/// Used to deserialize the nullable fields of `SettingsPatch`, so that a field that is present
+/// but `null` is told apart from a missing field.
+fn deserialize_present<'de, T: Deserialize<'de>, D: serde::de::Deserializer<'de>>(
+    field: D,
+) -> std::result::Result<Option<T>, D::Error> {
+    T::deserialize(field).map(Some)
 }
 
 /// TunnelOptions holds configuration data that applies to all kinds of tunnels.
@@ -166,6 +411,58 @@ pub struct TunnelOptions {
     /// Contains generic tunnel options that may apply to more than a single tunnel type.
     #[cfg_attr(target_os = "android", jnix(skip))]
     pub generic: GenericTunnelOptions,
+    /// DNS servers to use inside the tunnel. `None` means the default Mullvad DNS servers.
+    #[cfg_attr(target_os = "android", jnix(skip))]
+    pub custom_dns: Option<Vec<IpAddr>>,
+    /// Categories of domains blocked by the Mullvad DNS servers. Ignored while `custom_dns` is
+    /// set.
+    #[cfg_attr(target_os = "android", jnix(skip))]
+    pub dns_blockers: DnsBlockers,
+}
+
+impl TunnelOptions {
+    /// Returns the DNS servers to use inside the tunnel, or `None` for the default Mullvad DNS
+    /// servers. Custom DNS servers take precedence over content blockers.
+    pub fn dns_servers(&self) -> Option<Vec<IpAddr>> {
+        self.custom_dns
+            .clone()
+            .or_else(|| self.dns_blockers.resolver().map(|resolver| vec![resolver]))
+    }
+}
+
+/// Categories of domains that Mullvad's DNS servers can block.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DnsBlockers {
+    pub ads: bool,
+    pub trackers: bool,
+    pub malware: bool,
+    pub adult: bool,
+    pub gambling: bool,
+}
+
+impl DnsBlockers {
+    /// Returns the Mullvad DNS server that blocks the enabled categories, or `None` if no
+    /// category is blocked. Each category is a bit in the last octet of the server address.
+    pub fn resolver(&self) -> Option<IpAddr> {
+        let categories = [
+            self.ads,
+            self.trackers,
+            self.malware,
+            self.adult,
+            self.gambling,
+        ];
+        let last_octet = categories
+            .iter()
+            .enumerate()
+            .filter(|&(_, &blocked)| blocked)
+            .fold(0u8, |octet, (bit, _)| octet | (1 << bit));
+        if last_octet == 0 {
+            None
+        } else {
+            Some(IpAddr::from([100, 64, 0, last_octet]))
+        }
+    }
 }
 
 impl Default for TunnelOptions {
@@ -180,6 +477,8 @@ impl Default for TunnelOptions {
                 // Enable IPv6 be default on Android
                 enable_ipv6: cfg!(target_os = "android"),
             },
+            custom_dns: None,
+            dns_blockers: DnsBlockers::default(),
         }
     }
 }
@@ -195,6 +494,224 @@ fn deserialize_show_beta_releases<'de, D: serde::de::Deserializer<'de>>(
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::ConnectionConfig;
+    use std::net::Ipv4Addr;
+    use talpid_types::net::{Endpoint, TransportProtocol};
+
+    fn custom_relay(host: &str) -> CustomTunnelEndpoint {
+        CustomTunnelEndpoint::new(
+            host.to_owned(),
+            ConnectionConfig::OpenVpn(openvpn::ConnectionConfig::new(
+                Endpoint::new(Ipv4Addr::new(10, 0, 0, 1), 1194, TransportProtocol::Udp),
+                "user".to_owned(),
+                "password".to_owned(),
+            )),
+        )
+    }
+
+    #[test]
+    fn test_named_custom_relays() {
+        let mut settings = Settings::default();
+        settings
+            .add_custom_relay("home".to_owned(), custom_relay("home.example.com"))
+            .unwrap();
+        settings
+            .add_custom_relay("work".to_owned(), custom_relay("work.example.com"))
+            .unwrap();
+        assert_eq!(
+            settings.add_custom_relay("home".to_owned(), custom_relay("other.example.com")),
+            Err(CustomRelayError::NameTaken("home".to_owned()))
+        );
+        assert_eq!(
+            settings.add_custom_relay(String::new(), custom_relay("other.example.com")),
+            Err(CustomRelayError::EmptyName)
+        );
+
+        assert_eq!(settings.set_active_custom_relay("work"), Ok(true));
+        assert_eq!(settings.set_active_custom_relay("work"), Ok(false));
+        assert_eq!(
+            settings.get_relay_settings(),
+            RelaySettings::CustomTunnelEndpoint(custom_relay("work.example.com"))
+        );
+
+        assert_eq!(settings.remove_custom_relay("home"), Ok(()));
+        assert_eq!(
+            settings.remove_custom_relay("home"),
+            Err(CustomRelayError::UnknownName("home".to_owned()))
+        );
+        assert_eq!(
+            settings.set_active_custom_relay("home"),
+            Err(CustomRelayError::UnknownName("home".to_owned()))
+        );
+        let names: Vec<_> = settings.get_custom_relays().keys().collect();
+        assert_eq!(names, vec!["work"]);
+    }
+
+    #[test]
+    fn test_relay_settings_modes() {
+        let mut settings = Settings::default();
+        match settings.get_relay_settings() {
+            RelaySettings::Normal(_) => (),
+            RelaySettings::CustomTunnelEndpoint(_) => panic!("Expected normal relay settings"),
+        }
+
+        let relay = custom_relay("vpn.example.com");
+        settings.update_relay_settings(RelaySettingsUpdate::CustomTunnelEndpoint(relay.clone()));
+        assert_eq!(
+            settings.get_relay_settings(),
+            RelaySettings::CustomTunnelEndpoint(relay)
+        );
+
+        let location = Constraint::Only(LocationConstraint::Country("de".to_owned()));
+        settings.update_relay_settings(RelaySettingsUpdate::Normal(RelayConstraintsUpdate {
+            location: Some(location.clone()),
+            ..Default::default()
+        }));
+        match settings.get_relay_settings() {
+            RelaySettings::Normal(constraints) => assert_eq!(constraints.location, location),
+            RelaySettings::CustomTunnelEndpoint(_) => panic!("Expected normal relay settings"),
+        }
+    }
+
+    #[test]
+    fn test_unnamed_custom_relay_is_default_entry() {
+        let mut settings = Settings::default();
+        let relay = custom_relay("vpn.example.com");
+        assert!(settings
+            .update_relay_settings(RelaySettingsUpdate::CustomTunnelEndpoint(relay.clone())));
+        assert_eq!(
+            settings.get_custom_relays().get(DEFAULT_CUSTOM_RELAY_NAME),
+            Some(&relay)
+        );
+    }
+
+    #[test]
+    fn test_location_constraint_replaces_custom_relay() {
+        let mut settings = Settings::default();
+        settings.update_relay_settings(RelaySettingsUpdate::CustomTunnelEndpoint(custom_relay(
+            "vpn.example.com",
+        )));
+
+        let city = LocationConstraint::City("se".to_owned(), "got".to_owned());
+        assert!(settings.set_location_constraint(Some(city.clone())));
+        match settings.get_relay_settings() {
+            RelaySettings::Normal(constraints) => {
+                assert_eq!(constraints.location, Constraint::Only(city))
+            }
+            RelaySettings::CustomTunnelEndpoint(_) => panic!("Expected normal relay settings"),
+        }
+
+        // The custom relay is still stored and can be removed without touching the constraint.
+        assert!(settings
+            .remove_custom_relay(DEFAULT_CUSTOM_RELAY_NAME)
+            .is_ok());
+        assert!(settings.set_location_constraint(None));
+        match settings.get_relay_settings() {
+            RelaySettings::Normal(constraints) => assert_eq!(constraints.location, Constraint::Any),
+            RelaySettings::CustomTunnelEndpoint(_) => panic!("Expected normal relay settings"),
+        }
+    }
+
+    #[test]
+    fn test_custom_bridge_settings_round_trip() {
+        let mut settings = Settings::default();
+        settings.bridge_settings = BridgeSettings::Custom(openvpn::ProxySettings::Remote(
+            openvpn::RemoteProxySettings {
+                address: "10.0.0.1:1080".parse().unwrap(),
+                auth: Some(openvpn::ProxyAuth {
+                    username: "user".to_owned(),
+                    password: "secret".to_owned(),
+                }),
+            },
+        ));
+        assert!(settings.set_bridge_state(BridgeState::On));
+
+        let loaded = Settings::load_from_bytes(&serde_json::to_vec(&settings).unwrap()).unwrap();
+        assert_eq!(loaded, settings);
+    }
+
+    #[test]
+    fn test_dns_blocker_resolver() {
+        assert_eq!(DnsBlockers::default().resolver(), None);
+        let blockers = DnsBlockers {
+            ads: true,
+            malware: true,
+            ..DnsBlockers::default()
+        };
+        assert_eq!(blockers.resolver(), Some(IpAddr::from([100, 64, 0, 5])));
+        let blockers = DnsBlockers {
+            ads: true,
+            trackers: true,
+            malware: true,
+            adult: true,
+            gambling: true,
+        };
+        assert_eq!(blockers.resolver(), Some(IpAddr::from([100, 64, 0, 31])));
+    }
+
+    #[test]
+    fn test_custom_dns_overrides_dns_blockers() {
+        let mut options = TunnelOptions::default();
+        assert_eq!(options.dns_servers(), None);
+
+        options.dns_blockers.trackers = true;
+        assert_eq!(
+            options.dns_servers(),
+            Some(vec![IpAddr::from([100, 64, 0, 2])])
+        );
+
+        let custom_dns = vec![IpAddr::from([10, 0, 0, 1])];
+        options.custom_dns = Some(custom_dns.clone());
+        assert_eq!(options.dns_servers(), Some(custom_dns));
+
+        options.custom_dns = None;
+        assert_eq!(
+            options.dns_servers(),
+            Some(vec![IpAddr::from([100, 64, 0, 2])])
+        );
+    }
+
+    #[test]
+    fn test_apply_patch_only_changes_present_fields() {
+        let mut settings = Settings::default();
+        settings.tunnel_options.wireguard.mtu = Some(1380);
+        let patch: SettingsPatch =
+            serde_json::from_str(r#"{ "allow_lan": true, "wireguard_mtu": null }"#).unwrap();
+        assert!(patch.custom_dns.is_none());
+
+        let mut expected = settings.clone();
+        expected.allow_lan = true;
+        expected.tunnel_options.wireguard.mtu = None;
+
+        assert!(settings.apply_patch(patch));
+        assert_eq!(settings, expected);
+        assert!(!settings.apply_patch(SettingsPatch {
+            allow_lan: Some(true),
+            ..SettingsPatch::default()
+        }));
+    }
+
+    #[test]
+    fn test_split_tunnel_apps_round_trip() {
+        let mut settings = Settings::default();
+        let app = PathBuf::from("/usr/bin/firefox");
+
+        assert!(settings.add_split_tunnel_app(app.clone()));
+        assert!(!settings.add_split_tunnel_app(app.clone()));
+        assert!(settings.add_split_tunnel_app(PathBuf::from("/usr/bin/curl")));
+
+        let mut settings =
+            Settings::load_from_bytes(&serde_json::to_vec(&settings).unwrap()).unwrap();
+        assert_eq!(settings.get_split_tunnel_apps().len(), 2);
+        assert!(settings.get_split_tunnel_apps().contains(&app));
+
+        assert!(settings.remove_split_tunnel_app(&app));
+        assert!(!settings.remove_split_tunnel_app(&app));
+        assert_eq!(
+            settings.get_split_tunnel_apps().iter().collect::<Vec<_>>(),
+            vec![&PathBuf::from("/usr/bin/curl")]
+        );
+    }
 
     #[test]
     fn test_deserialization_of_2020_4_format() {
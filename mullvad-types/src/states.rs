@@ -1,4 +1,5 @@
-use crate::location::GeoIpLocation;
+use crate::{account::AccountExpiry, location::GeoIpLocation};
+use chrono::{offset::Utc, DateTime};
 #[cfg(target_os = "android")]
 use jnix::IntoJava;
 use serde::{Deserialize, Serialize};
@@ -24,7 +25,12 @@ pub enum TargetState {
 #[cfg_attr(target_os = "android", derive(IntoJava))]
 #[cfg_attr(target_os = "android", jnix(package = "net.mullvad.mullvadvpn.model"))]
 pub enum TunnelState {
-    Disconnected,
+    Disconnected {
+        /// Whether all traffic is blocked while disconnected, as requested by the
+        /// block-when-disconnected setting.
+        #[cfg_attr(target_os = "android", jnix(skip))]
+        blocking: bool,
+    },
     Connecting {
         endpoint: TunnelEndpoint,
         location: Option<GeoIpLocation>,
@@ -32,6 +38,10 @@ pub enum TunnelState {
     Connected {
         endpoint: TunnelEndpoint,
         location: Option<GeoIpLocation>,
+        /// When the tunnel was established. Absent when talking to older daemons.
+        #[serde(default)]
+        #[cfg_attr(target_os = "android", jnix(skip))]
+        connected_since: Option<DateTime<Utc>>,
     },
     Disconnecting(ActionAfterDisconnect),
     Error(ErrorState),
@@ -45,4 +55,496 @@ impl TunnelState {
             _ => false,
         }
     }
+
+    /// Returns the endpoint of the tunnel if it is connected, including the protocol and port
+    /// that are actually in use.
+    pub fn connected_endpoint(&self) -> Option<TunnelEndpoint> {
+        match self {
+            TunnelState::Connected { endpoint, .. } => Some(*endpoint),
+            _ => None,
+        }
+    }
+}
+
+/// What the firewall currently lets through. This can differ from the tunnel state, since
+/// traffic can be blocked while disconnected.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FirewallState {
+    /// All traffic is allowed.
+    Allowed,
+    /// Only traffic through the tunnel is allowed, plus local network traffic if `allow_lan`
+    /// is set. IPv6 is only routed through the tunnel if `enable_ipv6` is set, and is blocked
+    /// otherwise.
+    TunnelOnly { allow_lan: bool, enable_ipv6: bool },
+    /// All traffic is blocked, except what is needed to establish a tunnel and local network
+    /// traffic if `allow_lan` is set.
+    Blocked { allow_lan: bool },
+}
+
+impl FirewallState {
+    /// Returns the firewall state the tunnel state machine enforces in the given state, given
+    /// the current allow LAN and enable IPv6 settings.
+    pub fn from_tunnel_state(
+        tunnel_state: &TunnelState,
+        allow_lan: bool,
+        enable_ipv6: bool,
+    ) -> Self {
+        match tunnel_state {
+            TunnelState::Disconnected { blocking: false } => FirewallState::Allowed,
+            TunnelState::Disconnected { blocking: true } => FirewallState::Blocked { allow_lan },
+            TunnelState::Connecting { .. } | TunnelState::Disconnecting(_) => {
+                FirewallState::Blocked { allow_lan }
+            }
+            TunnelState::Connected { .. } => FirewallState::TunnelOnly {
+                allow_lan,
+                enable_ipv6,
+            },
+            TunnelState::Error(error_state) => {
+                if error_state.is_blocking() {
+                    FirewallState::Blocked { allow_lan }
+                } else {
+                    FirewallState::Allowed
+                }
+            }
+        }
+    }
+}
+
+/// Traffic statistics for the connected tunnel.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TunnelStats {
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+    /// Seconds since the tunnel was established.
+    pub uptime: u64,
+    /// When the last handshake with the relay completed.
+    pub last_handshake: Option<DateTime<Utc>>,
+    /// MTU of the tunnel interface, if known.
+    pub mtu: Option<u16>,
+}
+
+impl TunnelStats {
+    /// Combines traffic counters read from the tunnel with the tunnel state. Returns `None`
+    /// unless the tunnel is connected.
+    pub fn new(
+        tunnel_state: &TunnelState,
+        rx_bytes: u64,
+        tx_bytes: u64,
+        last_handshake: Option<DateTime<Utc>>,
+    ) -> Option<Self> {
+        match tunnel_state {
+            TunnelState::Connected {
+                connected_since, ..
+            } => {
+                let uptime = connected_since
+                    .map(|since| (Utc::now() - since).num_seconds().max(0) as u64)
+                    .unwrap_or(0);
+                Some(TunnelStats {
+                    rx_bytes,
+                    tx_bytes,
+                    uptime,
+                    last_handshake,
+                    mtu: None,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Progress of the automatic reconnection that the daemon performs when the tunnel fails while
+/// the target state is `Secured`.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ReconnectStatus {
+    /// Number of reconnection attempts scheduled since the tunnel was last connected.
+    pub attempt: u32,
+    /// When the next attempt is made, if one is scheduled.
+    pub next_attempt: Option<DateTime<Utc>>,
+}
+
+/// A tunnel state that the daemon entered, along with when it entered it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateHistoryEntry {
+    pub timestamp: DateTime<Utc>,
+    pub state: TunnelState,
+}
+
+/// Network connectivity of the host, as seen by the daemon.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Connectivity {
+    /// The host has no network connectivity.
+    Offline,
+    /// The host is online, but the last request to the API did not reach it.
+    ApiUnreachable,
+    /// The host is online and the last request to the API reached it, or no request has been
+    /// made yet.
+    Online,
+}
+
+impl Connectivity {
+    /// Combines the offline state of the host with whether the last API request reached the API,
+    /// if any request has been made.
+    pub fn new(is_offline: bool, api_reachable: Option<bool>) -> Self {
+        match (is_offline, api_reachable) {
+            (true, _) => Connectivity::Offline,
+            (false, Some(false)) => Connectivity::ApiUnreachable,
+            (false, _) => Connectivity::Online,
+        }
+    }
+
+    /// Returns false only if the host is offline.
+    pub fn is_online(&self) -> bool {
+        *self != Connectivity::Offline
+    }
+}
+
+/// The status of the daemon gathered in one place, for monitoring tools that poll it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatusSnapshot {
+    pub state: TunnelState,
+    /// The relay the tunnel is connected to, if connected.
+    pub relay: Option<TunnelEndpoint>,
+    /// Traffic statistics and uptime of the tunnel, if connected.
+    pub tunnel_stats: Option<TunnelStats>,
+    /// Expiry of the current account, if its account data has been fetched.
+    pub account_expiry: Option<AccountExpiry>,
+    pub reconnect_status: ReconnectStatus,
+}
+
+impl StatusSnapshot {
+    /// Creates a snapshot of the given state. Tunnel statistics are dropped unless the tunnel is
+    /// connected, so that they always agree with the state.
+    pub fn new(
+        state: TunnelState,
+        tunnel_stats: Option<TunnelStats>,
+        account_expiry: Option<AccountExpiry>,
+        reconnect_status: ReconnectStatus,
+    ) -> Self {
+        let relay = state.connected_endpoint();
+        StatusSnapshot {
+            tunnel_stats: relay.and(tunnel_stats),
+            relay,
+            state,
+            account_expiry,
+            reconnect_status,
+        }
+    }
+}
+
+/// Phases the daemon goes through while establishing a tunnel. `Established`, `Failed` and
+/// `Cancelled` end a connection attempt.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionProgress {
+    /// Picking a relay and generating the tunnel parameters for it.
+    SelectingRelay,
+    /// Starting the tunnel to the selected relay and waiting for it to come up.
+    EstablishingTunnel,
+    Established,
+    Failed,
+    Cancelled,
+}
+
+impl ConnectionProgress {
+    /// Returns the phase reached by moving from the `previous` to the `new` tunnel state, if
+    /// the transition is part of a connection attempt.
+    pub fn from_transition(previous: &TunnelState, new: &TunnelState) -> Option<Self> {
+        use TunnelState::*;
+        let was_connecting = match previous {
+            Connecting { .. } => true,
+            _ => false,
+        };
+        match new {
+            Connecting { .. } => Some(ConnectionProgress::EstablishingTunnel),
+            Connected { .. } => Some(ConnectionProgress::Established),
+            Error(_) if was_connecting => Some(ConnectionProgress::Failed),
+            Disconnecting(ActionAfterDisconnect::Reconnect) => None,
+            Disconnecting(_) | Disconnected { .. } if was_connecting => {
+                Some(ConnectionProgress::Cancelled)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use talpid_types::{
+        net::{Endpoint, TransportProtocol, TunnelType},
+        tunnel::ErrorStateCause,
+    };
+
+    fn tunnel_endpoint() -> TunnelEndpoint {
+        TunnelEndpoint {
+            endpoint: Endpoint::new([1, 2, 3, 4], 1194, TransportProtocol::Udp),
+            tunnel_type: TunnelType::OpenVpn,
+            proxy: None,
+        }
+    }
+
+    fn connecting_state() -> TunnelState {
+        TunnelState::Connecting {
+            endpoint: tunnel_endpoint(),
+            location: None,
+        }
+    }
+
+    #[test]
+    fn test_firewall_state_from_tunnel_state() {
+        for &allow_lan in &[false, true] {
+            let cases = vec![
+                (
+                    TunnelState::Disconnected { blocking: false },
+                    FirewallState::Allowed,
+                ),
+                (
+                    TunnelState::Disconnected { blocking: true },
+                    FirewallState::Blocked { allow_lan },
+                ),
+                (connecting_state(), FirewallState::Blocked { allow_lan }),
+                (
+                    TunnelState::Connected {
+                        endpoint: tunnel_endpoint(),
+                        location: None,
+                        connected_since: None,
+                    },
+                    FirewallState::TunnelOnly {
+                        allow_lan,
+                        enable_ipv6: false,
+                    },
+                ),
+                (
+                    TunnelState::Disconnecting(ActionAfterDisconnect::Nothing),
+                    FirewallState::Blocked { allow_lan },
+                ),
+                (
+                    TunnelState::Error(ErrorState::new(ErrorStateCause::IsOffline, true)),
+                    FirewallState::Blocked { allow_lan },
+                ),
+                (
+                    TunnelState::Error(ErrorState::new(ErrorStateCause::IsOffline, false)),
+                    FirewallState::Allowed,
+                ),
+            ];
+            for (tunnel_state, expected) in cases {
+                assert_eq!(
+                    FirewallState::from_tunnel_state(&tunnel_state, allow_lan, false),
+                    expected
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_firewall_state_reports_ipv6_when_connected() {
+        let connected = TunnelState::Connected {
+            endpoint: tunnel_endpoint(),
+            location: None,
+            connected_since: None,
+        };
+        for &enable_ipv6 in &[false, true] {
+            assert_eq!(
+                FirewallState::from_tunnel_state(&connected, true, enable_ipv6),
+                FirewallState::TunnelOnly {
+                    allow_lan: true,
+                    enable_ipv6,
+                }
+            );
+            assert_eq!(
+                FirewallState::from_tunnel_state(&connecting_state(), true, enable_ipv6),
+                FirewallState::Blocked { allow_lan: true }
+            );
+        }
+    }
+
+    #[test]
+    fn test_connected_endpoint() {
+        assert_eq!(
+            TunnelState::Disconnected { blocking: false }.connected_endpoint(),
+            None
+        );
+        assert_eq!(connecting_state().connected_endpoint(), None);
+
+        let connected = TunnelState::Connected {
+            endpoint: tunnel_endpoint(),
+            location: None,
+            connected_since: None,
+        };
+        assert_eq!(connected.connected_endpoint(), Some(tunnel_endpoint()));
+    }
+
+    #[test]
+    fn test_tunnel_stats_require_connected_tunnel() {
+        assert_eq!(
+            TunnelStats::new(&TunnelState::Disconnected { blocking: false }, 1, 2, None),
+            None
+        );
+        assert_eq!(TunnelStats::new(&connecting_state(), 1, 2, None), None);
+
+        let connected = TunnelState::Connected {
+            endpoint: tunnel_endpoint(),
+            location: None,
+            connected_since: Some(Utc::now() - chrono::Duration::seconds(90)),
+        };
+        let stats = TunnelStats::new(&connected, 1, 2, None).unwrap();
+        assert_eq!(stats.rx_bytes, 1);
+        assert_eq!(stats.tx_bytes, 2);
+        assert!(stats.uptime >= 90);
+    }
+
+    #[test]
+    fn test_status_snapshot_agrees_with_state() {
+        let stats = TunnelStats {
+            rx_bytes: 1,
+            tx_bytes: 2,
+            uptime: 3,
+            last_handshake: None,
+            mtu: None,
+        };
+        let reconnect_status = ReconnectStatus {
+            attempt: 0,
+            next_attempt: None,
+        };
+
+        let snapshot = StatusSnapshot::new(
+            connecting_state(),
+            Some(stats.clone()),
+            None,
+            reconnect_status.clone(),
+        );
+        assert_eq!(snapshot.relay, None);
+        assert_eq!(snapshot.tunnel_stats, None);
+
+        let connected = TunnelState::Connected {
+            endpoint: tunnel_endpoint(),
+            location: None,
+            connected_since: None,
+        };
+        let snapshot = StatusSnapshot::new(
+            connected.clone(),
+            Some(stats.clone()),
+            None,
+            reconnect_status.clone(),
+        );
+        assert_eq!(snapshot.relay, connected.connected_endpoint());
+        assert_eq!(snapshot.tunnel_stats, Some(stats));
+        assert_eq!(snapshot.reconnect_status, reconnect_status);
+    }
+
+    #[test]
+    fn test_connectivity_from_offline_state() {
+        for &api_reachable in &[None, Some(false), Some(true)] {
+            assert_eq!(
+                Connectivity::new(true, api_reachable),
+                Connectivity::Offline
+            );
+        }
+        assert_eq!(Connectivity::new(false, None), Connectivity::Online);
+        assert_eq!(
+            Connectivity::new(false, Some(false)),
+            Connectivity::ApiUnreachable
+        );
+        assert_eq!(Connectivity::new(false, Some(true)), Connectivity::Online);
+        assert!(!Connectivity::Offline.is_online());
+        assert!(Connectivity::ApiUnreachable.is_online());
+    }
+
+    #[test]
+    fn test_connection_progress_from_transition() {
+        let connecting = connecting_state();
+        let error = TunnelState::Error(ErrorState::new(ErrorStateCause::IsOffline, true));
+
+        assert_eq!(
+            ConnectionProgress::from_transition(
+                &TunnelState::Disconnected { blocking: false },
+                &connecting
+            ),
+            Some(ConnectionProgress::EstablishingTunnel)
+        );
+        assert_eq!(
+            ConnectionProgress::from_transition(&connecting, &error),
+            Some(ConnectionProgress::Failed)
+        );
+        assert_eq!(
+            ConnectionProgress::from_transition(
+                &connecting,
+                &TunnelState::Disconnecting(ActionAfterDisconnect::Nothing)
+            ),
+            Some(ConnectionProgress::Cancelled)
+        );
+        assert_eq!(
+            ConnectionProgress::from_transition(
+                &connecting,
+                &TunnelState::Disconnecting(ActionAfterDisconnect::Reconnect)
+            ),
+            None
+        );
+        assert_eq!(
+            ConnectionProgress::from_transition(
+                &TunnelState::Disconnected { blocking: false },
+                &error
+            ),
+            None
+        );
+        assert_eq!(
+            ConnectionProgress::from_transition(
+                &TunnelState::Disconnecting(ActionAfterDisconnect::Nothing),
+                &TunnelState::Disconnected { blocking: false }
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_deserialize_connected_without_timestamp() {
+        let mut json = serde_json::to_value(&TunnelState::Connected {
+            endpoint: tunnel_endpoint(),
+            location: None,
+            connected_since: Some(Utc::now()),
+        })
+        .unwrap();
+        json["details"]
+            .as_object_mut()
+            .unwrap()
+            .remove("connected_since");
+        match serde_json::from_value(json).unwrap() {
+            TunnelState::Connected {
+                connected_since, ..
+            } => assert_eq!(connected_since, None),
+            state => panic!("Unexpected state: {:?}", state),
+        }
+    }
+
+    #[test]
+    fn test_connected_timestamp_roundtrip() {
+        let connected_since: DateTime<Utc> = "2020-01-01T12:00:00Z".parse().unwrap();
+        let state = TunnelState::Connected {
+            endpoint: tunnel_endpoint(),
+            location: None,
+            connected_since: Some(connected_since),
+        };
+        let serialized = serde_json::to_value(&state).unwrap();
+        assert_eq!(
+            serialized["details"]["connected_since"],
+            "2020-01-01T12:00:00Z"
+        );
+        match serde_json::from_value(serialized).unwrap() {
+            TunnelState::Connected {
+                connected_since: deserialized,
+                ..
+            } => assert_eq!(deserialized, Some(connected_since)),
+            state => panic!("Unexpected state: {:?}", state),
+        }
+    }
+
+    #[test]
+    fn test_disconnected_reports_blocking() {
+        let state = TunnelState::Disconnected { blocking: true };
+        let serialized = serde_json::to_value(&state).unwrap();
+        assert_eq!(serialized["state"], "disconnected");
+        assert_eq!(serialized["details"]["blocking"], true);
+    }
 }
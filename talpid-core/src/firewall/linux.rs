@@ -456,11 +456,12 @@ impl<'a> PolicyBatch<'a> {
             FirewallPolicy::Connected {
                 peer_endpoint,
                 tunnel,
+                dns_servers,
                 allow_lan,
             } => {
                 self.add_allow_endpoint_rules(peer_endpoint);
-                self.add_allow_dns_rules(tunnel, TransportProtocol::Udp)?;
-                self.add_allow_dns_rules(tunnel, TransportProtocol::Tcp)?;
+                self.add_allow_dns_rules(tunnel, dns_servers, TransportProtocol::Udp)?;
+                self.add_allow_dns_rules(tunnel, dns_servers, TransportProtocol::Tcp)?;
                 // Important to block DNS *before* we allow the tunnel and allow LAN. So DNS
                 // can't leak to the wrong IPs in the tunnel or on the LAN.
                 self.add_drop_dns_rule();
@@ -540,13 +541,13 @@ impl<'a> PolicyBatch<'a> {
     fn add_allow_dns_rules(
         &mut self,
         tunnel: &tunnel::TunnelMetadata,
+        dns_servers: &[IpAddr],
         protocol: TransportProtocol,
     ) -> Result<()> {
-        // allow DNS traffic to the tunnel gateway(s)
-        self.add_allow_dns_rule(&tunnel.interface, protocol, tunnel.ipv4_gateway.into())?;
-        if let Some(ipv6_gateway) = tunnel.ipv6_gateway {
-            self.add_allow_dns_rule(&tunnel.interface, protocol, ipv6_gateway.into())?;
-        };
+        // allow DNS traffic to the DNS servers in the tunnel
+        for server in dns_servers {
+            self.add_allow_dns_rule(&tunnel.interface, protocol, *server)?;
+        }
         Ok(())
     }
 
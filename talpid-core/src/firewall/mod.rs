@@ -105,6 +105,9 @@ pub enum FirewallPolicy {
         peer_endpoint: Endpoint,
         /// Metadata about the tunnel and tunnel interface.
         tunnel: crate::tunnel::TunnelMetadata,
+        /// DNS servers that should be reachable through the tunnel. Ignored on Windows, where
+        /// only the tunnel gateway is allowed.
+        dns_servers: Vec<IpAddr>,
         /// Flag setting if communication with LAN networks should be possible.
         allow_lan: bool,
         /// A process that is allowed to send packets to the relay.
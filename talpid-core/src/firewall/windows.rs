@@ -99,6 +99,7 @@ impl FirewallT for Firewall {
                 tunnel,
                 allow_lan,
                 relay_client,
+                ..
             } => {
                 let cfg = &WinFwSettings::new(allow_lan);
                 self.set_connected_state(&peer_endpoint, &cfg, &tunnel, &relay_client)
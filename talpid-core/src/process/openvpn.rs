@@ -29,7 +29,6 @@ static BASE_ARGUMENTS: &[&[&str]] = &[
     &["--rcvbuf", "1048576"],
     &["--sndbuf", "1048576"],
     &["--fast-io"],
-    &["--cipher", "AES-256-CBC"],
     &["--tls-version-min", "1.2"],
     &["--verb", "3"],
     #[cfg(windows)]
@@ -44,6 +43,9 @@ static BASE_ARGUMENTS: &[&[&str]] = &[
     ],
 ];
 
+/// Data channel cipher used unless another one is set in the tunnel options.
+const DEFAULT_CIPHER: &str = "AES-256-CBC";
+
 static ALLOWED_TLS1_2_CIPHERS: &[&str] = &[
     "TLS-DHE-RSA-WITH-AES-256-GCM-SHA384",
     "TLS-DHE-RSA-WITH-AES-256-CBC-SHA",
@@ -231,6 +233,8 @@ impl OpenVpnCommand {
             args.push(OsString::from(mssfix.to_string()));
         }
 
+        args.extend(self.cipher_arguments().iter().map(OsString::from));
+
         if !self.enable_ipv6 {
             args.push(OsString::from("--pull-filter"));
             args.push(OsString::from("ignore"));
@@ -262,6 +266,25 @@ impl OpenVpnCommand {
         args
     }
 
+    /// Pins the data channel cipher. A selected cipher is also the only one offered during cipher
+    /// negotiation, so that the server can't push a different one.
+    fn cipher_arguments(&self) -> Vec<String> {
+        let mut args = vec![];
+        match self.tunnel_options.cipher {
+            Some(ref cipher) => {
+                args.push("--cipher".to_owned());
+                args.push(cipher.clone());
+                args.push("--ncp-ciphers".to_owned());
+                args.push(cipher.clone());
+            }
+            None => {
+                args.push("--cipher".to_owned());
+                args.push(DEFAULT_CIPHER.to_owned());
+            }
+        }
+        args
+    }
+
     fn tls_cipher_arguments() -> Vec<String> {
         let mut args = vec![];
         args.push("--tls-cipher".to_owned());
@@ -420,7 +443,7 @@ impl StoppableProcess for OpenVpnProcHandle {
 mod tests {
     use super::OpenVpnCommand;
     use std::{ffi::OsString, net::Ipv4Addr};
-    use talpid_types::net::{Endpoint, TransportProtocol};
+    use talpid_types::net::{openvpn::TunnelOptions, Endpoint, TransportProtocol};
 
     #[test]
     fn passes_one_remote() {
@@ -447,4 +470,22 @@ mod tests {
         assert!(testee_args.contains(&OsString::from("123")));
         assert!(testee_args.contains(&OsString::from("cde")));
     }
+
+    #[test]
+    fn passes_selected_cipher() {
+        let default_args = OpenVpnCommand::new("").get_arguments();
+        assert!(default_args.contains(&OsString::from("AES-256-CBC")));
+        assert!(!default_args.contains(&OsString::from("--ncp-ciphers")));
+
+        let tunnel_options = TunnelOptions {
+            cipher: Some("AES-256-GCM".to_owned()),
+            ..TunnelOptions::default()
+        };
+        let testee_args = OpenVpnCommand::new("")
+            .tunnel_options(&tunnel_options)
+            .get_arguments();
+        assert!(!testee_args.contains(&OsString::from("AES-256-CBC")));
+        assert!(testee_args.contains(&OsString::from("--ncp-ciphers")));
+        assert!(testee_args.contains(&OsString::from("AES-256-GCM")));
+    }
 }
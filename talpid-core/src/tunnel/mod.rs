@@ -343,6 +343,15 @@ impl CloseHandle {
             }
         }
     }
+
+    /// Returns the traffic statistics of the tunnel. Only WireGuard tunnels report statistics.
+    pub fn get_stats(&self) -> Option<wireguard::Stats> {
+        match self {
+            #[cfg(not(target_os = "android"))]
+            CloseHandle::OpenVpn(_) => None,
+            CloseHandle::Wireguard(handle) => handle.get_stats(),
+        }
+    }
 }
 
 enum InternalTunnelMonitor {
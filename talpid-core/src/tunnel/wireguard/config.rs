@@ -19,7 +19,8 @@ pub struct Config {
     pub mtu: u16,
 }
 
-const DEFAULT_MTU: u16 = 1380;
+/// MTU used when none is configured.
+pub const DEFAULT_MTU: u16 = 1380;
 
 /// Configuration errors
 #[derive(err_derive::Error, Debug)]
@@ -375,6 +375,7 @@ mod test {
             Stats {
                 rx_bytes: 1,
                 tx_bytes: 0,
+                last_handshake_time: None,
             },
         );
 
@@ -398,6 +399,7 @@ mod test {
             Stats {
                 rx_bytes: 1,
                 tx_bytes: 0,
+                last_handshake_time: None,
             },
         );
 
@@ -420,6 +422,7 @@ mod test {
             Stats {
                 rx_bytes: 1,
                 tx_bytes: 0,
+                last_handshake_time: None,
             },
         );
 
@@ -429,6 +432,7 @@ mod test {
             Stats {
                 rx_bytes: 1,
                 tx_bytes: 1,
+                last_handshake_time: None,
             },
         );
 
@@ -6,7 +6,7 @@ use crate::routing::{self, RequiredRoute};
 use std::{
     collections::HashSet,
     path::Path,
-    sync::{mpsc, Arc, Mutex},
+    sync::{mpsc, Arc, Mutex, Weak},
 };
 use talpid_types::ErrorExt;
 
@@ -17,6 +17,7 @@ mod logging;
 mod stats;
 mod wireguard_go;
 
+pub use self::stats::Stats;
 use self::wireguard_go::WgGoTunnel;
 
 type Result<T> = std::result::Result<T, Error>;
@@ -129,6 +130,7 @@ impl WireguardMonitor {
     pub fn close_handle(&self) -> CloseHandle {
         CloseHandle {
             chan: self.close_msg_sender.clone(),
+            tunnel: Arc::downgrade(&self.tunnel),
         }
     }
 
@@ -213,9 +215,10 @@ enum CloseMsg {
 }
 
 /// Close handle for a WireGuard tunnel.
-#[derive(Clone, Debug)]
+#[derive(Clone)]
 pub struct CloseHandle {
     chan: mpsc::Sender<CloseMsg>,
+    tunnel: Weak<Mutex<Option<Box<dyn Tunnel>>>>,
 }
 
 impl CloseHandle {
@@ -225,6 +228,22 @@ impl CloseHandle {
             log::trace!("Failed to send close message to wireguard tunnel - {}", e);
         }
     }
+
+    /// Returns the traffic statistics of the tunnel, or `None` if it has already been stopped.
+    pub fn get_stats(&self) -> Option<Stats> {
+        let tunnel = self.tunnel.upgrade()?;
+        let tunnel = tunnel.lock().ok()?;
+        match tunnel.as_ref()?.get_tunnel_stats() {
+            Ok(stats) => Some(stats),
+            Err(error) => {
+                log::error!(
+                    "{}",
+                    error.display_chain_with_msg("Failed to read tunnel stats")
+                );
+                None
+            }
+        }
+    }
 }
 
 pub(crate) trait Tunnel: Send {
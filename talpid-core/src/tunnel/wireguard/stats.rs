@@ -1,3 +1,5 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
 #[derive(err_derive::Error, Debug, PartialEq)]
 pub enum Error {
     #[error(display = "Failed to parse integer from string \"_0\"")]
@@ -10,14 +12,21 @@ pub enum Error {
 /// Contains bytes sent and received through a tunnel
 #[derive(Default, Debug, PartialEq, Clone, Copy)]
 pub struct Stats {
+    /// Bytes sent through the tunnel.
     pub tx_bytes: u64,
+    /// Bytes received through the tunnel.
     pub rx_bytes: u64,
+    /// Time of the last completed handshake, if any.
+    pub last_handshake_time: Option<SystemTime>,
 }
 
 impl Stats {
+    /// Parses the statistics out of a WireGuard userspace configuration string.
     pub fn parse_config_str(config: &str) -> Result<Self, Error> {
         let mut tx_bytes = None;
         let mut rx_bytes = None;
+        let mut handshake_sec: u64 = 0;
+        let mut handshake_nsec: u32 = 0;
 
         // parts iterates over keys and values
         let parts = config.split('\n').filter_map(|line| {
@@ -45,13 +54,36 @@ impl Stats {
                             .map_err(|err| Error::IntParseError(value.to_string(), err))?,
                     );
                 }
+                "last_handshake_time_sec" => {
+                    handshake_sec = value
+                        .trim()
+                        .parse()
+                        .map_err(|err| Error::IntParseError(value.to_string(), err))?;
+                }
+                "last_handshake_time_nsec" => {
+                    handshake_nsec = value
+                        .trim()
+                        .parse()
+                        .map_err(|err| Error::IntParseError(value.to_string(), err))?;
+                }
 
                 _ => continue,
             }
         }
 
+        // A zero timestamp means that no handshake has been completed yet.
+        let last_handshake_time = if handshake_sec == 0 && handshake_nsec == 0 {
+            None
+        } else {
+            Some(UNIX_EPOCH + Duration::new(handshake_sec, handshake_nsec))
+        };
+
         match (tx_bytes, rx_bytes) {
-            (Some(tx_bytes), Some(rx_bytes)) => Ok(Self { tx_bytes, rx_bytes }),
+            (Some(tx_bytes), Some(rx_bytes)) => Ok(Self {
+                tx_bytes,
+                rx_bytes,
+                last_handshake_time,
+            }),
             _ => Err(Error::KeyNotFoundError),
         }
     }
@@ -61,6 +93,7 @@ impl Stats {
 #[cfg(test)]
 mod test {
     use super::{Error, Stats};
+    use std::time::{Duration, UNIX_EPOCH};
 
     #[test]
     fn test_parsing() {
@@ -69,6 +102,10 @@ mod test {
         let stats = Stats::parse_config_str(valid_input).expect("Failed to parse valid input");
         assert_eq!(stats.rx_bytes, 2396);
         assert_eq!(stats.tx_bytes, 2740);
+        assert_eq!(
+            stats.last_handshake_time,
+            Some(UNIX_EPOCH + Duration::new(1578420649, 369416131))
+        );
     }
 
     #[test]
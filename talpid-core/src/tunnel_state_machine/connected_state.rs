@@ -10,6 +10,7 @@ use futures01::{
     sync::{mpsc, oneshot},
     Async, Future, Stream,
 };
+use std::net::IpAddr;
 use talpid_types::{
     net::{Endpoint, TunnelParameters},
     tunnel::ErrorStateCause,
@@ -58,6 +59,7 @@ impl ConnectedState {
         let policy = FirewallPolicy::Connected {
             peer_endpoint,
             tunnel: self.metadata.clone(),
+            dns_servers: self.get_dns_servers(shared_values),
             allow_lan: shared_values.allow_lan,
             #[cfg(windows)]
             relay_client: TunnelMonitor::get_relay_client(
@@ -78,11 +80,25 @@ impl ConnectedState {
         }
     }
 
-    fn set_dns(&self, shared_values: &mut SharedTunnelStateValues) -> Result<(), BoxedError> {
+    /// Returns the custom DNS servers if any are set, or the tunnel gateways otherwise.
+    fn get_dns_servers(&self, shared_values: &SharedTunnelStateValues) -> Vec<IpAddr> {
+        if let Some(ref custom_dns) = shared_values.custom_dns {
+            if cfg!(windows) {
+                log::warn!("Custom DNS servers are not supported on Windows, using the gateway");
+            } else {
+                return custom_dns.clone();
+            }
+        }
+
         let mut dns_ips = vec![self.metadata.ipv4_gateway.into()];
         if let Some(ipv6_gateway) = self.metadata.ipv6_gateway {
             dns_ips.push(ipv6_gateway.into());
         };
+        dns_ips
+    }
+
+    fn set_dns(&self, shared_values: &mut SharedTunnelStateValues) -> Result<(), BoxedError> {
+        let dns_ips = self.get_dns_servers(shared_values);
 
         shared_values
             .dns_monitor
@@ -159,8 +175,12 @@ impl ConnectedState {
                 shared_values.block_when_disconnected = block_when_disconnected;
                 SameState(self)
             }
+            Ok(TunnelCommand::CustomDns(servers)) => {
+                shared_values.custom_dns = servers;
+                SameState(self)
+            }
             Ok(TunnelCommand::IsOffline(is_offline)) => {
-                shared_values.is_offline = is_offline;
+                shared_values.set_is_offline(is_offline);
                 if is_offline {
                     self.disconnect(
                         shared_values,
@@ -179,6 +199,12 @@ impl ConnectedState {
             Ok(TunnelCommand::Block(reason)) => {
                 self.disconnect(shared_values, AfterDisconnect::Block(reason))
             }
+            Ok(TunnelCommand::GetTunnelStats(tx)) => {
+                if let Some(stats) = self.close_handle.as_ref().and_then(|h| h.get_stats()) {
+                    let _ = tx.send(stats);
+                }
+                SameState(self)
+            }
         }
     }
 
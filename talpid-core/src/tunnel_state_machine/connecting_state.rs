@@ -226,8 +226,12 @@ impl ConnectingState {
                 shared_values.block_when_disconnected = block_when_disconnected;
                 SameState(self)
             }
+            Ok(TunnelCommand::CustomDns(servers)) => {
+                shared_values.custom_dns = servers;
+                SameState(self)
+            }
             Ok(TunnelCommand::IsOffline(is_offline)) => {
-                shared_values.is_offline = is_offline;
+                shared_values.set_is_offline(is_offline);
                 if is_offline {
                     self.disconnect(
                         shared_values,
@@ -246,6 +250,7 @@ impl ConnectingState {
             Ok(TunnelCommand::Block(reason)) => {
                 self.disconnect(shared_values, AfterDisconnect::Block(reason))
             }
+            Ok(TunnelCommand::GetTunnelStats(_)) => SameState(self),
         }
     }
 
@@ -39,8 +39,12 @@ impl DisconnectingState {
                     shared_values.block_when_disconnected = block_when_disconnected;
                     AfterDisconnect::Nothing
                 }
+                Ok(TunnelCommand::CustomDns(servers)) => {
+                    shared_values.custom_dns = servers;
+                    AfterDisconnect::Nothing
+                }
                 Ok(TunnelCommand::IsOffline(is_offline)) => {
-                    shared_values.is_offline = is_offline;
+                    shared_values.set_is_offline(is_offline);
                     AfterDisconnect::Nothing
                 }
                 Ok(TunnelCommand::Connect) => AfterDisconnect::Reconnect(0),
@@ -56,8 +60,12 @@ impl DisconnectingState {
                     shared_values.block_when_disconnected = block_when_disconnected;
                     AfterDisconnect::Block(reason)
                 }
+                Ok(TunnelCommand::CustomDns(servers)) => {
+                    shared_values.custom_dns = servers;
+                    AfterDisconnect::Block(reason)
+                }
                 Ok(TunnelCommand::IsOffline(is_offline)) => {
-                    shared_values.is_offline = is_offline;
+                    shared_values.set_is_offline(is_offline);
                     if !is_offline && reason == ErrorStateCause::IsOffline {
                         AfterDisconnect::Reconnect(0)
                     } else {
@@ -67,7 +75,7 @@ impl DisconnectingState {
                 Ok(TunnelCommand::Connect) => AfterDisconnect::Reconnect(0),
                 Ok(TunnelCommand::Disconnect) => AfterDisconnect::Nothing,
                 Ok(TunnelCommand::Block(new_reason)) => AfterDisconnect::Block(new_reason),
-                Err(_) => AfterDisconnect::Block(reason),
+                Ok(TunnelCommand::GetTunnelStats(_)) | Err(_) => AfterDisconnect::Block(reason),
             },
             AfterDisconnect::Reconnect(retry_attempt) => match event {
                 Ok(TunnelCommand::AllowLan(allow_lan)) => {
@@ -78,8 +86,12 @@ impl DisconnectingState {
                     shared_values.block_when_disconnected = block_when_disconnected;
                     AfterDisconnect::Reconnect(retry_attempt)
                 }
+                Ok(TunnelCommand::CustomDns(servers)) => {
+                    shared_values.custom_dns = servers;
+                    AfterDisconnect::Reconnect(retry_attempt)
+                }
                 Ok(TunnelCommand::IsOffline(is_offline)) => {
-                    shared_values.is_offline = is_offline;
+                    shared_values.set_is_offline(is_offline);
                     if is_offline {
                         AfterDisconnect::Block(ErrorStateCause::IsOffline)
                     } else {
@@ -89,6 +101,7 @@ impl DisconnectingState {
                 Ok(TunnelCommand::Connect) => AfterDisconnect::Reconnect(retry_attempt),
                 Ok(TunnelCommand::Disconnect) | Err(_) => AfterDisconnect::Nothing,
                 Ok(TunnelCommand::Block(reason)) => AfterDisconnect::Block(reason),
+                Ok(TunnelCommand::GetTunnelStats(_)) => AfterDisconnect::Reconnect(retry_attempt),
             },
         };
 
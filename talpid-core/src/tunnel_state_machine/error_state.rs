@@ -92,8 +92,12 @@ impl TunnelState for ErrorState {
                 shared_values.block_when_disconnected = block_when_disconnected;
                 SameState(self)
             }
+            Ok(TunnelCommand::CustomDns(servers)) => {
+                shared_values.custom_dns = servers;
+                SameState(self)
+            }
             Ok(TunnelCommand::IsOffline(is_offline)) => {
-                shared_values.is_offline = is_offline;
+                shared_values.set_is_offline(is_offline);
                 if !is_offline && self.block_reason == ErrorStateCause::IsOffline {
                     NewState(ConnectingState::enter(shared_values, 0))
                 } else {
@@ -105,6 +109,7 @@ impl TunnelState for ErrorState {
                 NewState(DisconnectedState::enter(shared_values, ()))
             }
             Ok(TunnelCommand::Block(reason)) => NewState(ErrorState::enter(shared_values, reason)),
+            Ok(TunnelCommand::GetTunnelStats(_)) => SameState(self),
         }
     }
 }
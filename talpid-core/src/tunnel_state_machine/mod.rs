@@ -20,7 +20,7 @@ use crate::{
     mpsc::Sender,
     offline,
     routing::RouteManager,
-    tunnel::tun_provider::TunProvider,
+    tunnel::{tun_provider::TunProvider, wireguard::Stats},
 };
 
 use futures01::{
@@ -30,6 +30,7 @@ use futures01::{
 use std::{
     collections::HashSet,
     io,
+    net::IpAddr,
     path::{Path, PathBuf},
     sync::{mpsc as sync_mpsc, Arc},
     thread,
@@ -77,14 +78,19 @@ pub enum Error {
 }
 
 /// Spawn the tunnel state machine thread, returning a channel for sending tunnel commands.
+///
+/// The `offline_state_listener` is told whether the computer is offline when the state machine
+/// starts, and again every time that changes.
 pub fn spawn(
     allow_lan: bool,
     block_when_disconnected: bool,
+    custom_dns: Option<Vec<IpAddr>>,
     tunnel_parameters_generator: impl TunnelParametersGenerator,
     log_dir: Option<PathBuf>,
     resource_dir: PathBuf,
     cache_dir: impl AsRef<Path> + Send + 'static,
     state_change_listener: impl Sender<TunnelStateTransition> + Send + 'static,
+    offline_state_listener: impl Sender<bool> + Send + 'static,
     shutdown_tx: oneshot::Sender<()>,
     #[cfg(target_os = "android")] android_context: AndroidContext,
 ) -> Result<Arc<mpsc::UnboundedSender<TunnelCommand>>, Error> {
@@ -110,6 +116,7 @@ pub fn spawn(
         match create_event_loop(
             allow_lan,
             block_when_disconnected,
+            custom_dns,
             is_offline,
             tunnel_parameters_generator,
             tun_provider,
@@ -118,6 +125,7 @@ pub fn spawn(
             cache_dir,
             command_rx,
             state_change_listener,
+            offline_state_listener,
             shutdown_tx,
         ) {
             Ok((mut reactor, event_loop)) => {
@@ -150,6 +158,7 @@ pub fn spawn(
 fn create_event_loop(
     allow_lan: bool,
     block_when_disconnected: bool,
+    custom_dns: Option<Vec<IpAddr>>,
     is_offline: bool,
     tunnel_parameters_generator: impl TunnelParametersGenerator,
     tun_provider: TunProvider,
@@ -158,12 +167,14 @@ fn create_event_loop(
     cache_dir: impl AsRef<Path>,
     commands: mpsc::UnboundedReceiver<TunnelCommand>,
     state_change_listener: impl Sender<TunnelStateTransition>,
+    offline_state_listener: impl Sender<bool> + Send + 'static,
     shutdown_tx: oneshot::Sender<()>,
 ) -> Result<(Core, impl Future<Item = (), Error = Error>), Error> {
     let reactor = Core::new().map_err(Error::ReactorError)?;
     let state_machine = TunnelStateMachine::new(
         allow_lan,
         block_when_disconnected,
+        custom_dns,
         is_offline,
         tunnel_parameters_generator,
         tun_provider,
@@ -171,6 +182,7 @@ fn create_event_loop(
         resource_dir,
         cache_dir,
         commands,
+        offline_state_listener,
     )?;
 
     let future = state_machine
@@ -195,6 +207,9 @@ pub enum TunnelCommand {
     AllowLan(bool),
     /// Enable or disable the block_when_disconnected feature.
     BlockWhenDisconnected(bool),
+    /// Set the DNS servers to use inside the tunnel, or `None` to use the tunnel gateway. Takes
+    /// effect on the next connect.
+    CustomDns(Option<Vec<IpAddr>>),
     /// Notify the state machine of the connectivity of the device.
     IsOffline(bool),
     /// Open tunnel connection.
@@ -203,6 +218,9 @@ pub enum TunnelCommand {
     Disconnect,
     /// Disconnect any open tunnel and block all network access
     Block(ErrorStateCause),
+    /// Request traffic statistics for the tunnel. The sender is dropped unless a tunnel that
+    /// reports statistics is connected.
+    GetTunnelStats(oneshot::Sender<Stats>),
 }
 
 /// Asynchronous handling of the tunnel state machine.
@@ -221,6 +239,7 @@ impl TunnelStateMachine {
     fn new(
         allow_lan: bool,
         block_when_disconnected: bool,
+        custom_dns: Option<Vec<IpAddr>>,
         is_offline: bool,
         tunnel_parameters_generator: impl TunnelParametersGenerator,
         tun_provider: TunProvider,
@@ -228,6 +247,7 @@ impl TunnelStateMachine {
         resource_dir: PathBuf,
         cache_dir: impl AsRef<Path>,
         commands: mpsc::UnboundedReceiver<TunnelCommand>,
+        offline_state_listener: impl Sender<bool> + Send + 'static,
     ) -> Result<Self, Error> {
         let args = if block_when_disconnected {
             FirewallArguments {
@@ -245,13 +265,18 @@ impl TunnelStateMachine {
         let dns_monitor = DnsMonitor::new(cache_dir).map_err(Error::InitDnsMonitorError)?;
         let route_manager =
             RouteManager::new(HashSet::new()).map_err(Error::InitRouteManagerError)?;
+        if offline_state_listener.send(is_offline).is_err() {
+            log::warn!("Failed to send initial offline state to listener");
+        }
         let mut shared_values = SharedTunnelStateValues {
             firewall,
             dns_monitor,
             route_manager,
             allow_lan,
             block_when_disconnected,
+            custom_dns,
             is_offline,
+            offline_state_listener: Box::new(offline_state_listener),
             tunnel_parameters_generator: Box::new(tunnel_parameters_generator),
             tun_provider,
             log_dir,
@@ -337,8 +362,12 @@ struct SharedTunnelStateValues {
     allow_lan: bool,
     /// Should network access be allowed when in the disconnected state.
     block_when_disconnected: bool,
+    /// DNS servers to use inside the tunnel instead of the tunnel gateway.
+    custom_dns: Option<Vec<IpAddr>>,
     /// True when the computer is known to be offline.
     is_offline: bool,
+    /// Told about every change to `is_offline`.
+    offline_state_listener: Box<dyn Sender<bool> + Send>,
     /// The generator of new `TunnelParameter`s
     tunnel_parameters_generator: Box<dyn TunnelParametersGenerator>,
     /// The provider of tunnel devices.
@@ -371,6 +400,15 @@ impl SharedTunnelStateValues {
 
         Ok(())
     }
+
+    pub fn set_is_offline(&mut self, is_offline: bool) {
+        if self.is_offline != is_offline {
+            self.is_offline = is_offline;
+            if self.offline_state_listener.send(is_offline).is_err() {
+                log::warn!("Failed to send offline state change to listener");
+            }
+        }
+    }
 }
 
 /// Asynchronous result of an attempt to progress a state.
@@ -49,6 +49,59 @@ impl IpcServer {
         meta_extractor: E,
         path: &str,
     ) -> Result<Self, Error>
+    where
+        M: Metadata + Default,
+        E: MetaExtractor<M>,
+    {
+        let server = Self::start_server(handler, meta_extractor, path)?;
+        #[cfg(unix)]
+        server.set_permissions(0o766)?;
+        #[cfg(windows)]
+        win::deny_network_access(path).map_err(Error::PermissionsError)?;
+        Ok(server)
+    }
+
+    /// Starts a server on a unix domain socket that only gets the permissions in `mode`, so that
+    /// no connections can be made that `mode` would not allow. The socket is created in a new
+    /// directory next to `path` that only the current user can access, and is only moved to
+    /// `path` once it has its final permissions.
+    #[cfg(unix)]
+    pub fn start_with_metadata_and_mode<M, E>(
+        handler: MetaIoHandler<M>,
+        meta_extractor: E,
+        path: &str,
+        mode: u32,
+    ) -> Result<Self, Error>
+    where
+        M: Metadata + Default,
+        E: MetaExtractor<M>,
+    {
+        use std::{fs, os::unix::fs::DirBuilderExt, path::Path};
+
+        let private_dir = format!("{}.tmp-{}", path, std::process::id());
+        fs::DirBuilder::new()
+            .mode(0o700)
+            .create(&private_dir)
+            .map_err(Error::PermissionsError)?;
+        let private_path = Path::new(&private_dir).join("socket");
+        let result = Self::start_server(handler, meta_extractor, &private_path.to_string_lossy())
+            .and_then(|mut server| {
+                server.set_permissions(mode)?;
+                fs::rename(&private_path, path).map_err(Error::PermissionsError)?;
+                server.path = path.to_owned();
+                Ok(server)
+            });
+        if let Err(error) = fs::remove_dir_all(&private_dir) {
+            log::warn!("Failed to remove {}: {}", private_dir, error);
+        }
+        result
+    }
+
+    fn start_server<M, E>(
+        handler: MetaIoHandler<M>,
+        meta_extractor: E,
+        path: &str,
+    ) -> Result<Self, Error>
     where
         M: Metadata + Default,
         E: MetaExtractor<M>,
@@ -73,15 +126,6 @@ impl IpcServer {
                 path: path.to_owned(),
                 server,
             })?;
-
-        #[cfg(unix)]
-        {
-            use std::{fs, os::unix::fs::PermissionsExt};
-            fs::set_permissions(&path, PermissionsExt::from_mode(0o766))
-                .map_err(Error::PermissionsError)?;
-        }
-        #[cfg(windows)]
-        win::deny_network_access(path).map_err(Error::PermissionsError)?;
         Ok(server)
     }
 
@@ -90,6 +134,15 @@ impl IpcServer {
         &self.path
     }
 
+    /// Replaces the permissions of the unix domain socket this `IpcServer` is listening on.
+    /// Use this to restrict which users can connect, since anyone can by default.
+    #[cfg(unix)]
+    pub fn set_permissions(&self, mode: u32) -> Result<(), Error> {
+        use std::{fs, os::unix::fs::PermissionsExt};
+        fs::set_permissions(&self.path, PermissionsExt::from_mode(mode))
+            .map_err(Error::PermissionsError)
+    }
+
     /// Creates a handle bound to this `IpcServer` that can be used to shut it down.
     pub fn close_handle(&self) -> CloseHandle {
         CloseHandle(self.server.close_handle())
@@ -46,6 +46,42 @@ fn can_call_rpcs_on_server() {
     server.close_handle().close();
 }
 
+#[test]
+fn can_restrict_socket_permissions() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let (server, _rx) = create_server();
+    server.set_permissions(0o600).unwrap();
+
+    let metadata = std::fs::metadata(server.path()).unwrap();
+    assert_eq!(metadata.permissions().mode() & 0o777, 0o600);
+    server.close_handle().close();
+}
+
+#[test]
+fn can_create_socket_with_restricted_permissions() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let (io, rx) = create_handler();
+    let path = create_ipc_path();
+    let server = talpid_ipc::IpcServer::start_with_metadata_and_mode(
+        io.into(),
+        jsonrpc_ipc_server::NoopExtractor,
+        &path,
+        0o600,
+    )
+    .unwrap();
+    assert_eq!(server.path(), path);
+
+    let metadata = std::fs::metadata(server.path()).unwrap();
+    assert_eq!(metadata.permissions().mode() & 0o777, 0o600);
+
+    let client = create_client(path);
+    let _result: () = client.call_method("foo", &[97]).wait().unwrap();
+    assert_eq!(Ok(97), rx.recv_timeout(Duration::from_millis(500)));
+    server.close_handle().close();
+}
+
 #[test]
 #[should_panic]
 fn ipc_client_invalid_url() {
@@ -53,19 +89,26 @@ fn ipc_client_invalid_url() {
 }
 
 fn create_server() -> (talpid_ipc::IpcServer, mpsc::Receiver<i64>) {
+    let (io, rx) = create_handler();
+    let server = talpid_ipc::IpcServer::start(io.into(), &create_ipc_path()).unwrap();
+    (server, rx)
+}
+
+fn create_handler() -> (IoHandler, mpsc::Receiver<i64>) {
     let (tx, rx) = mpsc::channel();
     let rpc = ApiImpl { tx: Mutex::new(tx) };
     let mut io = IoHandler::new();
     io.extend_with(rpc.to_delegate());
+    (io, rx)
+}
 
+fn create_ipc_path() -> String {
     let uuid = uuid::Uuid::new_v4().to_string();
-    let ipc_path = if cfg!(windows) {
+    if cfg!(windows) {
         format!(r"\\.\pipe\ipc-test-{}", uuid)
     } else {
         format!("/tmp/ipc-test-{}", uuid)
-    };
-    let server = talpid_ipc::IpcServer::start(io.into(), &ipc_path).unwrap();
-    (server, rx)
+    }
 }
 
 fn create_client(ipc_path: String) -> jsonrpc_client_core::ClientHandle {
@@ -42,8 +42,15 @@ pub struct TunnelOptions {
     /// Optional argument for openvpn to try and limit TCP packet size,
     /// as discussed [here](https://openvpn.net/archive/openvpn-users/2003-11/msg00154.html)
     pub mssfix: Option<u16>,
+    /// Data channel cipher to use instead of the default. Must be one of [`OPENVPN_CIPHERS`].
+    #[serde(default)]
+    pub cipher: Option<String>,
 }
 
+/// List of data channel ciphers that can be selected for an OpenVPN tunnel.
+/// Cf. [`TunnelOptions::cipher`].
+pub static OPENVPN_CIPHERS: &[&str] = &["AES-256-CBC", "AES-256-GCM"];
+
 /// Proxy server options to be used by `OpenVpnMonitor` when starting a tunnel.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
 #[serde(rename_all = "snake_case")]
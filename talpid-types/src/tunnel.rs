@@ -60,8 +60,29 @@ impl ErrorState {
     pub fn cause(&self) -> &ErrorStateCause {
         &self.cause
     }
+
+    /// How serious the error is. Failing to block traffic is always critical, regardless of
+    /// the cause.
+    pub fn severity(&self) -> ErrorSeverity {
+        if self.is_blocking {
+            self.cause.severity()
+        } else {
+            ErrorSeverity::Critical
+        }
+    }
 }
 
+/// How serious an [`ErrorState`] is, from least to most severe.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorSeverity {
+    /// The error is expected to resolve on its own, e.g. once the device is back online.
+    Minor,
+    /// The error persists until the user or the system changes something.
+    Major,
+    /// Traffic is not being blocked while in the error state.
+    Critical,
+}
 
 /// Reason for the tunnel state machine entering an [`ErrorState`].
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
@@ -86,6 +107,8 @@ pub enum ErrorStateCause {
     IsOffline,
     /// A problem with the TAP adapter has been detected.
     TapAdapterProblem,
+    /// No tunnel could be established within the configured connect timeout.
+    ConnectTimeout,
     /// The Android VPN permission was denied.
     #[cfg(target_os = "android")]
     VpnPermissionDenied,
@@ -111,6 +134,16 @@ pub enum ParameterGenerationError {
     CustomTunnelHostResultionError,
 }
 
+impl ErrorStateCause {
+    /// How serious entering the error state for this reason is, assuming traffic is blocked.
+    pub fn severity(&self) -> ErrorSeverity {
+        match *self {
+            ErrorStateCause::IsOffline | ErrorStateCause::ConnectTimeout => ErrorSeverity::Minor,
+            _ => ErrorSeverity::Major,
+        }
+    }
+}
+
 impl fmt::Display for ErrorStateCause {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         use self::ErrorStateCause::*;
@@ -134,6 +167,7 @@ impl fmt::Display for ErrorStateCause {
             }
             IsOffline => "This device is offline, no tunnels can be established",
             TapAdapterProblem => "A problem with the TAP adapter has been detected",
+            ConnectTimeout => "Failed to establish a tunnel within the connect timeout",
             #[cfg(target_os = "android")]
             VpnPermissionDenied => "The Android VPN permission was denied when creating the tunnel",
         };